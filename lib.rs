@@ -0,0 +1,1884 @@
+// The parts of woro that don't need a window: the word model, deck files, settings,
+// grading and the review schedule. main.rs is the egui app on top.
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// ------------------- Words -------------------
+pub const MAX_LEVEL: u8 = 5;
+
+pub const SECS_PER_DAY: u64 = 86_400;
+
+// Word model
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Word {
+    pub foreign: String,
+    pub translation: String,
+    pub level: u8, // 1..=MAX_LEVEL
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    #[serde(alias = "added_at")]
+    pub created_at: u64, // unix seconds, 0 = unknown (saved before this was tracked)
+    #[serde(default)]
+    pub id: u64, // stable within a deck, 0 = not assigned yet
+    #[serde(default)]
+    pub archived: bool, // mastered and put aside: hidden from the list, game and progress
+    #[serde(default)]
+    pub mastered_streak: u32, // correct answers in a row while at MAX_LEVEL
+    #[serde(default)]
+    pub mastered_since: u64, // unix seconds the current streak began, 0 = none
+    #[serde(default)]
+    pub last_reviewed_at: u64, // unix seconds, 0 = never
+    #[serde(default)]
+    pub decayed_at: u64, // last time decay lowered the level; restarts the grace period
+    #[serde(default)]
+    pub snoozed_until: u64, // kept out of the game until then, 0 = not snoozed
+    #[serde(default)]
+    pub image: String, // file name in the deck's <stem>.media folder, empty = none
+    #[serde(default)]
+    pub speaking_level: u8, // like level, for saying the word; 0 = never spoken
+    #[serde(default)]
+    pub cloze: Option<ClozeCard>,
+    #[serde(default)]
+    pub conjugations: Vec<(String, String)>, // (form, e.g. "yo, pretérito"; conjugated word)
+    #[serde(default)]
+    pub article: String, // grammatical gender as its article ("der", "la"), empty = none
+    #[serde(default)]
+    pub correct_streak: u32, // right answers in a row since the last level change or miss
+    #[serde(default)]
+    pub ease: f32, // SM-2 ease factor, 0 = not reviewed under SM-2 yet
+    #[serde(default)]
+    pub interval_days: f32, // SM-2 interval, 0 = follow the level
+    #[serde(default)]
+    pub due_at: u64, // next due date set by hand, over the schedule; 0 = none. Cleared by a review
+    #[serde(default)]
+    pub pinned_daily: bool, // due every day, whatever the schedule says
+}
+
+// The word blanked out of an example sentence, a second card with its own schedule
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClozeCard {
+    pub sentence: String,
+    pub level: u8,
+    pub last_reviewed_at: u64,
+}
+
+impl Word {
+    pub fn new(foreign: String, translation: String) -> Self {
+        Self {
+            foreign,
+            translation,
+            level: 1,
+            tags: Vec::new(),
+            notes: String::new(),
+            created_at: now_secs(),
+            id: 0,
+            archived: false,
+            mastered_streak: 0,
+            mastered_since: 0,
+            last_reviewed_at: 0,
+            decayed_at: 0,
+            snoozed_until: 0,
+            image: String::new(),
+            speaking_level: 0,
+            cloze: None,
+            conjugations: Vec::new(),
+            article: String::new(),
+            correct_streak: 0,
+            ease: 0.0,
+            interval_days: 0.0,
+            due_at: 0,
+            pinned_daily: false,
+        }
+    }
+}
+
+// How a question was asked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewMode {
+    #[default]
+    Translate,
+    Speak,
+    Handwrite,
+    Cloze,
+    Conjugate,
+    Article,
+    Reading,
+    Typing,
+}
+
+// One answered question, appended to the deck's review log. The log is never rewritten,
+// so it can rebuild levels if the word save is lost or damaged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReviewEvent {
+    pub at: u64,      // unix seconds
+    pub session: u64, // start time of the game session it belongs to
+    pub foreign: String,
+    pub correct: bool,
+    #[serde(default)]
+    pub word_id: u64,
+    #[serde(default)]
+    pub mode: ReviewMode,
+    #[serde(default)]
+    pub response_ms: u64,
+    #[serde(default)]
+    pub level: u8, // level after the answer, 0 = not recorded
+}
+
+// A word as it is right after an answer. Appended to the deck's journal at once, so a
+// crash before the debounced save still keeps the session's progress, whatever the
+// scheduler keeps on the word.
+#[derive(Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub at: u64,
+    pub word: Word,
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn is_snoozed(word: &Word, now: u64) -> bool {
+    word.snoozed_until > now
+}
+
+// When the word editor's overrides make the word due, if it has any: the date set by
+// hand, or the day after the last review for a word pinned to daily review
+pub fn due_override(word: &Word) -> Option<u64> {
+    if word.due_at != 0 {
+        Some(word.due_at)
+    } else if word.pinned_daily {
+        Some((word.last_reviewed_at / SECS_PER_DAY + 1) * SECS_PER_DAY)
+    } else {
+        None
+    }
+}
+
+// Ids are never reused, so a new word always gets one past the highest in the deck
+pub fn next_word_id(words: &[Word]) -> u64 {
+    words.iter().map(|w| w.id).max().unwrap_or(0) + 1
+}
+
+pub fn mark_reviewed(word: &mut Word, now: u64) {
+    word.last_reviewed_at = now;
+    word.due_at = 0;
+}
+
+// ------------------- Settings -------------------
+pub const SETTINGS_FILE: &str = "woro_settings.json";
+
+// App-wide preferences, stored next to the decks in SETTINGS_FILE
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub auto_archive: bool,
+    pub auto_archive_reviews: u32, // correct answers in a row at max level…
+    pub auto_archive_days: u32,    // …spread over at least this many days
+    pub maintenance_sample: u32,
+    pub maintenance_fail_level: u8, // level a forgotten word drops back to
+    pub decay_enabled: bool,
+    pub decay_base_days: f32, // grace period at level 1…
+    pub decay_growth: f32,    // …multiplied by this for every level above
+    pub decay_rate: f32,      // levels lost per grace period without review
+    pub single_instance: bool,
+    pub api_enabled: bool,
+    pub api_port: u16,
+    // Listen on every interface instead of only this computer; requests then need
+    // api_token, as ?token= or an "Authorization: Bearer" header
+    pub api_lan: bool,
+    pub api_token: String,
+    pub import_overwrite_translations: bool,
+    // Records speech and prints what was heard, e.g. a whisper.cpp script; "{lang}" is
+    // replaced with the deck's foreign language ("en" for hands-free voice commands).
+    // Empty = no speaking exercise or voice commands.
+    pub speech_recognition_command: String,
+    // Prints a verb's conjugation table as "form = conjugated" lines; "{word}" is
+    // replaced with the verb. Empty = tables are typed in by hand.
+    pub conjugation_command: String,
+    // Prints the text in a picture; "{image}" is replaced with its path. Empty = no
+    // photo import.
+    pub ocr_command: String,
+    pub preview_new_words: bool, // show a never-seen word with its answer before asking it
+    pub wait_after_answer: bool, // keep the answered word and its result up until Enter
+    // A miss on a word with an example sentence shows the sentence for one more try;
+    // getting it then keeps the level where it was
+    pub second_chance: bool,
+    pub present_word_secs: u32,        // presentation: the word alone…
+    pub present_translation_secs: u32, // …then with its translation
+    // A new word comes back this many questions later after each right answer, and
+    // only levels up once it's through all of them. Empty = no learning steps.
+    pub learning_steps: Vec<u32>,
+    pub answers_to_level_up: u32, // right answers in a row a level takes; one lucky guess isn't enough
+    // Fixed seed for picking words, so a scheduling bug can be replayed; not in the
+    // Settings screen. The --seed flag overrides it.
+    pub seed: Option<u64>,
+    pub storage: StorageKind, // where decks keep their words and review log
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            auto_archive: false,
+            auto_archive_reviews: 3,
+            auto_archive_days: 14,
+            maintenance_sample: 10,
+            maintenance_fail_level: 3,
+            decay_enabled: false,
+            decay_base_days: 2.0,
+            decay_growth: 2.0,
+            decay_rate: 1.0,
+            single_instance: false,
+            api_enabled: false,
+            api_port: 8787,
+            api_lan: false,
+            api_token: String::new(),
+            import_overwrite_translations: false,
+            speech_recognition_command: String::new(),
+            conjugation_command: String::new(),
+            ocr_command: "tesseract {image} stdout".to_string(),
+            preview_new_words: false,
+            wait_after_answer: false,
+            second_chance: false,
+            present_word_secs: 4,
+            present_translation_secs: 3,
+            learning_steps: vec![1, 10],
+            answers_to_level_up: 1,
+            seed: None,
+            storage: StorageKind::Files,
+        }
+    }
+}
+
+impl Settings {
+    // How long a word may go unreviewed at `level` before it starts slipping
+    pub fn grace_days(&self, level: u8) -> f32 {
+        self.decay_base_days * self.decay_growth.powi(level.max(1) as i32 - 1)
+    }
+
+    // A word is due again halfway through its grace period, well before it would decay
+    pub fn review_interval_days(&self, level: u8) -> f32 {
+        self.grace_days(level) / 2.0
+    }
+
+    // No settings file yet gives the defaults
+    pub fn load() -> Result<Self, String> {
+        match fs::read_to_string(SETTINGS_FILE) {
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| format!("Error parsing {}: {}", SETTINGS_FILE, e)),
+            Err(_e) => Ok(Settings::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(SETTINGS_FILE, json)
+            .map_err(|e| format!("Error saving to {}: {}", SETTINGS_FILE, e))
+    }
+}
+
+// ------------------- Deck files -------------------
+pub fn deck_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+pub fn file_mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+pub const BACKUP_COUNT: usize = 3;
+
+pub const BACKUP_INTERVAL_SECS: u64 = 60 * 60; // at most one new backup an hour
+
+// <stem>.bak1.json is the newest
+pub fn backup_path(deck: &Path, n: usize) -> PathBuf {
+    deck.with_file_name(format!("{}.bak{}.json", deck_name(deck), n))
+}
+
+// Copies the deck about to be overwritten into the backup rotation. Only a deck that
+// still parses is backed up, so a damaged file never pushes out a good backup.
+pub fn rotate_backups(deck: &Path) -> io::Result<()> {
+    let newest = backup_path(deck, 1);
+    let recent = file_mtime(&newest)
+        .and_then(|t| t.elapsed().ok())
+        .is_some_and(|age| age.as_secs() < BACKUP_INTERVAL_SECS);
+    if recent || !deck.exists() || read_deck_file(deck).is_err() {
+        return Ok(());
+    }
+    for n in (1..BACKUP_COUNT).rev() {
+        let from = backup_path(deck, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(deck, n + 1))?;
+        }
+    }
+    fs::copy(deck, &newest)?;
+    Ok(())
+}
+
+pub fn valid_backups(deck: &Path) -> Vec<(PathBuf, usize, u64)> {
+    (1..=BACKUP_COUNT)
+        .map(|n| backup_path(deck, n))
+        .filter_map(|path| {
+            let words = read_deck_file(&path).ok()?;
+            let modified = file_mtime(&path)
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            Some((path, words.len(), modified))
+        })
+        .collect()
+}
+
+// Binary decks start with this, followed by MessagePack. Fields are stored by name
+// (unlike postcard/bincode) so decks saved before a new Word field still load.
+pub const BINARY_DECK_MAGIC: &[u8] = b"WORO-MP1";
+
+pub fn encode_binary_deck(words: &[Word]) -> Result<Vec<u8>, String> {
+    let mut bytes = BINARY_DECK_MAGIC.to_vec();
+    bytes.extend(rmp_serde::to_vec_named(words).map_err(|e| e.to_string())?);
+    Ok(bytes)
+}
+
+// Any deck file woro writes: JSON or binary, either of them possibly gzipped
+pub fn decode_deck(mut bytes: Vec<u8>) -> Result<Vec<Word>, String> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut plain = Vec::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut plain)
+            .map_err(|e| e.to_string())?;
+        bytes = plain;
+    }
+    match bytes.strip_prefix(BINARY_DECK_MAGIC) {
+        Some(packed) => rmp_serde::from_slice(packed).map_err(|e| e.to_string()),
+        None => serde_json::from_slice(&bytes).map_err(|e| e.to_string()),
+    }
+}
+
+pub fn read_deck_file(path: &Path) -> Result<Vec<Word>, String> {
+    decode_deck(fs::read(path).map_err(|e| e.to_string())?)
+}
+
+// Write beside the file and swap it in, so a crash mid-write can't truncate it
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+// ------------------- Storage -------------------
+// Where a deck's words and review log are kept. The desktop app uses deck files or SQLite
+// as Settings picks (see open_storage), and MemoryStorage keeps tests off disk.
+pub trait Storage {
+    fn load(&self) -> Result<Vec<Word>, String>;
+    fn save(&mut self, words: &[Word]) -> Result<(), String>;
+    // The review log is append-only, so recording an answer never rewrites it
+    fn append_event(&mut self, event: &ReviewEvent) -> Result<(), String>;
+    fn events(&self) -> Result<Vec<ReviewEvent>, String>;
+    // Only for moving history between decks, like when one is split
+    fn replace_events(&mut self, events: &[ReviewEvent]) -> Result<(), String>;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageKind {
+    #[default]
+    Files, // JsonFileStorage: the deck file itself
+    Sqlite, // SqliteStorage: <deck>.sqlite next to the deck file
+}
+
+impl StorageKind {
+    pub const ALL: [StorageKind; 2] = [StorageKind::Files, StorageKind::Sqlite];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StorageKind::Files => "Deck file (JSON)",
+            StorageKind::Sqlite => "SQLite database",
+        }
+    }
+}
+
+// The deck at `path` in the backend `kind`. A deck opened as SQLite for the first time
+// starts out with the words and review log of its deck file.
+pub fn open_storage(
+    kind: StorageKind,
+    path: &Path,
+    compress: bool,
+    binary: bool,
+) -> Result<Box<dyn Storage + Send>, String> {
+    let files = JsonFileStorage {
+        path: path.to_path_buf(),
+        compress,
+        binary,
+    };
+    if kind == StorageKind::Files {
+        return Ok(Box::new(files));
+    }
+    let db_path = path.with_file_name(format!("{}.sqlite", deck_name(path)));
+    let migrate = !db_path.exists() && path.exists();
+    let mut db = SqliteStorage::open(&db_path).map_err(|e| e.to_string())?;
+    if migrate {
+        db.save(&files.load()?)?;
+        db.replace_events(&files.events()?)?;
+    }
+    Ok(Box::new(db))
+}
+
+// The deck's words without writing anything: a deck not yet moved to SQLite is read from
+// its deck file instead of being migrated
+pub fn read_words(kind: StorageKind, path: &Path) -> Result<Vec<Word>, String> {
+    let db_path = path.with_file_name(format!("{}.sqlite", deck_name(path)));
+    if kind == StorageKind::Sqlite && db_path.exists() {
+        SqliteStorage::open(&db_path)
+            .map_err(|e| e.to_string())?
+            .load()
+    } else {
+        JsonFileStorage::new(path.to_path_buf()).load()
+    }
+}
+
+// The deck file (see decode_deck) with its review log beside it in <deck>.reviews.jsonl.
+// Every save first rotates the backups.
+pub struct JsonFileStorage {
+    pub path: PathBuf,
+    pub compress: bool, // gzip the deck file
+    pub binary: bool,   // MessagePack instead of pretty JSON
+}
+
+impl JsonFileStorage {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            compress: false,
+            binary: false,
+        }
+    }
+
+    pub fn reviews_path(&self) -> PathBuf {
+        self.path
+            .with_file_name(format!("{}.reviews.jsonl", deck_name(&self.path)))
+    }
+}
+
+impl Storage for JsonFileStorage {
+    // No file yet is an empty deck
+    fn load(&self) -> Result<Vec<Word>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        read_deck_file(&self.path)
+    }
+
+    fn save(&mut self, words: &[Word]) -> Result<(), String> {
+        let encoded = if self.binary {
+            encode_binary_deck(words)?
+        } else {
+            serde_json::to_vec_pretty(words).map_err(|e| e.to_string())?
+        };
+        rotate_backups(&self.path)
+            .map_err(|e| format!("Error backing up {}: {}", self.path.display(), e))?;
+        let bytes = if self.compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(&encoded)
+                .and_then(|()| encoder.finish())
+                .map_err(|e| e.to_string())?
+        } else {
+            encoded
+        };
+        write_atomically(&self.path, &bytes)
+    }
+
+    fn append_event(&mut self, event: &ReviewEvent) -> Result<(), String> {
+        let line = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.reviews_path())
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| e.to_string())
+    }
+
+    // Lines that don't parse are skipped; no log yet is an empty one
+    fn events(&self) -> Result<Vec<ReviewEvent>, String> {
+        match fs::read_to_string(self.reviews_path()) {
+            Ok(data) => Ok(data
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn replace_events(&mut self, events: &[ReviewEvent]) -> Result<(), String> {
+        let mut data = String::new();
+        for event in events {
+            data += &serde_json::to_string(event).map_err(|e| e.to_string())?;
+            data.push('\n');
+        }
+        write_atomically(&self.reviews_path(), data.as_bytes())
+    }
+}
+
+// One SQLite database holding the words in deck order and the review log, each row
+// the same JSON a deck file has
+pub struct SqliteStorage {
+    conn: rusqlite::Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::init(rusqlite::Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> rusqlite::Result<Self> {
+        Self::init(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn init(conn: rusqlite::Connection) -> rusqlite::Result<Self> {
+        // The app and its saver thread each hold a connection to the same file
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS words (position INTEGER PRIMARY KEY, data TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS reviews (at INTEGER NOT NULL, data TEXT NOT NULL);",
+        )?;
+        Ok(Self { conn })
+    }
+
+    fn rows<T: serde::de::DeserializeOwned>(&self, query: &str) -> Result<Vec<T>, String> {
+        let mut statement = self.conn.prepare(query).map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        rows.map(|row| {
+            let data = row.map_err(|e| e.to_string())?;
+            serde_json::from_str(&data).map_err(|e| e.to_string())
+        })
+        .collect()
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> Result<Vec<Word>, String> {
+        self.rows("SELECT data FROM words ORDER BY position")
+    }
+
+    fn save(&mut self, words: &[Word]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM words", [])
+            .map_err(|e| e.to_string())?;
+        for (position, word) in words.iter().enumerate() {
+            let data = serde_json::to_string(word).map_err(|e| e.to_string())?;
+            tx.execute(
+                "INSERT INTO words (position, data) VALUES (?1, ?2)",
+                rusqlite::params![position as i64, data],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    fn append_event(&mut self, event: &ReviewEvent) -> Result<(), String> {
+        let data = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "INSERT INTO reviews (at, data) VALUES (?1, ?2)",
+                rusqlite::params![event.at as i64, data],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn events(&self) -> Result<Vec<ReviewEvent>, String> {
+        self.rows("SELECT data FROM reviews ORDER BY rowid")
+    }
+
+    fn replace_events(&mut self, events: &[ReviewEvent]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM reviews", [])
+            .map_err(|e| e.to_string())?;
+        for event in events {
+            let data = serde_json::to_string(event).map_err(|e| e.to_string())?;
+            tx.execute(
+                "INSERT INTO reviews (at, data) VALUES (?1, ?2)",
+                rusqlite::params![event.at as i64, data],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    pub words: Vec<Word>,
+    pub events: Vec<ReviewEvent>,
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self) -> Result<Vec<Word>, String> {
+        Ok(self.words.clone())
+    }
+
+    fn save(&mut self, words: &[Word]) -> Result<(), String> {
+        self.words = words.to_vec();
+        Ok(())
+    }
+
+    fn append_event(&mut self, event: &ReviewEvent) -> Result<(), String> {
+        self.events.push(event.clone());
+        Ok(())
+    }
+
+    fn events(&self) -> Result<Vec<ReviewEvent>, String> {
+        Ok(self.events.clone())
+    }
+
+    fn replace_events(&mut self, events: &[ReviewEvent]) -> Result<(), String> {
+        self.events = events.to_vec();
+        Ok(())
+    }
+}
+
+// ------------------- TXT format -------------------
+pub fn split_tags(text: &str) -> Vec<String> {
+    text.split([',', ';'])
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// "#tag: chapter3" / "#deck: Spanish A1" lines; other # lines are comments
+pub enum TxtDirective {
+    Tags(Vec<String>),
+    Deck(Option<String>),
+}
+
+pub fn parse_txt_directive(line: &str) -> Option<TxtDirective> {
+    let (key, value) = line.strip_prefix('#')?.split_once(':')?;
+    let value = value.trim();
+    match key.trim().to_lowercase().as_str() {
+        "tag" | "tags" => Some(TxtDirective::Tags(split_tags(value))),
+        "deck" => Some(TxtDirective::Deck(
+            (!value.is_empty()).then(|| value.to_string()),
+        )),
+        _ => None,
+    }
+}
+
+// Separators that mark the boundary explicitly, tried in order
+pub const TXT_SEPARATORS: [&str; 6] = ["\t", " = ", " — ", " – ", " - ", ";"];
+
+// How one TXT line splits into foreign word and translation
+pub enum TxtLine {
+    Pair(String, String),
+    Ambiguous(Vec<String>),
+    Invalid,
+}
+
+// Quoted phrases first ("buenos días" "good morning"), then an explicit separator,
+// then a plain two-word line. Anything longer is left for the user to split.
+pub fn split_txt_line(line: &str) -> TxtLine {
+    if let Some(rest) = line.strip_prefix('"') {
+        if let Some((foreign, rest)) = rest.split_once('"') {
+            let translation = rest.trim().trim_matches('"').trim();
+            return if foreign.trim().is_empty() || translation.is_empty() {
+                TxtLine::Invalid
+            } else {
+                TxtLine::Pair(foreign.trim().to_string(), translation.to_string())
+            };
+        }
+    }
+
+    for separator in TXT_SEPARATORS {
+        if let Some((foreign, translation)) = line.split_once(separator) {
+            let (foreign, translation) = (foreign.trim(), translation.trim());
+            if !foreign.is_empty() && !translation.is_empty() {
+                return TxtLine::Pair(foreign.to_string(), translation.to_string());
+            }
+        }
+    }
+
+    let tokens: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+    match tokens.len() {
+        0 | 1 => TxtLine::Invalid,
+        2 => TxtLine::Pair(tokens[0].clone(), tokens[1].clone()),
+        _ => TxtLine::Ambiguous(tokens),
+    }
+}
+
+// The words of a TXT list that read unambiguously, tagged by the #tag: directives in
+// force; lines needing a split point and #deck: directives are left out
+pub fn txt_words(content: &str) -> Vec<Word> {
+    let mut tags = Vec::new();
+    let mut words = Vec::new();
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if line.starts_with('#') {
+            if let Some(TxtDirective::Tags(t)) = parse_txt_directive(line) {
+                tags = t;
+            }
+        } else if let TxtLine::Pair(foreign, translation) = split_txt_line(line) {
+            let mut word = Word::new(foreign, translation);
+            word.tags = tags.clone();
+            words.push(word);
+        }
+    }
+    words
+}
+
+// One word as a TXT line that split_txt_line reads back as the same pair
+pub fn format_txt_line(foreign: &str, translation: &str) -> String {
+    let single = |text: &str| text.split_whitespace().count() == 1;
+    if single(foreign) && single(translation) {
+        format!("{} {}", foreign, translation)
+    } else if !foreign.contains(" = ") && !translation.contains(" = ") && !foreign.contains('\t') {
+        format!("{} = {}", foreign, translation)
+    } else {
+        format!("\"{}\" \"{}\"", foreign.replace('"', "'"), translation)
+    }
+}
+
+pub fn write_txt_deck(path: &Path, name: &str, words: &[Word]) -> io::Result<usize> {
+    fs::write(path, txt_deck(name, words))?;
+    Ok(words.len())
+}
+
+// The deck in the TXT import format. Words are grouped by their tags, each group under
+// a #tag: directive; levels, notes and the archive aren't part of the format.
+pub fn txt_deck(name: &str, words: &[Word]) -> String {
+    let mut sorted: Vec<&Word> = words.iter().collect();
+    sorted.sort_by(|a, b| a.tags.cmp(&b.tags));
+
+    let mut out = format!("# {} — exported from woro\n", name);
+    let mut current_tags: &[String] = &[];
+    for word in &sorted {
+        if word.tags != current_tags {
+            out.push_str(&format!("\n#tag: {}\n", word.tags.join(", ")));
+            current_tags = &word.tags;
+        }
+        out.push_str(&format_txt_line(&word.foreign, &word.translation));
+        out.push('\n');
+    }
+    out
+}
+
+// What happened to one imported word
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImportOutcome {
+    Added,
+    Existing, // already in the deck; level and stats kept
+    Updated,  // already in the deck; translation replaced, level and stats kept
+}
+
+// Where each word of a deck is, by its trimmed lowercase foreign word, and the id the next
+// one gets. Built once per import so a big import doesn't rescan the deck for every word;
+// only import_word may change the deck while it's in use.
+pub struct ImportIndex {
+    positions: HashMap<String, usize>,
+    next_id: u64,
+}
+
+impl ImportIndex {
+    pub fn new(words: &[Word]) -> Self {
+        let mut positions = HashMap::new();
+        for (i, w) in words.iter().enumerate() {
+            positions
+                .entry(w.foreign.trim().to_lowercase())
+                .or_insert(i);
+        }
+        Self {
+            positions,
+            next_id: next_word_id(words),
+        }
+    }
+
+    // Where the word with this foreign side (ignoring case) is, if it's there
+    pub fn position(&self, foreign: &str) -> Option<usize> {
+        self.positions.get(&foreign.trim().to_lowercase()).copied()
+    }
+}
+
+// A word already in `words` (same foreign word, ignoring case) keeps its level and
+// history and only picks up the import's new tags, plus its translation if `overwrite`.
+pub fn import_word(
+    words: &mut Vec<Word>,
+    index: &mut ImportIndex,
+    mut word: Word,
+    overwrite: bool,
+) -> ImportOutcome {
+    let key = word.foreign.trim().to_lowercase();
+    let Some(existing) = index.positions.get(&key).and_then(|&i| words.get_mut(i)) else {
+        word.id = index.next_id;
+        index.next_id += 1;
+        index.positions.insert(key, words.len());
+        words.push(word);
+        return ImportOutcome::Added;
+    };
+
+    for tag in word.tags {
+        if !existing.tags.contains(&tag) {
+            existing.tags.push(tag);
+        }
+    }
+    if existing.notes.is_empty() {
+        existing.notes = word.notes;
+    }
+    if overwrite && !word.translation.is_empty() && existing.translation != word.translation {
+        existing.translation = word.translation;
+        return ImportOutcome::Updated;
+    }
+    ImportOutcome::Existing
+}
+
+// ------------------- Grading -------------------
+// Optimal string alignment distance: Levenshtein plus swapped neighbours ("ie" / "ei")
+pub fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// How alike a word and its translation are, 0..=1, ignoring case, punctuation and a
+// leading "to " or article
+pub fn cognate_similarity(foreign: &str, translation: &str) -> f32 {
+    let simplify = |text: &str| -> Vec<char> {
+        let text = text.trim().to_lowercase();
+        let text = ["to ", "the ", "a ", "an "]
+            .iter()
+            .find_map(|prefix| text.strip_prefix(prefix))
+            .unwrap_or(&text)
+            .to_string();
+        text.chars().filter(|c| c.is_alphanumeric()).collect()
+    };
+    let (a, b) = (simplify(foreign), simplify(translation));
+    let longest = a.len().max(b.len());
+    if a.len().min(b.len()) < 3 {
+        return 0.0; // too short to tell
+    }
+    1.0 - edit_distance(&a, &b) as f32 / longest as f32
+}
+
+// A translation can list several accepted answers: "dog, hound; pooch"
+pub fn translation_variants(translation: &str) -> Vec<&str> {
+    let variants: Vec<&str> = translation
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .collect();
+    if variants.is_empty() {
+        vec![translation.trim()]
+    } else {
+        variants
+    }
+}
+
+// Readings are written Anki-style after the text they belong to: "日本[にほん]語[ご]",
+// or "今日[きょう] は" with a space marking where the text starts.
+
+// (text, reading) pieces; a bracket with nothing before it is left as plain text
+pub fn parse_ruby(text: &str) -> Vec<(&str, Option<&str>)> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        let Some(len) = rest[open..].find(']') else {
+            break;
+        };
+        let close = open + len;
+        let before = &rest[..open];
+        let (plain, base) = match before.rfind(' ') {
+            Some(space) => (&before[..space], &before[space + 1..]),
+            None => ("", before),
+        };
+        if base.is_empty() {
+            pieces.push((&rest[..=close], None));
+        } else {
+            if !plain.is_empty() {
+                pieces.push((plain, None));
+            }
+            pieces.push((base, Some(&rest[open + 1..close])));
+        }
+        rest = &rest[close + 1..];
+    }
+    if !rest.is_empty() {
+        pieces.push((rest, None));
+    }
+    pieces
+}
+
+pub fn has_ruby(text: &str) -> bool {
+    parse_ruby(text)
+        .iter()
+        .any(|(_, reading)| reading.is_some())
+}
+
+// The text without its readings, for grading, speech and stroke order
+pub fn ruby_base(text: &str) -> String {
+    parse_ruby(text).iter().map(|(base, _)| *base).collect()
+}
+
+// The text as it's read: each annotated part replaced by its reading
+pub fn ruby_reading(text: &str) -> String {
+    parse_ruby(text)
+        .iter()
+        .map(|(base, reading)| reading.unwrap_or(base))
+        .collect()
+}
+
+pub fn cloze_regex(foreign: &str) -> Option<regex::Regex> {
+    let foreign = ruby_base(foreign);
+    if foreign.trim().is_empty() {
+        return None;
+    }
+    regex::RegexBuilder::new(&regex::escape(foreign.trim()))
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+pub fn blank_out(sentence: &str, foreign: &str) -> String {
+    match cloze_regex(foreign) {
+        Some(re) => re.replace_all(sentence, "_____").into_owned(),
+        None => sentence.to_string(),
+    }
+}
+
+// The first line of the notes that uses the word, as the sentence for its cloze card
+pub fn cloze_sentence(word: &Word) -> Option<String> {
+    let re = cloze_regex(&word.foreign)?;
+    word.notes
+        .lines()
+        .map(str::trim)
+        .find(|line| re.is_match(line) && line.chars().count() > word.foreign.chars().count())
+        .map(str::to_string)
+}
+
+// The sentence a miss on `word` is retried with; only the first miss gets one, so a miss
+// on the retry counts
+pub fn second_chance_sentence(word: &Word, retried: bool) -> Option<String> {
+    if retried {
+        return None;
+    }
+    cloze_sentence(word)
+}
+
+// "form = conjugated" lines; lines without an "=" are skipped
+pub fn parse_conjugations(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(form, conjugated)| (form.trim().to_string(), conjugated.trim().to_string()))
+        .filter(|(form, conjugated)| !form.is_empty() && !conjugated.is_empty())
+        .collect()
+}
+
+pub fn format_conjugations(conjugations: &[(String, String)]) -> String {
+    conjugations
+        .iter()
+        .map(|(form, conjugated)| format!("{} = {}", form, conjugated))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The numbers in a transcription, so "14/3/2024", "14.03.2024" and "14 3 2024" all match
+pub fn digit_groups(text: &str) -> Vec<u32> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|group| group.parse().ok())
+        .collect()
+}
+
+// One character of a typing answer compared with the word
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CharDiff {
+    Same(char),
+    Missing(char), // in the word, not typed
+    Extra(char),   // typed, not in the word
+}
+
+// Lines the typed text up with the expected one, keeping as many characters as possible
+// (a longest common subsequence), so one slip only marks that character
+pub fn char_diff(expected: &str, typed: &str) -> Vec<CharDiff> {
+    let a: Vec<char> = expected.chars().collect();
+    let b: Vec<char> = typed.chars().collect();
+    // common[i][j]: longest common subsequence of a[i..] and b[j..]
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            diff.push(CharDiff::Same(a[i]));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || common[i][j + 1] >= common[i + 1][j]) {
+            diff.push(CharDiff::Extra(b[j]));
+            j += 1;
+        } else {
+            diff.push(CharDiff::Missing(a[i]));
+            i += 1;
+        }
+    }
+    diff
+}
+
+// ------------------- Markdown -------------------
+// The little Markdown that notes may use: **bold**, *italic* or _italic_, `code`,
+// [links](https://…), "- " and "1. " list items and "# " headings. Anything else,
+// furigana included, stays text.
+
+#[derive(Debug, PartialEq)]
+pub enum Span {
+    Text(String),
+    Bold(String),
+    Italic(String),
+    Code(String),
+    Link(String, String), // text, url
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MarkdownLine {
+    Heading(Vec<Span>),
+    Item(String, Vec<Span>), // marker ("•", "1."), text
+    Text(Vec<Span>),         // empty for a blank line
+}
+
+pub fn markdown_lines(text: &str) -> Vec<MarkdownLine> {
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+            if let Some(rest) = line.strip_prefix('#') {
+                let rest = rest.trim_start_matches('#');
+                if rest.starts_with(' ') {
+                    return MarkdownLine::Heading(markdown_spans(rest.trim()));
+                }
+            }
+            for bullet in ["- ", "* ", "+ "] {
+                if let Some(rest) = line.strip_prefix(bullet) {
+                    return MarkdownLine::Item("•".to_string(), markdown_spans(rest.trim()));
+                }
+            }
+            if let Some((number, rest)) = line.split_once(". ") {
+                if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+                    return MarkdownLine::Item(format!("{}.", number), markdown_spans(rest.trim()));
+                }
+            }
+            MarkdownLine::Text(markdown_spans(line))
+        })
+        .collect()
+}
+
+// Markers only count when they close; a lone * or an underscore inside a word is text.
+// A link's [ can't follow a letter, as that's a reading (日本[にほん]).
+pub fn markdown_spans(line: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut text = String::new();
+    let mut rest = line;
+    let mut previous: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        let after_word = previous.is_some_and(char::is_alphanumeric);
+        let found = if let Some(inner) = rest.strip_prefix("**") {
+            inner
+                .find("**")
+                .filter(|&end| end > 0)
+                .map(|end| (Span::Bold(inner[..end].to_string()), end + 4))
+        } else if c == '*' || (c == '_' && !after_word) || c == '`' {
+            let inner = &rest[1..];
+            inner
+                .find(c)
+                .filter(|&end| end > 0 && !inner.starts_with(' '))
+                .map(|end| {
+                    let content = inner[..end].to_string();
+                    let span = if c == '`' {
+                        Span::Code(content)
+                    } else {
+                        Span::Italic(content)
+                    };
+                    (span, end + 2)
+                })
+        } else if c == '[' && !after_word {
+            rest.find("](").and_then(|middle| {
+                let url_len = rest[middle + 2..].find(')')?;
+                let url = &rest[middle + 2..middle + 2 + url_len];
+                (middle > 1 && !url.is_empty() && !url.contains(char::is_whitespace)).then(|| {
+                    (
+                        Span::Link(rest[1..middle].to_string(), url.to_string()),
+                        middle + 3 + url_len,
+                    )
+                })
+            })
+        } else {
+            None
+        };
+        match found {
+            Some((span, len)) => {
+                if !text.is_empty() {
+                    spans.push(Span::Text(std::mem::take(&mut text)));
+                }
+                spans.push(span);
+                previous = rest[..len].chars().last();
+                rest = &rest[len..];
+            }
+            None => {
+                text.push(c);
+                previous = Some(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if !text.is_empty() {
+        spans.push(Span::Text(text));
+    }
+    spans
+}
+
+// ------------------- Scheduling -------------------
+// Decides when a word comes back and what an answer does to its level. Each deck picks
+// one in its settings; another algorithm implements this and gets a SchedulerKind.
+pub trait Scheduler {
+    // Days after its last review the word is due again
+    fn interval_days(&self, settings: &Settings, word: &Word) -> f32;
+
+    // Updates the level, streak and whatever else the algorithm keeps after an answer
+    fn record(&self, settings: &Settings, word: &mut Word, correct: bool);
+
+    // The next question out of `candidates` (indexes into `words`), any of them by default
+    fn pick(
+        &self,
+        _settings: &Settings,
+        _words: &[Word],
+        candidates: &[usize],
+        _now: u64,
+        rng: &mut StdRng,
+    ) -> usize {
+        candidates[rng.gen_range(0..candidates.len())]
+    }
+
+    // Never-reviewed words are due right away; the rest once their interval passes
+    fn is_due(&self, settings: &Settings, word: &Word, now: u64) -> bool {
+        if word.archived || word.translation.is_empty() || is_snoozed(word, now) {
+            return false;
+        }
+        if let Some(due_at) = due_override(word) {
+            return now >= due_at;
+        }
+        if word.last_reviewed_at == 0 {
+            return true;
+        }
+        let idle_days = now.saturating_sub(word.last_reviewed_at) as f32 / SECS_PER_DAY as f32;
+        idle_days >= self.interval_days(settings, word)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulerKind {
+    #[default]
+    Levels,
+    Sm2,
+    Leitner,
+}
+
+impl SchedulerKind {
+    pub const ALL: [SchedulerKind; 3] = [
+        SchedulerKind::Levels,
+        SchedulerKind::Sm2,
+        SchedulerKind::Leitner,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SchedulerKind::Levels => "Levels",
+            SchedulerKind::Sm2 => "SM-2",
+            SchedulerKind::Leitner => "Leitner boxes",
+        }
+    }
+
+    pub fn scheduler(self) -> &'static dyn Scheduler {
+        match self {
+            SchedulerKind::Levels => &Levels,
+            SchedulerKind::Sm2 => &Sm2,
+            SchedulerKind::Leitner => &Leitner,
+        }
+    }
+}
+
+// woro's own: the review intervals from Settings, one level up after
+// answers_to_level_up right answers in a row and one down for a wrong answer
+pub struct Levels;
+
+impl Scheduler for Levels {
+    fn interval_days(&self, settings: &Settings, word: &Word) -> f32 {
+        settings.review_interval_days(word.level)
+    }
+
+    fn record(&self, settings: &Settings, word: &mut Word, correct: bool) {
+        if correct {
+            word.correct_streak += 1;
+            if word.level < MAX_LEVEL && word.correct_streak >= settings.answers_to_level_up.max(1)
+            {
+                word.level += 1;
+                word.correct_streak = 0;
+            }
+        } else {
+            word.level = word.level.saturating_sub(1).max(1);
+            word.correct_streak = 0;
+        }
+    }
+}
+
+pub const SM2_START_EASE: f32 = 2.5;
+
+// SuperMemo 2, with a right answer graded 4 and a wrong one 1. Each word keeps its own
+// ease and interval; its level follows the interval so the rest of woro can read it.
+// Words without an interval yet (reviewed under another scheduler) start from their level.
+pub struct Sm2;
+
+impl Sm2 {
+    fn level_for(interval_days: f32) -> u8 {
+        match interval_days {
+            d if d < 2.0 => 1,
+            d if d < 7.0 => 2,
+            d if d < 21.0 => 3,
+            d if d < 60.0 => 4,
+            _ => MAX_LEVEL,
+        }
+    }
+}
+
+impl Scheduler for Sm2 {
+    fn interval_days(&self, settings: &Settings, word: &Word) -> f32 {
+        if word.interval_days > 0.0 {
+            word.interval_days
+        } else {
+            settings.review_interval_days(word.level)
+        }
+    }
+
+    fn record(&self, settings: &Settings, word: &mut Word, correct: bool) {
+        let ease = if word.ease > 0.0 {
+            word.ease
+        } else {
+            SM2_START_EASE
+        };
+        let missed = if correct { 1.0 } else { 4.0 }; // 5 - quality
+        word.ease = (ease + 0.1 - missed * (0.08 + missed * 0.02)).max(1.3);
+        if correct {
+            word.interval_days = match word.correct_streak {
+                0 => 1.0,
+                1 => 6.0,
+                _ => self.interval_days(settings, word) * ease,
+            };
+            word.correct_streak += 1;
+        } else {
+            word.interval_days = 1.0;
+            word.correct_streak = 0;
+        }
+        word.level = Self::level_for(word.interval_days);
+    }
+
+    // The most overdue word first, for its share of its own interval
+    fn pick(
+        &self,
+        settings: &Settings,
+        words: &[Word],
+        candidates: &[usize],
+        now: u64,
+        _rng: &mut StdRng,
+    ) -> usize {
+        let overdue = |i: usize| {
+            let word = &words[i];
+            if word.last_reviewed_at == 0 {
+                return 1.0;
+            }
+            let idle_days = now.saturating_sub(word.last_reviewed_at) as f32 / SECS_PER_DAY as f32;
+            idle_days / self.interval_days(settings, word).max(0.01)
+        };
+        candidates
+            .iter()
+            .copied()
+            .max_by(|&a, &b| overdue(a).total_cmp(&overdue(b)))
+            .unwrap_or(candidates[0])
+    }
+}
+
+// Leitner boxes: the level is the box. A right answer moves the word up a box and a wrong
+// one back to the first; box n comes round every 2^(n-1) days, lowest boxes first.
+pub struct Leitner;
+
+impl Scheduler for Leitner {
+    fn interval_days(&self, _settings: &Settings, word: &Word) -> f32 {
+        (1u32 << (word.level.clamp(1, MAX_LEVEL) - 1)) as f32
+    }
+
+    fn record(&self, _settings: &Settings, word: &mut Word, correct: bool) {
+        if correct {
+            word.level = (word.level + 1).min(MAX_LEVEL);
+            word.correct_streak += 1;
+        } else {
+            word.level = 1;
+            word.correct_streak = 0;
+        }
+    }
+
+    fn pick(
+        &self,
+        _settings: &Settings,
+        words: &[Word],
+        candidates: &[usize],
+        _now: u64,
+        rng: &mut StdRng,
+    ) -> usize {
+        let lowest = candidates.iter().map(|&i| words[i].level).min();
+        let first_box: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|&i| Some(words[i].level) == lowest)
+            .collect();
+        first_box[rng.gen_range(0..first_box.len())]
+    }
+}
+
+// Like Scheduler::is_due, by the cloze card's own level and last review on the
+// Levels intervals
+pub fn is_cloze_due(settings: &Settings, word: &Word, now: u64) -> bool {
+    let Some(card) = &word.cloze else {
+        return false;
+    };
+    if word.archived || is_snoozed(word, now) {
+        return false;
+    }
+    if card.last_reviewed_at == 0 {
+        return true;
+    }
+    let idle_days = now.saturating_sub(card.last_reviewed_at) as f32 / SECS_PER_DAY as f32;
+    idle_days >= settings.review_interval_days(card.level.max(1))
+}
+
+pub struct SimulationDay {
+    pub reviews: usize,
+    pub new: usize,
+    pub mastered: usize,
+}
+
+// Same due rule as the game: never-seen words are new, the rest come back when the
+// deck's scheduler says so
+pub fn simulate(
+    settings: &Settings,
+    scheduler: &dyn Scheduler,
+    mut words: Vec<Word>,
+    days: u32,
+    accuracy: f32,
+    new_per_day: usize,
+    rng: &mut StdRng,
+) -> Vec<SimulationDay> {
+    words.retain(|w| !w.archived && !w.translation.is_empty());
+    let start = now_secs();
+    (0..u64::from(days))
+        .map(|day| {
+            let now = start + day * SECS_PER_DAY;
+            let mut result = SimulationDay {
+                reviews: 0,
+                new: 0,
+                mastered: 0,
+            };
+            for w in words.iter_mut() {
+                if w.last_reviewed_at == 0 {
+                    if result.new >= new_per_day {
+                        continue;
+                    }
+                    result.new += 1;
+                } else if !scheduler.is_due(settings, w, now) {
+                    continue;
+                }
+                result.reviews += 1;
+                mark_reviewed(w, now);
+                let correct = rng.gen::<f32>() < accuracy;
+                scheduler.record(settings, w, correct);
+            }
+            result.mastered = words.iter().filter(|w| w.level >= MAX_LEVEL).count();
+            result
+        })
+        .collect()
+}
+
+// ------------------- Share codes -------------------
+// A small deck as one line of text, for a QR code or a message: SHARE_CODE_PREFIX and
+// the deck's TXT export, deflated and base64url-encoded. A QR code holds under 3 KB and
+// is easier to scan off a projector the less it holds.
+pub const SHARE_CODE_PREFIX: &str = "woro:";
+pub const MAX_SHARE_CODE_LEN: usize = 1800;
+
+pub fn share_code(name: &str, words: &[Word]) -> Result<String, String> {
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(txt_deck(name, words).as_bytes())
+        .map_err(|e| e.to_string())?;
+    let deflated = encoder.finish().map_err(|e| e.to_string())?;
+    let code = format!("{}{}", SHARE_CODE_PREFIX, URL_SAFE_NO_PAD.encode(deflated));
+    if code.len() > MAX_SHARE_CODE_LEN {
+        return Err(format!(
+            "{} words are too many for a QR code; share a link to an exported copy instead",
+            words.len()
+        ));
+    }
+    Ok(code)
+}
+
+// The TXT deck a share code holds
+pub fn decode_share_code(code: &str) -> Result<String, String> {
+    let data = code
+        .trim()
+        .strip_prefix(SHARE_CODE_PREFIX)
+        .ok_or("Not a woro share code")?;
+    let deflated = URL_SAFE_NO_PAD.decode(data).map_err(|e| e.to_string())?;
+    let mut txt = String::new();
+    flate2::read::DeflateDecoder::new(&deflated[..])
+        .read_to_string(&mut txt)
+        .map_err(|e| e.to_string())?;
+    Ok(txt)
+}
+
+// ------------------- Insights -------------------
+// Hours with fewer answers than this aren't picked as the best study hour
+pub const MIN_ANSWERS_FOR_BEST_HOUR: usize = 20;
+
+// Patterns in the review log, worked out locally for the Insights screen
+pub struct Insights {
+    pub by_hour: [(usize, usize); 24], // (answers, right) by local hour of day
+    pub sessions: usize,
+    pub average_session_secs: u64, // first to last answer
+    pub average_session_answers: f32,
+    // (answers, right) by the level the word had going into the answer; translation
+    // answers only, as the other exercises keep levels of their own
+    pub by_level: [(usize, usize); MAX_LEVEL as usize],
+}
+
+impl Insights {
+    // `utc_offset` in seconds, to put answers in local hours
+    pub fn from_reviews(events: &[ReviewEvent], utc_offset: i64) -> Self {
+        let mut by_hour = [(0, 0); 24];
+        let mut by_level = [(0, 0); MAX_LEVEL as usize];
+        let mut sessions: HashMap<u64, (u64, u64, usize)> = HashMap::new(); // first, last, answers
+        let mut levels: HashMap<u64, u8> = HashMap::new(); // word id -> level after last answer
+
+        for event in events {
+            let hour = (event.at as i64 + utc_offset).rem_euclid(SECS_PER_DAY as i64) / 3_600;
+            let tally = &mut by_hour[hour as usize];
+            tally.0 += 1;
+            tally.1 += usize::from(event.correct);
+
+            let session = sessions
+                .entry(event.session)
+                .or_insert((event.at, event.at, 0));
+            session.0 = session.0.min(event.at);
+            session.1 = session.1.max(event.at);
+            session.2 += 1;
+
+            if event.mode != ReviewMode::Translate || event.word_id == 0 {
+                continue;
+            }
+            // A word's first logged answer was asked at level 1
+            let before = levels.get(&event.word_id).copied().unwrap_or(1);
+            if event.level != 0 {
+                levels.insert(event.word_id, event.level);
+            }
+            let tally = &mut by_level[before.clamp(1, MAX_LEVEL) as usize - 1];
+            tally.0 += 1;
+            tally.1 += usize::from(event.correct);
+        }
+
+        let count = sessions.len().max(1);
+        Self {
+            by_hour,
+            sessions: sessions.len(),
+            average_session_secs: sessions.values().map(|s| s.1 - s.0).sum::<u64>() / count as u64,
+            average_session_answers: sessions.values().map(|s| s.2).sum::<usize>() as f32
+                / count as f32,
+            by_level,
+        }
+    }
+
+    // The hour with the best accuracy among those with enough answers to tell
+    pub fn best_hour(&self) -> Option<usize> {
+        (0..24)
+            .filter(|&h| self.by_hour[h].0 >= MIN_ANSWERS_FOR_BEST_HOUR)
+            .max_by(|&a, &b| {
+                let rate = |h: usize| self.by_hour[h].1 as f32 / self.by_hour[h].0 as f32;
+                rate(a).total_cmp(&rate(b))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn edit_distance_counts_swaps_once() {
+        assert_eq!(edit_distance(&chars("casa"), &chars("casa")), 0);
+        assert_eq!(edit_distance(&chars("casa"), &chars("cosa")), 1);
+        assert_eq!(edit_distance(&chars("believe"), &chars("beleive")), 1);
+        assert_eq!(edit_distance(&chars(""), &chars("abc")), 3);
+    }
+
+    #[test]
+    fn translation_variants_split_on_commas_and_semicolons() {
+        assert_eq!(
+            translation_variants("house, home; dwelling"),
+            ["house", "home", "dwelling"]
+        );
+        assert_eq!(translation_variants(" , "), [","]);
+    }
+
+    #[test]
+    fn ruby_readings_attach_to_the_text_before_them() {
+        assert_eq!(
+            parse_ruby("日本[にほん]語[ご]"),
+            [("日本", Some("にほん")), ("語", Some("ご"))]
+        );
+        assert_eq!(ruby_base("日本[にほん]語"), "日本語");
+        assert_eq!(ruby_reading("食[た]べる"), "たべる");
+        assert!(!has_ruby("[note] plain"));
+    }
+
+    #[test]
+    fn markdown_marks_up_only_what_closes() {
+        assert_eq!(
+            markdown_spans("**der** Hund, see [dict](https://dict.cc) or snake_case *x"),
+            [
+                Span::Bold("der".into()),
+                Span::Text(" Hund, see ".into()),
+                Span::Link("dict".into(), "https://dict.cc".into()),
+                Span::Text(" or snake_case *x".into()),
+            ]
+        );
+        assert_eq!(
+            markdown_spans("日本[にほん](country)"),
+            [Span::Text("日本[にほん](country)".into())]
+        );
+        assert_eq!(
+            markdown_lines("# Hi\n2. two"),
+            [
+                MarkdownLine::Heading(vec![Span::Text("Hi".into())]),
+                MarkdownLine::Item("2.".into(), vec![Span::Text("two".into())]),
+            ]
+        );
+    }
+
+    #[test]
+    fn char_diff_marks_only_the_slips() {
+        use CharDiff::*;
+        assert_eq!(
+            char_diff("gato", "gatto"),
+            [Same('g'), Same('a'), Same('t'), Extra('t'), Same('o')]
+        );
+        assert_eq!(
+            char_diff("perro", "pero"),
+            [Same('p'), Same('e'), Same('r'), Missing('r'), Same('o')]
+        );
+        assert_eq!(char_diff("a", "b"), [Extra('b'), Missing('a')]);
+    }
+
+    #[test]
+    fn txt_lines_round_trip() {
+        for (foreign, translation) in [
+            ("perro", "dog"),
+            ("buenos días", "good morning"),
+            ("a = b", "equals"),
+        ] {
+            match split_txt_line(&format_txt_line(foreign, translation)) {
+                TxtLine::Pair(f, t) => assert_eq!((f.as_str(), t.as_str()), (foreign, translation)),
+                _ => panic!("{} / {} did not read back as a pair", foreign, translation),
+            }
+        }
+        assert!(matches!(
+            split_txt_line("uno dos tres"),
+            TxtLine::Ambiguous(_)
+        ));
+        assert!(matches!(split_txt_line("solo"), TxtLine::Invalid));
+    }
+
+    #[test]
+    fn txt_words_pick_up_tags_and_skip_ambiguous_lines() {
+        let words = txt_words("perro = dog\n#tags: animals\ngato = cat\nuno dos tres\n");
+        let read: Vec<_> = words
+            .iter()
+            .map(|w| (w.foreign.as_str(), w.tags.len()))
+            .collect();
+        assert_eq!(read, [("perro", 0), ("gato", 1)]);
+    }
+
+    #[test]
+    fn share_codes_decode_to_the_txt_deck() {
+        let words = vec![Word::new("perro".into(), "dog".into())];
+        let code = share_code("animals", &words).unwrap();
+        assert!(code.starts_with(SHARE_CODE_PREFIX));
+        let read = txt_words(&decode_share_code(&code).unwrap());
+        assert_eq!(
+            (read[0].foreign.as_str(), read[0].translation.as_str()),
+            ("perro", "dog")
+        );
+        assert!(decode_share_code("perro = dog").is_err());
+    }
+
+    #[test]
+    fn import_keeps_progress_of_existing_words() {
+        let mut words = vec![Word::new("Perro".into(), "dog".into())];
+        words[0].level = 4;
+        let mut index = ImportIndex::new(&words);
+        let hound = Word::new("perro".into(), "hound".into());
+        let outcome = import_word(&mut words, &mut index, hound, true);
+        assert!(outcome == ImportOutcome::Updated);
+        assert_eq!(
+            (words[0].level, words[0].translation.as_str()),
+            (4, "hound")
+        );
+    }
+
+    #[test]
+    fn imported_words_get_an_id_right_away() {
+        let mut words = vec![Word::new("perro".into(), "dog".into())];
+        words[0].id = 7;
+        let mut index = ImportIndex::new(&words);
+        for (foreign, translation) in [("gato", "cat"), ("pez", "fish"), ("Gato", "kitty")] {
+            let word = Word::new(foreign.into(), translation.into());
+            import_word(&mut words, &mut index, word, false);
+        }
+        assert_eq!(words.iter().map(|w| w.id).collect::<Vec<_>>(), [7, 8, 9]);
+    }
+
+    #[test]
+    fn binary_decks_decode_back() {
+        let words = vec![Word::new("gato".into(), "cat".into())];
+        let decoded = decode_deck(encode_binary_deck(&words).unwrap()).unwrap();
+        assert_eq!(decoded[0].foreign, "gato");
+        let json = serde_json::to_vec(&words).unwrap();
+        assert_eq!(decode_deck(json).unwrap()[0].translation, "cat");
+    }
+
+    fn storage_round_trip(storage: &mut dyn Storage) {
+        let mut words = vec![
+            Word::new("gato".into(), "cat".into()),
+            Word::new("perro".into(), "dog".into()),
+        ];
+        words[1].level = 3;
+        storage.save(&words).unwrap();
+        words.truncate(1);
+        storage.save(&words).unwrap();
+        let event = ReviewEvent {
+            at: 1,
+            session: 1,
+            foreign: "gato".into(),
+            correct: true,
+            word_id: 0,
+            mode: ReviewMode::Translate,
+            response_ms: 800,
+            level: 2,
+        };
+        storage.append_event(&event).unwrap();
+        storage.append_event(&event).unwrap();
+
+        let loaded = storage.load().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].foreign, "gato");
+        let events = storage.events().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[1].level, events[1].response_ms), (2, 800));
+
+        storage.replace_events(&events[..1]).unwrap();
+        assert_eq!(storage.events().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn memory_storage_keeps_what_was_saved() {
+        storage_round_trip(&mut MemoryStorage::default());
+    }
+
+    #[test]
+    fn sqlite_storage_keeps_what_was_saved() {
+        storage_round_trip(&mut SqliteStorage::in_memory().unwrap());
+    }
+
+    #[test]
+    fn cloze_uses_the_first_sentence_with_the_word() {
+        let mut word = Word::new("gato".into(), "cat".into());
+        word.notes = "Gato\nfeline\nEl Gato duerme.".into();
+        assert_eq!(cloze_sentence(&word).as_deref(), Some("El Gato duerme."));
+        assert_eq!(blank_out("El Gato duerme.", "gato"), "El _____ duerme.");
+    }
+
+    #[test]
+    fn two_misses_in_a_row_record_one_wrong_answer() {
+        let mut word = Word::new("gato".into(), "cat".into());
+        word.notes = "El gato duerme.".into();
+        let (mut retried, mut recorded) = (false, 0);
+        for _ in 0..2 {
+            match second_chance_sentence(&word, retried) {
+                Some(_) => retried = true,
+                None => recorded += 1,
+            }
+        }
+        assert_eq!(recorded, 1);
+    }
+
+    #[test]
+    fn words_come_due_after_their_interval() {
+        let settings = Settings::default();
+        let mut word = Word::new("casa".into(), "house".into());
+        let now = 1_000 * SECS_PER_DAY;
+        assert!(Levels.is_due(&settings, &word, now));
+        word.last_reviewed_at = now;
+        assert!(!Levels.is_due(&settings, &word, now));
+        let later = now + (settings.review_interval_days(word.level) * SECS_PER_DAY as f32) as u64;
+        assert!(Levels.is_due(&settings, &word, later + 1));
+        word.archived = true;
+        assert!(!Levels.is_due(&settings, &word, later + 1));
+    }
+
+    #[test]
+    fn overrides_beat_the_schedule_until_the_next_review() {
+        let settings = Settings::default();
+        let mut word = Word::new("examen".into(), "exam".into());
+        let now = 1_000 * SECS_PER_DAY + 3600;
+        mark_reviewed(&mut word, now);
+        word.pinned_daily = true;
+        assert!(!Levels.is_due(&settings, &word, now + 3600));
+        assert!(Levels.is_due(&settings, &word, now + SECS_PER_DAY));
+        word.pinned_daily = false;
+        word.due_at = now + 2 * SECS_PER_DAY;
+        assert!(!Levels.is_due(&settings, &word, now + SECS_PER_DAY));
+        assert!(Levels.is_due(&settings, &word, now + 2 * SECS_PER_DAY));
+        mark_reviewed(&mut word, now + 2 * SECS_PER_DAY);
+        assert_eq!(word.due_at, 0);
+    }
+
+    #[test]
+    fn leitner_sends_misses_back_to_the_first_box() {
+        let settings = Settings::default();
+        let mut word = Word::new("casa".into(), "house".into());
+        Leitner.record(&settings, &mut word, true);
+        Leitner.record(&settings, &mut word, true);
+        assert_eq!(
+            (word.level, Leitner.interval_days(&settings, &word)),
+            (3, 4.0)
+        );
+        Leitner.record(&settings, &mut word, false);
+        assert_eq!(
+            (word.level, Leitner.interval_days(&settings, &word)),
+            (1, 1.0)
+        );
+    }
+
+    #[test]
+    fn sm2_intervals_grow_by_the_ease() {
+        let settings = Settings::default();
+        let mut word = Word::new("casa".into(), "house".into());
+        let intervals: Vec<f32> = (0..4)
+            .map(|_| {
+                Sm2.record(&settings, &mut word, true);
+                word.interval_days
+            })
+            .collect();
+        assert_eq!(intervals, [1.0, 6.0, 15.0, 37.5]);
+        assert_eq!(word.level, 4);
+        Sm2.record(&settings, &mut word, false);
+        assert_eq!(
+            (word.interval_days, word.level, word.correct_streak),
+            (1.0, 1, 0)
+        );
+        assert!(word.ease < SM2_START_EASE);
+    }
+
+    #[test]
+    fn insights_tally_hours_sessions_and_levels() {
+        let answer = |at: u64, session: u64, word_id: u64, correct: bool, level: u8| ReviewEvent {
+            at,
+            session,
+            foreign: String::new(),
+            correct,
+            word_id,
+            mode: ReviewMode::Translate,
+            response_ms: 0,
+            level,
+        };
+        let events = [
+            answer(9 * 3_600, 1, 1, true, 2),
+            answer(9 * 3_600 + 60, 1, 1, false, 1),
+            answer(9 * 3_600 + 300, 1, 2, true, 2),
+            answer(SECS_PER_DAY + 20 * 3_600, 2, 1, true, 2),
+        ];
+        let insights = Insights::from_reviews(&events, 3_600);
+        assert_eq!(insights.by_hour[10], (3, 2));
+        assert_eq!(insights.by_hour[21], (1, 1));
+        assert_eq!((insights.sessions, insights.average_session_secs), (2, 150));
+        assert_eq!(insights.average_session_answers, 2.0);
+        assert_eq!(insights.by_level[0], (3, 3));
+        assert_eq!(insights.by_level[1], (1, 0));
+        assert_eq!(insights.best_hour(), None);
+    }
+
+    #[test]
+    fn simulation_is_reproducible_with_a_seed() {
+        let settings = Settings::default();
+        let words: Vec<Word> = (0..30)
+            .map(|i| Word::new(format!("w{}", i), format!("t{}", i)))
+            .collect();
+        let run = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            simulate(&settings, &Levels, words.clone(), 20, 0.9, 5, &mut rng)
+                .iter()
+                .map(|day| (day.reviews, day.new, day.mastered))
+                .collect::<Vec<_>>()
+        };
+        let first = run(7);
+        assert_eq!(first, run(7));
+        assert_eq!(first.iter().map(|day| day.1).sum::<usize>(), 30);
+        assert!(first.iter().all(|day| day.0 >= day.1));
+    }
+}
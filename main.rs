@@ -1,40 +1,663 @@
+use calamine::{open_workbook, Reader, Xlsx, XlsxError};
 use eframe::egui;
-use rand::Rng;
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+use fs2::FileExt;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::{self, BufRead, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use woro::{
+    blank_out, char_diff, cloze_sentence, cognate_similarity, deck_name, decode_share_code,
+    digit_groups, due_override, edit_distance, file_mtime, format_conjugations, has_ruby,
+    import_word, is_cloze_due, is_snoozed, mark_reviewed, markdown_lines, next_word_id, now_secs,
+    open_storage, parse_conjugations, parse_ruby, parse_txt_directive, read_deck_file, read_words,
+    ruby_base, ruby_reading, second_chance_sentence, share_code, simulate, split_tags,
+    split_txt_line, translation_variants, txt_words, valid_backups, write_atomically,
+    write_txt_deck, CharDiff, ClozeCard, ImportIndex, ImportOutcome, Insights, JournalEntry,
+    MarkdownLine, MemoryStorage, ReviewEvent, ReviewMode, Scheduler, SchedulerKind, Settings, Span,
+    Storage, StorageKind, TxtDirective, TxtLine, Word, MAX_LEVEL, MIN_ANSWERS_FOR_BEST_HOUR,
+    SECS_PER_DAY, SHARE_CODE_PREFIX,
+};
 
 const SAVE_FILE: &str = "words_data.json";
+const STATE_FILE: &str = "woro_state.json";
+const INBOX_FILE: &str = "woro_inbox.json";
+const UI_STATE_KEY: &str = "woro_ui";
+const RECENT_SESSIONS: usize = 3; // how far back "recently failed" looks
+const MAX_THINK_SECS: u64 = 120; // longer gaps between answers count as a break
+const COGNATE_TAG: &str = "cognate";
+const COGNATE_SIMILARITY: f32 = 0.75; // foreign and translation at least this alike
+const SAVE_DEBOUNCE: Duration = Duration::from_secs(3);
+const APP_TITLE: &str = "woro 📚";
+// Localhost port the running instance listens on
+const INSTANCE_PORT: u16 = 47_613;
+// Exchanged on that port, so some other program that happens to hold it isn't taken for woro
+const INSTANCE_HELLO: &str = "woro focus";
+const INSTANCE_REPLY: &str = "woro ok";
+const INSTANCE_TIMEOUT: Duration = Duration::from_secs(2);
 
 // Screens
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 enum Screen {
     AddWords,
     Game,
     End,
+    Stats,
+    Settings,
+    Dictation,
+    Insights,
+    Present,
 }
 
-// Word model
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct Word {
-    foreign: String,
+// Where the game is with the current word. Grading an answer only records it; moving
+// on to another word is a separate step, so the result can stay up in between.
+#[derive(Clone, Copy, PartialEq)]
+enum QuestionState {
+    Asking,        // waiting for an answer
+    SecondChance,  // missed once, asking again with the example sentence shown
+    ShowingResult, // graded, the feedback is about this word
+    Next,          // done with it, pick another
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Theme {
+    System,
+    Dark,
+    Light,
+}
+
+// Density of the whole UI: compact fits more of the word list on screen, large print is
+// bigger and higher contrast
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+enum Layout {
+    #[default]
+    Normal,
+    Compact,
+    LargePrint,
+}
+
+impl Layout {
+    fn row_height(self) -> f32 {
+        match self {
+            Layout::Normal => 20.0,
+            Layout::Compact => 16.0,
+            Layout::LargePrint => 30.0,
+        }
+    }
+
+    fn apply(self, style: &mut egui::Style) {
+        let scale = match self {
+            Layout::Normal => return,
+            Layout::Compact => 0.9,
+            Layout::LargePrint => 1.4,
+        };
+        for font in style.text_styles.values_mut() {
+            font.size *= scale;
+        }
+        let spacing = &mut style.spacing;
+        if self == Layout::Compact {
+            spacing.item_spacing = egui::vec2(6.0, 2.0);
+            spacing.button_padding = egui::vec2(3.0, 0.0);
+            spacing.interact_size.y = 16.0;
+        } else {
+            spacing.item_spacing *= scale;
+            spacing.button_padding *= scale;
+            spacing.interact_size *= scale;
+            let visuals = &mut style.visuals;
+            visuals.override_text_color = Some(if visuals.dark_mode {
+                egui::Color32::WHITE
+            } else {
+                egui::Color32::BLACK
+            });
+            visuals.widgets.noninteractive.bg_stroke.width = 1.5;
+            visuals.widgets.inactive.bg_stroke = egui::Stroke::new(1.5, visuals.text_color());
+        }
+    }
+}
+
+// Accent colors offered next to the color picker in Settings
+const ACCENT_PRESETS: [(&str, [u8; 3]); 4] = [
+    ("Ocean", [40, 125, 200]),
+    ("Forest", [55, 150, 85]),
+    ("Sunset", [225, 115, 45]),
+    ("Grape", [145, 85, 195]),
+];
+
+// Look-and-feel and list filters; small enough to live in eframe's own storage
+// instead of the deck files
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct UiState {
+    theme: Theme,
+    accent: Option<[u8; 3]>, // None = egui's own blue
+    layout: Layout,
+    word_view: WordView,
+    recent_days: u32,
+    stale_days: u32,
+    search_query: String,
+    word_table: bool,
+    word_column_widths: [f32; 2], // word and translation columns of the table
+    hide_readings: bool,
+}
+
+impl Default for UiState {
+    fn default() -> Self {
+        Self {
+            theme: Theme::System,
+            accent: None,
+            layout: Layout::Normal,
+            word_view: WordView::All,
+            recent_days: 7,
+            stale_days: 30,
+            search_query: String::new(),
+            word_table: false,
+            word_column_widths: [140.0, 140.0],
+            hide_readings: false,
+        }
+    }
+}
+
+// Where the user left off, written to STATE_FILE whenever it changes
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct ResumeState {
+    deck: PathBuf,
+    recent_decks: Vec<PathBuf>,
+    screen: Screen,
+    session: SessionKind,
+    session_id: u64,
+    current_word_id: u64,
+    maintenance_queue: Vec<u64>,
+    maintenance_kept: usize,
+    maintenance_returned: usize,
+    #[serde(default)]
+    custom_queue: Vec<u64>,
+    #[serde(default)]
+    custom_right: usize,
+    #[serde(default)]
+    custom_wrong: usize,
+}
+
+// A word caught by clipboard capture, waiting for a translation
+#[derive(Clone, Serialize, Deserialize)]
+struct InboxItem {
+    text: String,
+    #[serde(default)]
     translation: String,
-    level: u8, // 1..=5
+    #[serde(default)]
+    captured_at: u64,
+    #[serde(default)]
+    context: String, // sentence it was found in, becomes the word's notes
+}
+
+// Settings that belong to one deck, kept in <deck>.deck.json
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct DeckSettings {
+    // Regex an answer must match in full, with {} standing for the expected translation,
+    // e.g. "(to )?{}" also accepts "to run" for "run". Empty = exact match only.
+    answer_pattern: String,
+    // Word list for the deck's language, most frequent first (see load_frequency_list)
+    frequency_list: Option<PathBuf>,
+    frequency_order: bool, // introduce new words most frequent first
+    scheduler: SchedulerKind,
+    direction: Direction, // which way the Translate exercise asks
+    // Make Me a Hanzi graphics.txt, for stroke order animations (see load_stroke_data)
+    stroke_data: Option<PathBuf>,
+    compress: bool, // gzip the deck file; loading detects it either way
+    binary: bool,   // MessagePack instead of pretty JSON, for very large decks
+    // Speech voices (language codes like "es", "en"), empty = the system default
+    foreign_language: String,
+    native_language: String,
+    speech_rate: u32,  // words per minute
+    speech_pitch: u32, // 0..=99
+    subscriptions: Vec<Subscription>,
+}
+
+// A TXT word list, file or http(s) URL, that the deck follows: every
+// SUBSCRIPTION_REFRESH_SECS new words are added and changed translations taken over and
+// tagged CHANGED_TAG for review, unless the user edited that translation here
+#[derive(Clone, Serialize, Deserialize)]
+struct Subscription {
+    source: String,
+    #[serde(default)]
+    last_checked: u64, // unix seconds of the last successful fetch, 0 = never
+    #[serde(default)]
+    seen: HashMap<String, String>, // lowercase word -> its translation in the source last time
 }
 
-impl Word {
-    fn new(foreign: String, translation: String) -> Self {
+impl Default for DeckSettings {
+    fn default() -> Self {
         Self {
-            foreign,
-            translation,
-            level: 1,
+            answer_pattern: String::new(),
+            frequency_list: None,
+            frequency_order: false,
+            scheduler: SchedulerKind::Levels,
+            direction: Direction::ForeignToNative,
+            stroke_data: None,
+            compress: false,
+            binary: false,
+            foreign_language: String::new(),
+            native_language: String::new(),
+            speech_rate: DEFAULT_SPEECH_RATE,
+            speech_pitch: DEFAULT_SPEECH_PITCH,
+            subscriptions: Vec::new(),
+        }
+    }
+}
+
+// The settings kept in <deck>.deck.json; a deck without them has the defaults
+fn read_deck_settings(deck: &Path) -> Result<DeckSettings, String> {
+    let path = deck.with_file_name(format!("{}.deck.json", deck_name(deck)));
+    match fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data)
+            .map_err(|e| format!("Error parsing {}: {}", path.display(), e)),
+        Err(_e) => Ok(DeckSettings::default()),
+    }
+}
+
+impl DeckSettings {
+    fn foreign_voice(&self) -> Voice {
+        Voice {
+            language: self.foreign_language.trim().to_string(),
+            rate: self.speech_rate,
+            pitch: self.speech_pitch,
+        }
+    }
+
+    fn native_voice(&self) -> Voice {
+        Voice {
+            language: self.native_language.trim().to_string(),
+            ..self.foreign_voice()
+        }
+    }
+
+    fn answer_regex(&self, expected: &str) -> Result<Option<regex::Regex>, regex::Error> {
+        if self.answer_pattern.trim().is_empty() {
+            return Ok(None);
+        }
+        let pattern = self
+            .answer_pattern
+            .trim()
+            .replace("{}", &regex::escape(expected));
+        regex::RegexBuilder::new(&format!("^(?:{})$", pattern))
+            .case_insensitive(true)
+            .build()
+            .map(Some)
+    }
+
+    // An invalid pattern accepts nothing; load_deck_settings and the Settings screen say so
+    fn accepts(&self, answer: &str, expected: &str) -> bool {
+        self.answer_regex(expected)
+            .ok()
+            .flatten()
+            .is_some_and(|re| re.is_match(answer.trim()))
+    }
+}
+
+// Per-day aggregates, kept in the deck's history file rather than the word save
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct DailySnapshot {
+    day: u64, // days since the unix epoch (UTC)
+    mastered: usize,
+    total: usize,
+    #[serde(default)]
+    reviews: u32,
+    #[serde(default)]
+    correct: u32,
+    #[serde(default)]
+    study_secs: u64,
+}
+
+// Built-in filters for the word list
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum WordView {
+    All,
+    RecentlyAdded,
+    RecentlyFailed,
+    NotReviewed,
+    Cognates,
+    Archived,
+}
+
+// Columns of the word table, for sorting
+#[derive(Clone, Copy, PartialEq)]
+enum WordColumn {
+    Foreign,
+    Translation,
+    Level,
+    Created,
+    LastReviewed,
+}
+
+// Which words a game session draws from
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+enum SessionKind {
+    All,
+    Tagged(String),
+    RecentlyFailed,
+    Maintenance,             // one pass over a sample of mastered/archived words
+    Stale,                   // words untouched for stale_days, oldest first, any level
+    Custom { counts: bool }, // one pass over custom_queue; levels only change if counts
+}
+
+// Right-click menu on a word in the list or table
+#[derive(Clone, Copy)]
+enum WordRowAction {
+    Edit,
+    ResetLevel,
+}
+
+fn word_row_menu(ui: &mut egui::Ui, word: &Word) -> Option<WordRowAction> {
+    let mut action = None;
+    if ui.button("✏ Edit").clicked() {
+        action = Some(WordRowAction::Edit);
+    }
+    if ui
+        .add_enabled(word.level > 1, egui::Button::new("⏮ Reset level to 1"))
+        .on_hover_text("Only this word; its review history stays")
+        .clicked()
+    {
+        action = Some(WordRowAction::ResetLevel);
+    }
+    if action.is_some() {
+        ui.close_menu();
+    }
+    action
+}
+
+// Spreadsheet import: which woro field a file column feeds
+#[derive(Clone, Copy, PartialEq)]
+enum ImportField {
+    Ignore,
+    Foreign,
+    Translation,
+    Tags,
+    Notes,
+}
+
+impl ImportField {
+    const ALL: [ImportField; 5] = [
+        ImportField::Ignore,
+        ImportField::Foreign,
+        ImportField::Translation,
+        ImportField::Tags,
+        ImportField::Notes,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            ImportField::Ignore => "(ignore)",
+            ImportField::Foreign => "Foreign word",
+            ImportField::Translation => "Translation",
+            ImportField::Tags => "Tags",
+            ImportField::Notes => "Notes",
+        }
+    }
+}
+
+// Word being edited in the editor window (tags kept as comma-separated text)
+struct WordEditor {
+    index: usize,
+    foreign: String,
+    translation: String,
+    tags: String,
+    notes: String,
+    snooze: Option<chrono::NaiveDate>,
+    due: Option<chrono::NaiveDate>, // next due date set by hand
+    pinned_daily: bool,
+    image: String,
+    conjugations: String, // "form = conjugated" lines
+    article: String,
+    fetching: Option<Receiver<Result<String, String>>>, // conjugation command running
+}
+
+// Things the Ctrl+K palette can jump to or trigger
+#[derive(Clone)]
+enum PaletteAction {
+    EditWord(usize),
+    AddWords,
+    Game,
+    TaggedSession(String),
+    Maintenance,
+    StaleSession,
+    CustomSession,
+    ImportTxt,
+    ImportSpreadsheet,
+    ImportAnki,
+    ImportKindle,
+    MineSubtitles,
+    ExportTxt,
+    ExportJson,
+    MediaManager,
+    ExportAudio,
+    MakeClozeCards,
+    Dictation,
+    SwitchDeck(PathBuf),
+    OpenDeck,
+    NewDeck,
+    MergeDeck,
+    SplitDeck,
+    SaveNow,
+    PushToAnki,
+    ShareQr,
+    ImportCode,
+    ImportPhoto,
+    Present,
+}
+
+// A TXT line with several words and nothing marking where the translation starts
+struct AmbiguousLine {
+    tokens: Vec<String>,
+    split: usize, // tokens[..split] are the foreign word
+    keep: bool,
+    tags: Vec<String>,    // from the #tag: directive in force
+    deck: Option<String>, // from the #deck: directive in force
+}
+
+// Words cycling full screen for a projector or the corner of a screen: the word, then
+// its translation, timed by Settings, with nothing to answer
+struct Presentation {
+    order: Vec<usize>, // word indices, shuffled
+    position: usize,
+    showing_translation: bool,
+    since: Instant,
+    paused: bool,
+    back_to: Screen,
+}
+
+// The Share as QR window
+struct ShareQr {
+    url: String, // a hosted export to point to instead, for decks too big to fit
+    payload: Result<String, String>, // what the code holds, or why there's nothing to show
+    qr: Option<qrcode::QrCode>,
+}
+
+impl ShareQr {
+    fn set_payload(&mut self, payload: Result<String, String>) {
+        self.qr = payload
+            .as_ref()
+            .ok()
+            .and_then(|p| qrcode::QrCode::new(p.as_bytes()).ok());
+        self.payload = payload;
+    }
+}
+
+// Pending spreadsheet import waiting for the user to map its columns
+struct ColumnMapping {
+    source: String, // file name
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    fields: Vec<ImportField>,
+}
+
+// Parsed TXT or spreadsheet rows, shown for fixing before they go into the deck
+struct ImportPreview {
+    source: String, // file name
+    rows: Vec<PreviewRow>,
+    skipped: usize, // lines or rows that couldn't be read as a word
+    tags: String,   // comma separated, added to every imported row
+}
+
+struct PreviewRow {
+    keep: bool,
+    foreign: String,
+    translation: String,
+    tags: String, // comma separated
+    notes: String,
+    deck: Option<String>, // from a #deck: directive
+}
+
+// Filter choosing which words "Split deck" moves into a new deck file
+struct SplitFilter {
+    tag: Option<String>,
+    min_level: u8,
+    max_level: u8,
+    recent_only: bool,
+    recent_days: u32,
+}
+
+impl SplitFilter {
+    fn matches(&self, word: &Word, now: u64) -> bool {
+        let tag_ok = self.tag.as_ref().is_none_or(|t| word.tags.contains(t));
+        let level_ok = (self.min_level..=self.max_level).contains(&word.level);
+        let date_ok = !self.recent_only
+            || word.created_at >= now.saturating_sub(self.recent_days as u64 * SECS_PER_DAY);
+        tag_ok && level_ok && date_ok
+    }
+}
+
+// A new word being learned: which of Settings::learning_steps is next, and the
+// question count it comes back at
+struct LearningStep {
+    step: usize,
+    due_at: u64,
+}
+
+// One answer in the current game session, for the summary at its end
+struct SessionAnswer {
+    word_id: u64,
+    foreign: String,
+    correct: bool,
+    response_ms: u64,
+    level_before: u8,
+    level_after: u8,
+}
+
+// Custom study session dialog: which words to draw and how to ask them
+struct SessionBuilder {
+    tags: Vec<String>, // any of these; empty = any tag
+    min_level: u8,
+    max_level: u8,
+    recently_failed: bool,
+    limit: u32, // random sample of this many, 0 = all matching
+    mode_index: usize,
+    counts: bool, // answers move levels and the schedule like a normal game
+}
+
+impl SessionBuilder {
+    // Only words the game can ask: a snoozed one would sit in the queue unasked
+    fn matches(&self, word: &Word, now: u64, recently_failed: &HashSet<String>) -> bool {
+        let tag_ok = self.tags.is_empty() || self.tags.iter().any(|t| word.tags.contains(t));
+        let level_ok = (self.min_level..=self.max_level).contains(&word.level);
+        let failed_ok = !self.recently_failed || recently_failed.contains(&word.foreign);
+        let askable = !word.archived && !word.translation.is_empty() && !is_snoozed(word, now);
+        askable && tag_ok && level_ok && failed_ok
+    }
+}
+
+// The deck file didn't parse; these backups of it did
+struct CorruptDeck {
+    error: String,
+    backups: Vec<(PathBuf, usize, u64)>, // path, word count, modified (unix secs), newest first
+}
+
+// What merging another deck into the current one changed
+#[derive(Default)]
+struct MergeReport {
+    source: String,
+    added: usize,
+    duplicates: usize,
+    level_raised: usize,
+    tags_merged: usize,
+    notes_merged: usize,
+    conflicts: Vec<MergeConflict>,
+}
+
+// Same foreign word with a different translation in the other deck; ours is kept
+// until the user picks theirs
+struct MergeConflict {
+    word_id: u64,
+    foreign: String,
+    ours: String,
+    theirs: String,
+}
+
+// A card read from an Anki .apkg, with the scheduling data woro can use
+struct AnkiCard {
+    foreign: String,
+    translation: String,
+    tags: Vec<String>,
+    is_new: bool,
+    interval_days: i64, // negative = seconds in (re)learning
+    ease_permille: i64, // 2500 = 250%
+}
+
+impl AnkiCard {
+    // Long intervals mean Anki already considers the card well known
+    fn seeded_level(&self) -> u8 {
+        if self.is_new || self.interval_days <= 0 {
+            return 1;
+        }
+        let level: u8 = match self.interval_days {
+            1..=2 => 2,
+            3..=9 => 3,
+            10..=29 => 4,
+            _ => MAX_LEVEL,
+        };
+        // Cards the user keeps failing have a low ease; hold them back a level
+        if self.ease_permille > 0 && self.ease_permille < 2000 {
+            level.saturating_sub(1).max(1)
+        } else {
+            level
         }
     }
 }
 
+// Pending .apkg import waiting for the user to confirm
+struct AnkiImport {
+    cards: Vec<AnkiCard>,
+    seed_levels: bool,
+}
+
 // App state
 struct App {
     screen: Screen,
     words: Vec<Word>,
+    settings: Settings,
+    theme: Theme,
+    accent: Option<[u8; 3]>,
+    layout: Layout,
+    theme_dirty: bool, // applied on the next frame, where the system theme is known
+
+    // Decks
+    deck_path: PathBuf,
+    storage: Box<dyn Storage + Send>, // the open deck's, for loading and the review log
+    recent_decks: Vec<PathBuf>,
 
     // Add form
     new_foreign: String,
@@ -44,351 +667,10419 @@ struct App {
     current_word_index: usize,
     user_answer: String,
     feedback_message: String,
+    question: QuestionState,
+    session: SessionKind,
+    session_id: u64,
+    maintenance_queue: Vec<u64>, // word ids still to ask in a maintenance review
+    maintenance_kept: usize,
+    maintenance_returned: usize,
+    custom_queue: Vec<u64>, // word ids still to ask in a custom session
+    custom_right: usize,
+    custom_wrong: usize,
+    session_builder: Option<SessionBuilder>,
+    session_answers: Vec<SessionAnswer>,
+    reset_tags: Vec<String>, // End screen: only reset words with these tags, empty = all
+    reset_level: u8,         // End screen: level "Play Again" puts words back to
+
+    // Review history
+    reviews: Vec<ReviewEvent>,
+    recently_failed: HashSet<String>,
+    history: Vec<DailySnapshot>,
+    history_dirty: bool,
+    last_answer_at: u64,
+    question_shown_at: Instant,
+
+    // Word list
+    search_query: String,
+    word_view: WordView,
+    recent_days: u32,
+    stale_days: u32, // "not reviewed in N days"
+    word_table: bool,
+    word_column_widths: [f32; 2],
+    table_sort: Option<(WordColumn, bool)>, // column, ascending
+    hide_readings: bool,                    // furigana off in the game, for harder practice
+    word_editor: Option<WordEditor>,
+    bulk_level: u8, // level the "set level" bulk action assigns to the shown words
+
+    // Command palette
+    palette_open: bool,
+    palette_query: String,
+    palette_selected: usize,
+
+    // Import
+    column_mapping: Option<ColumnMapping>,
+    import_preview: Option<ImportPreview>,
+    new_subscription: String, // source typed into Settings
+    // background fetch of due subscriptions
+    subscription_results: Option<Receiver<SubscriptionResult>>,
+    next_subscription_check: Option<Instant>, // None = as soon as possible
+    ambiguous_lines: Vec<AmbiguousLine>,
+    anki_import: Option<AnkiImport>,
+    merge_report: Option<MergeReport>,
+    split_filter: Option<SplitFilter>,
+
+    // Last state written to STATE_FILE
+    resume_state: Option<ResumeState>,
+
+    saver: Saver,
+    dirty_since: Option<Instant>, // first unsaved change since the last write
+    title_unsaved: bool,          // whether the window title currently shows "●"
+
+    // Held for as long as the deck is open; without it the deck is read-only
+    deck_lock: Option<fs::File>,
+    read_only: bool,
+
+    // Pings from later launches asking this window to come forward
+    focus_requests: Option<Receiver<()>>,
+
+    // Noticing edits made to the deck by other programs
+    egui_ctx: Option<egui::Context>,
+    deck_watcher: Option<DeckWatcher>,
+    deck_mtime: Option<SystemTime>, // as of our own last load or save
+    deck_touched: bool,             // watcher fired; checked once our own writes are done
+    external_change: bool,
+    corrupt_deck: Option<CorruptDeck>, // saving is held back until the user picks a way out
+
+    // Exercise types, see question_modes()
+    modes: Vec<Box<dyn QuestionMode>>,
+    mode_index: usize,
+    grading_script: Option<GradingScript>, // the deck's <stem>.rhai, if it has one
+    deck_settings: DeckSettings,
+    frequency: HashMap<String, u32>, // lowercase word -> rank, 1 = most frequent
+    strokes: HashMap<char, Vec<Vec<egui::Pos2>>>, // character -> strokes, 0..=1 square
+
+    // Requests from the HTTP API, answered on the UI thread
+    api_requests: Option<Receiver<ApiRequest>>,
+    api_quiz: ApiQuiz,
+
+    anki_push: Option<AnkiPush>,
+    audio_export: Option<Receiver<Result<(usize, PathBuf), String>>>, // while rendering
+    subtitle_mining: Option<SubtitleMining>,
+
+    // Word pictures, decoded the first time they're shown
+    media_cache: MediaCache,
+    media_manager: Option<MediaScan>,
+    share_qr: Option<ShareQr>,
+    code_import: Option<String>, // what's been pasted into the Import from code window
+    code_fetch: Option<Receiver<Result<String, String>>>, // the pasted link, downloading
+    ocr_run: Option<(PathBuf, Receiver<Result<String, String>>)>, // photo being read
+    presentation: Option<Presentation>,
+
+    hands_free: Option<HandsFree>,
+    dictation: Dictation,
+    previewed: HashSet<u64>, // new words already shown this run, see preview_new_word
+    learning: HashMap<u64, LearningStep>, // word id -> its next learning step, this run only
+    rng: StdRng,             // word picking; seeded with Settings::seed or --seed if given
+    questions_asked: u64,    // this run, the clock learning steps count in
+    speech: Option<std::process::Child>, // the 🔊 buttons; a new one cuts the last short
+
+    // Clipboard capture; the inbox outlives decks and restarts
+    inbox: Vec<InboxItem>,
+    capture: Option<ClipboardCapture>,
+
+    toasts: Vec<Toast>, // newest last, see notify
 }
 
 impl Default for App {
     fn default() -> Self {
+        let (settings, settings_error) = match Settings::load() {
+            Ok(settings) => (settings, None),
+            Err(e) => (Settings::default(), Some(e)),
+        };
+        let (inbox, inbox_error) = match load_inbox() {
+            Ok(inbox) => (inbox, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
         let mut app = Self {
             screen: Screen::AddWords,
             words: Vec::new(),
+            settings,
+            theme: Theme::System,
+            accent: None,
+            layout: Layout::Normal,
+            theme_dirty: false,
+            deck_path: PathBuf::from(SAVE_FILE),
+            storage: Box::new(MemoryStorage::default()), // load() opens the deck's own
+            recent_decks: vec![PathBuf::from(SAVE_FILE)],
             new_foreign: String::new(),
             new_translation: String::new(),
             current_word_index: 0,
             user_answer: String::new(),
             feedback_message: String::new(),
+            question: QuestionState::Asking,
+            session: SessionKind::All,
+            session_id: 0,
+            maintenance_queue: Vec::new(),
+            maintenance_kept: 0,
+            maintenance_returned: 0,
+            custom_queue: Vec::new(),
+            custom_right: 0,
+            custom_wrong: 0,
+            session_builder: None,
+            session_answers: Vec::new(),
+            reset_tags: Vec::new(),
+            reset_level: 1,
+            reviews: Vec::new(),
+            recently_failed: HashSet::new(),
+            history: Vec::new(),
+            history_dirty: false,
+            last_answer_at: 0,
+            question_shown_at: Instant::now(),
+            search_query: String::new(),
+            word_view: WordView::All,
+            recent_days: 7,
+            stale_days: 30,
+            word_table: false,
+            word_column_widths: [140.0, 140.0],
+            table_sort: None,
+            hide_readings: false,
+            word_editor: None,
+            bulk_level: MAX_LEVEL,
+            palette_open: false,
+            palette_query: String::new(),
+            palette_selected: 0,
+            column_mapping: None,
+            import_preview: None,
+            new_subscription: String::new(),
+            subscription_results: None,
+            next_subscription_check: None,
+            ambiguous_lines: Vec::new(),
+            anki_import: None,
+            merge_report: None,
+            split_filter: None,
+            resume_state: None,
+            saver: Saver::spawn(),
+            dirty_since: None,
+            title_unsaved: false,
+            deck_lock: None,
+            read_only: false,
+            focus_requests: None,
+            egui_ctx: None,
+            deck_watcher: None,
+            deck_mtime: None,
+            deck_touched: false,
+            external_change: false,
+            corrupt_deck: None,
+            modes: question_modes("", "", Direction::default(), &[]),
+            mode_index: 0,
+            grading_script: None,
+            deck_settings: DeckSettings::default(),
+            frequency: HashMap::new(),
+            strokes: HashMap::new(),
+            api_requests: None,
+            api_quiz: ApiQuiz::default(),
+            anki_push: None,
+            audio_export: None,
+            subtitle_mining: None,
+            media_cache: MediaCache::new(),
+            media_manager: None,
+            share_qr: None,
+            code_import: None,
+            code_fetch: None,
+            ocr_run: None,
+            presentation: None,
+            hands_free: None,
+            dictation: Dictation::default(),
+            previewed: HashSet::new(),
+            learning: HashMap::new(),
+            rng: StdRng::from_entropy(),
+            questions_asked: 0,
+            speech: None,
+            inbox,
+            capture: None,
+            toasts: Vec::new(),
         };
-        app.load();
+        if let Some(seed) = seed_arg().or(app.settings.seed) {
+            app.rng = StdRng::seed_from_u64(seed);
+        }
+        for e in settings_error.into_iter().chain(inbox_error) {
+            app.notify_error(e);
+        }
+        app.resume();
         app
     }
 }
 
+// Daily log files, the last LOG_FILES_KEPT of them, so a failed save or a clash with a
+// sync client can be looked into after the fact
+const LOG_DIR: &str = "woro_logs";
+const LOG_FILES_KEPT: usize = 7;
+
+// Info, warnings and errors by default; --verbose adds debug detail such as every save.
+// Writing happens on a background thread that the returned guard flushes when dropped.
+// Warnings and errors also go to stderr.
+fn init_logging() -> Option<tracing_appender::non_blocking::WorkerGuard> {
+    let verbose = std::env::args().any(|arg| arg == "--verbose");
+    if let Err(e) = fs::create_dir_all(LOG_DIR) {
+        eprintln!("Error creating log directory {}: {}", LOG_DIR, e);
+        return None;
+    }
+    let appender = tracing_appender::rolling::Builder::new()
+        .rotation(tracing_appender::rolling::Rotation::DAILY)
+        .filename_prefix("woro")
+        .filename_suffix("log")
+        .max_log_files(LOG_FILES_KEPT)
+        .build(LOG_DIR)
+        .map_err(|e| eprintln!("Error opening log directory {}: {}", LOG_DIR, e))
+        .ok()?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    tracing_subscriber::fmt()
+        .with_writer(writer.and(io::stderr.with_max_level(tracing::Level::WARN)))
+        .with_ansi(false)
+        .with_max_level(if verbose {
+            tracing::Level::DEBUG
+        } else {
+            tracing::Level::INFO
+        })
+        .init();
+    Some(guard)
+}
+
+// `--seed N` or `--seed=N` on the command line
+fn seed_arg() -> Option<u64> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            return args.next()?.parse().ok();
+        }
+        if let Some(seed) = arg.strip_prefix("--seed=") {
+            return seed.parse().ok();
+        }
+    }
+    None
+}
+
 fn main() -> Result<(), eframe::Error> {
-    let options = eframe::NativeOptions::default();
+    let _log_guard = init_logging();
+    info!(version = env!("CARGO_PKG_VERSION"), "woro started");
+
+    if std::env::args().nth(1).as_deref() == Some("simulate") {
+        run_simulation(
+            std::env::args()
+                .skip(2)
+                .filter(|arg| arg != "--verbose")
+                .collect(),
+        );
+        return Ok(());
+    }
+
+    // Size, position and maximized state are restored from eframe's storage (keyed by the
+    // app id); the inner size only applies on the very first launch.
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_app_id("woro")
+            .with_inner_size([720.0, 640.0]),
+        persist_window: true,
+        ..Default::default()
+    };
+
+    let listener = if Settings::load().unwrap_or_default().single_instance {
+        if forward_to_running_instance() {
+            return Ok(());
+        }
+        TcpListener::bind((Ipv4Addr::LOCALHOST, INSTANCE_PORT))
+            .map_err(|e| eprintln!("Error listening on port {}: {}", INSTANCE_PORT, e))
+            .ok()
+    } else {
+        None
+    };
+
     eframe::run_native(
-        "woro 📚",
+        APP_TITLE,
         options,
-    Box::new(|_cc| Box::new(App::default())),
-
+        Box::new(move |cc| {
+            let mut app = App::new(cc);
+            if let Some(listener) = listener {
+                app.focus_requests = Some(listen_for_instances(listener, cc.egui_ctx.clone()));
+            }
+            Box::new(app)
+        }),
     )
 }
 
+// ------------------- Scheduler simulation -------------------
+// `woro simulate --days 90 --accuracy 0.85 --new 10`: a virtual learner works through
+// everything due each day, getting each answer right with the given chance, to show what
+// the current schedule settings cost and bring. No window is opened.
+
+fn run_simulation(args: Vec<String>) {
+    let mut days = 90u32;
+    let mut accuracy = 0.85f32;
+    let mut new_per_day = 10usize;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // `--flag N` or `--flag=N`, as seed_arg reads them
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), value.to_string()),
+            None => (arg, args.next().unwrap_or_default()),
+        };
+        let parsed = match flag.as_str() {
+            "--days" => value.parse().map(|v| days = v).is_ok(),
+            "--accuracy" => value
+                .parse()
+                .ok()
+                .filter(|v| (0.0..=1.0).contains(v))
+                .map(|v| accuracy = v)
+                .is_some(),
+            "--new" => value.parse().map(|v| new_per_day = v).is_ok(),
+            "--seed" => value.parse::<u64>().is_ok(), // read by seed_arg
+            _ => false,
+        };
+        if !parsed {
+            eprintln!("Usage: woro simulate [--days N] [--accuracy 0..1] [--new N] [--seed N]");
+            return;
+        }
+    }
+
+    let settings = match Settings::load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let deck = fs::read_to_string(STATE_FILE)
+        .ok()
+        .and_then(|data| serde_json::from_str::<ResumeState>(&data).ok())
+        .map(|state| state.deck)
+        .filter(|deck| deck.exists())
+        .unwrap_or_else(|| PathBuf::from(SAVE_FILE));
+    let words = match read_words(settings.storage, &deck) {
+        Ok(words) => words,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", deck.display(), e);
+            return;
+        }
+    };
+    let deck_settings = read_deck_settings(&deck).unwrap_or_default();
+    let mut rng = match seed_arg().or(settings.seed) {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    println!(
+        "Simulating {} days of {} ({} words, {}) at {:.0}% accuracy, up to {} new words a day",
+        days,
+        deck_name(&deck),
+        words.len(),
+        deck_settings.scheduler.name(),
+        accuracy * 100.0,
+        new_per_day
+    );
+    let results = simulate(
+        &settings,
+        deck_settings.scheduler.scheduler(),
+        words,
+        days,
+        accuracy,
+        new_per_day,
+        &mut rng,
+    );
+    println!(
+        "{:>5} {:>8} {:>5} {:>9}",
+        "day", "reviews", "new", "mastered"
+    );
+    for (day, result) in results.iter().enumerate() {
+        println!(
+            "{:>5} {:>8} {:>5} {:>9}",
+            day + 1,
+            result.reviews,
+            result.new,
+            result.mastered
+        );
+    }
+    let total: usize = results.iter().map(|r| r.reviews).sum();
+    let peak = results.iter().map(|r| r.reviews).max().unwrap_or(0);
+    println!(
+        "Average {:.1} reviews a day (peak {}), {} mastered at the end",
+        total as f32 / results.len().max(1) as f32,
+        peak,
+        results.last().map_or(0, |r| r.mastered)
+    );
+}
+
+// ------------------- Single instance -------------------
+// Tells an already running woro to show itself; true if one answered as woro
+fn forward_to_running_instance() -> bool {
+    let address = (Ipv4Addr::LOCALHOST, INSTANCE_PORT).into();
+    let Ok(mut stream) = TcpStream::connect_timeout(&address, INSTANCE_TIMEOUT) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(INSTANCE_TIMEOUT));
+    if writeln!(stream, "{}", INSTANCE_HELLO).is_err() {
+        return false;
+    }
+    let mut reply = String::new();
+    io::BufReader::new(stream).read_line(&mut reply).is_ok() && reply.trim() == INSTANCE_REPLY
+}
+
+// Each caller gets its own thread and a read timeout, so one that never speaks can't
+// hold up the next
+fn listen_for_instances(listener: TcpListener, ctx: egui::Context) -> Receiver<()> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let tx = tx.clone();
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let _ = stream.set_read_timeout(Some(INSTANCE_TIMEOUT));
+                let mut hello = String::new();
+                if io::BufReader::new(&stream).read_line(&mut hello).is_err()
+                    || hello.trim() != INSTANCE_HELLO
+                {
+                    return;
+                }
+                let _ = writeln!(&stream, "{}", INSTANCE_REPLY);
+                if tx.send(()).is_ok() {
+                    ctx.request_repaint();
+                }
+            });
+        }
+    });
+    rx
+}
+
+impl App {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self {
+            egui_ctx: Some(cc.egui_ctx.clone()),
+            ..Self::default()
+        };
+        app.watch_deck(); // again, now that events can wake the UI
+        if app.settings.api_enabled {
+            // An old or hand-edited settings file may open the API to the network without a
+            // token; it never serves the network without one
+            if app.settings.api_lan && app.settings.api_token.is_empty() {
+                app.settings.api_token = format!("{:032x}", rand::thread_rng().gen::<u128>());
+                if let Err(e) = app.settings.save() {
+                    app.notify_error(e);
+                }
+            }
+            let token = Some(app.settings.api_token.clone()).filter(|_| app.settings.api_lan);
+            match start_api_server(app.settings.api_port, token, cc.egui_ctx.clone()) {
+                Ok(requests) => app.api_requests = Some(requests),
+                Err(e) => app.notify_error(e),
+            }
+        }
+        if let Some(ui_state) = cc
+            .storage
+            .and_then(|storage| eframe::get_value::<UiState>(storage, UI_STATE_KEY))
+        {
+            app.theme = ui_state.theme;
+            app.accent = ui_state.accent;
+            app.layout = ui_state.layout;
+            app.theme_dirty = ui_state.theme != Theme::System
+                || ui_state.accent.is_some()
+                || ui_state.layout != Layout::Normal;
+            app.word_view = ui_state.word_view;
+            app.recent_days = ui_state.recent_days;
+            app.stale_days = ui_state.stale_days;
+            app.word_table = ui_state.word_table;
+            app.word_column_widths = ui_state.word_column_widths;
+            app.hide_readings = ui_state.hide_readings;
+            app.search_query = ui_state.search_query;
+        }
+        app
+    }
+
+    fn apply_theme(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        let dark = match self.theme {
+            Theme::Dark => true,
+            Theme::Light => false,
+            Theme::System => frame.info().system_theme != Some(eframe::Theme::Light),
+        };
+        let mut visuals = if dark {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+        if let Some([r, g, b]) = self.accent {
+            let accent = egui::Color32::from_rgb(r, g, b);
+            visuals.selection.bg_fill = accent;
+            visuals.hyperlink_color = accent;
+            visuals.widgets.hovered.bg_stroke.color = accent;
+            visuals.widgets.active.bg_fill = accent;
+            visuals.widgets.active.weak_bg_fill = accent;
+        }
+        let mut style = egui::Style {
+            visuals,
+            ..Default::default()
+        };
+        self.layout.apply(&mut style);
+        ctx.set_style(style);
+        self.theme_dirty = false;
+    }
+}
+
 impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let ui_state = UiState {
+            theme: self.theme,
+            accent: self.accent,
+            layout: self.layout,
+            word_view: self.word_view,
+            recent_days: self.recent_days,
+            stale_days: self.stale_days,
+            search_query: self.search_query.clone(),
+            word_table: self.word_table,
+            word_column_widths: self.word_column_widths,
+            hide_readings: self.hide_readings,
+        };
+        eframe::set_value(storage, UI_STATE_KEY, &ui_state);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if !self.external_change {
+            self.flush_dirty();
+        }
+        self.saver.flush();
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if self.theme_dirty {
+            self.apply_theme(ctx, frame);
+        }
+        if let Some(requests) = &self.focus_requests {
+            if requests.try_iter().count() > 0 {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+        }
+
+        // A presentation gets the whole screen
+        let presenting = self.screen == Screen::Present;
+        if !presenting {
+            egui::TopBottomPanel::top("nav").show(ctx, |ui| self.nav_bar(ui));
+        }
+        if self.read_only && !presenting {
+            egui::TopBottomPanel::top("read_only").show(ctx, |ui| {
+                ui.colored_label(
+                    egui::Color32::from_rgb(220, 160, 40),
+                    "🔒 Another woro window has this deck open. Changes made here won't be saved.",
+                );
+            });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| match self.screen {
             Screen::AddWords => self.add_words_screen(ui),
             Screen::Game => self.game_screen(ui),
             Screen::End => self.end_screen(ui),
+            Screen::Stats => self.stats_screen(ui),
+            Screen::Settings => self.settings_screen(ui),
+            Screen::Dictation => self.dictation_screen(ui),
+            Screen::Insights => self.insights_screen(ui),
+            Screen::Present => self.present_screen(ui),
         });
+
+        self.column_mapping_window(ctx);
+        self.import_preview_window(ctx);
+        self.ambiguous_lines_window(ctx);
+        self.anki_import_window(ctx);
+        self.merge_report_window(ctx);
+        self.split_deck_window(ctx);
+        self.session_builder_window(ctx);
+        self.word_editor_window(ctx);
+        self.command_palette(ctx);
+        self.external_change_window(ctx);
+        self.corrupt_deck_window(ctx);
+        self.media_manager_window(ctx);
+        self.share_qr_window(ctx);
+        self.code_import_window(ctx);
+        self.anki_push_window(ctx);
+        self.subtitle_mining_window(ctx);
+        self.toasts_ui(ctx);
+
+        let save_now = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+        if ctx.input_mut(|i| i.consume_shortcut(&save_now)) {
+            self.run_palette_action(PaletteAction::SaveNow);
+        }
+
+        self.save_if_due(ctx);
+        self.handle_api_requests();
+        self.hands_free_tick(ctx);
+        self.poll_clipboard(ctx);
+        self.poll_audio_export();
+        self.poll_ocr();
+        self.saver.poll();
+        for error in std::mem::take(&mut self.saver.errors) {
+            self.toasts.push(Toast::new(error, true));
+        }
+        for error in std::mem::take(&mut self.media_cache.errors) {
+            self.toasts.push(Toast::new(error, true));
+        }
+        self.check_external_change();
+        self.subscriptions_tick(ctx);
+        self.save_resume_state();
+
+        let unsaved = self.has_unsaved_changes();
+        if unsaved != self.title_unsaved {
+            self.title_unsaved = unsaved;
+            let title = if unsaved {
+                format!("● {}", APP_TITLE)
+            } else {
+                APP_TITLE.to_string()
+            };
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title));
+        }
+        if self.saver.pending > 0 {
+            // Keep polling until the saver reports back so the indicator clears
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+
+        // egui only repaints on input or when asked. Everything above that waits on time
+        // (save debounce, clipboard, subscriptions) asks for the repaint it needs, and
+        // background threads wake the UI when they report back; the rest only drains
+        // channels and compares state. Beyond that, words come due, so wake when the next
+        // one does.
+        if let Some(wait) = self.next_due_in() {
+            ctx.request_repaint_after(wait);
+        }
     }
 }
 
 // ------------------- Persistence -------------------
 impl App {
-    fn save(&self) {
-        match serde_json::to_string_pretty(&self.words) {
-            Ok(json) => {
-                if let Err(e) = fs::write(SAVE_FILE, json) {
-                    eprintln!("Error saving to {}: {}", SAVE_FILE, e);
-                }
-            }
-            Err(e) => eprintln!("Error serializing words: {}", e),
+    // Edits only mark the deck dirty; the write happens once things settle for SAVE_DEBOUNCE
+    fn mark_dirty(&mut self) {
+        self.dirty_since.get_or_insert_with(Instant::now);
+    }
+
+    fn save_if_due(&mut self, ctx: &egui::Context) {
+        let Some(since) = self.dirty_since else {
+            return;
+        };
+        if self.external_change {
+            return; // wait until the user decides whose version wins
+        }
+        let elapsed = since.elapsed();
+        if elapsed >= SAVE_DEBOUNCE {
+            self.flush_dirty();
+        } else {
+            ctx.request_repaint_after(SAVE_DEBOUNCE - elapsed);
         }
     }
 
-    fn load(&mut self) {
-        match fs::read_to_string(SAVE_FILE) {
-            Ok(data) => match serde_json::from_str::<Vec<Word>>(&data) {
-                Ok(vec) => {
-                    self.words = vec;
-                    if !self.words.is_empty() {
-                        self.current_word_index = 0;
-                    }
-                }
-                Err(e) => eprintln!("Error parsing {}: {}", SAVE_FILE, e),
+    // Edits waiting for the debounce, or written but not yet confirmed by the saver
+    fn has_unsaved_changes(&self) -> bool {
+        self.dirty_since.is_some() || self.saver.pending > 0
+    }
+
+    fn flush_dirty(&mut self) {
+        if self.dirty_since.take().is_some() {
+            self.save();
+        }
+    }
+
+    // Serializing and writing happen on the saver thread; the UI only pays for the clone
+    fn save(&mut self) {
+        self.dirty_since = None;
+        self.assign_ids();
+        // Writing now would bury a damaged deck before the user could restore a backup
+        if self.read_only || self.corrupt_deck.is_some() {
+            return;
+        }
+        let storage = match self.deck_storage() {
+            Ok(storage) => storage,
+            Err(e) => {
+                self.notify_error(format!(
+                    "Error saving to {}: {}",
+                    self.deck_path.display(),
+                    e
+                ));
+                return;
+            }
+        };
+        self.saver.submit(SaveJob::Words {
+            path: self.deck_path.clone(),
+            storage,
+            words: self.words.clone(),
+        });
+
+        self.record_snapshot();
+    }
+
+    // Keeps one snapshot per day; only rewrites the history file when today's numbers move
+    fn record_snapshot(&mut self) {
+        let mastered = self.words.iter().filter(|w| w.level >= MAX_LEVEL).count();
+        let total = self.words.len();
+
+        let snapshot = self.today_snapshot();
+        let changed = snapshot.mastered != mastered || snapshot.total != total;
+        snapshot.mastered = mastered;
+        snapshot.total = total;
+        if !changed && !self.history_dirty {
+            return;
+        }
+        self.history_dirty = false;
+
+        self.saver.submit(SaveJob::History {
+            path: self.sidecar_path("history.json"),
+            history: self.history.clone(),
+        });
+    }
+
+    fn today_snapshot(&mut self) -> &mut DailySnapshot {
+        let today = now_secs() / SECS_PER_DAY;
+        if self.history.last().is_none_or(|s| s.day != today) {
+            self.history.push(DailySnapshot {
+                day: today,
+                ..Default::default()
+            });
+        }
+        self.history
+            .last_mut()
+            .expect("today's snapshot was just ensured")
+    }
+
+    // Counts an answer towards today's reviews, accuracy and study time
+    fn record_answer_stats(&mut self, correct: bool) {
+        let now = now_secs();
+        let spent = now.saturating_sub(self.last_answer_at).min(MAX_THINK_SECS);
+        self.last_answer_at = now;
+        self.count_review(correct, spent);
+    }
+
+    fn count_review(&mut self, correct: bool, spent: u64) {
+        let snapshot = self.today_snapshot();
+        snapshot.reviews += 1;
+        if correct {
+            snapshot.correct += 1;
+        }
+        snapshot.study_secs += spent;
+        self.history_dirty = true;
+    }
+
+    // Consecutive days with at least one review, ending today (or yesterday if not yet today)
+    fn study_streak(&self) -> u64 {
+        let studied: HashSet<u64> = self
+            .history
+            .iter()
+            .filter(|s| s.reviews > 0)
+            .map(|s| s.day)
+            .collect();
+        let today = now_secs() / SECS_PER_DAY;
+        let mut day = if studied.contains(&today) {
+            today
+        } else {
+            today.saturating_sub(1)
+        };
+
+        let mut streak = 0;
+        while studied.contains(&day) {
+            streak += 1;
+            if day == 0 {
+                break;
+            }
+            day -= 1;
+        }
+        streak
+    }
+
+    fn load_history(&mut self) {
+        let path = self.sidecar_path("history.json");
+        self.history = match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                self.notify_error(format!("Error parsing {}: {}", path.display(), e));
+                Vec::new()
+            }),
+            Err(_e) => Vec::new(),
+        };
+    }
+
+    fn load(&mut self) {
+        // The deck may still have a write in flight from an earlier visit
+        self.saver.flush();
+        self.saver.written.remove(&self.deck_path);
+        self.lock_deck();
+        self.watch_deck();
+        self.deck_mtime = file_mtime(&self.deck_path);
+        self.deck_touched = false;
+        self.external_change = false;
+        self.corrupt_deck = None;
+        match self.deck_storage() {
+            Ok(storage) => self.storage = storage,
+            Err(e) => {
+                // Keep the deck in memory rather than write it somewhere else
+                self.notify_error(format!(
+                    "Error opening {}: {}; opening read-only",
+                    self.deck_path.display(),
+                    e
+                ));
+                self.storage = Box::new(MemoryStorage::default());
+                self.read_only = true;
+            }
+        }
+        match self.storage.load() {
+            Ok(vec) => {
+                info!(deck = %self.deck_path.display(), words = vec.len(), "deck loaded");
+                self.words = vec;
+                if !self.words.is_empty() {
+                    self.current_word_index = 0;
+                }
+            }
+            Err(e) => {
+                error!(deck = %self.deck_path.display(), "deck can't be read: {}", e);
+                self.corrupt_deck = Some(CorruptDeck {
+                    error: e.to_string(),
+                    backups: valid_backups(&self.deck_path),
+                });
+            }
+        }
+
+        self.assign_ids();
+        self.grading_script = GradingScript::load(&self.sidecar_path("rhai")).unwrap_or_else(|e| {
+            self.notify_error(e);
+            None
+        });
+        self.load_deck_settings();
+        self.replay_journal();
+        self.load_reviews();
+        self.load_history();
+
+        let decayed = self.apply_decay();
+        if decayed > 0 {
+            self.notify(format!(
+                "📉 {} words slipped a level since your last review",
+                decayed
+            ));
+            self.mark_dirty();
+        }
+    }
+
+    // Takes an advisory lock on <deck>.lock. If another instance holds it this one keeps
+    // working on the deck in memory but never writes it back.
+    fn lock_deck(&mut self) {
+        self.deck_lock = None;
+        self.read_only = false;
+        let path = self.sidecar_path("lock");
+        let file = match fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) => {
+                // Can't even create the lock (read-only directory?); don't block saving on it
+                warn!(path = %path.display(), "lock file unavailable: {}", e);
+                return;
+            }
+        };
+        match file.try_lock_exclusive() {
+            Ok(()) => self.deck_lock = Some(file),
+            Err(_) => {
+                warn!(deck = %self.deck_path.display(), "locked by another instance, read-only");
+                self.read_only = true;
+            }
+        }
+    }
+
+    // Watches the deck's folder (editors and sync clients often replace the file rather
+    // than write to it) and wakes the UI whenever the deck itself is touched
+    fn watch_deck(&mut self) {
+        self.deck_watcher = None;
+        let (tx, events) = mpsc::channel();
+        let deck = self.deck_path.clone();
+        let ctx = self.egui_ctx.clone();
+        let handler = move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else { return };
+            if event
+                .paths
+                .iter()
+                .any(|p| p.file_name() == deck.file_name())
+            {
+                let _ = tx.send(());
+                if let Some(ctx) = &ctx {
+                    ctx.request_repaint();
+                }
+            }
+        };
+
+        let dir = match self.deck_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let watcher = RecommendedWatcher::new(handler, notify::Config::default())
+            .and_then(|mut w| w.watch(&dir, RecursiveMode::NonRecursive).map(|()| w));
+        match watcher {
+            Ok(watcher) => {
+                self.deck_watcher = Some(DeckWatcher {
+                    _watcher: watcher,
+                    events,
+                })
+            }
+            Err(e) => self.notify_error(format!("Error watching {}: {}", dir.display(), e)),
+        }
+    }
+
+    // Our own saves move deck_mtime to what the saver found on disk right after writing,
+    // so anything newer than that came from somewhere else, even mid-save
+    fn check_external_change(&mut self) {
+        if let Some(written) = self.saver.written.remove(&self.deck_path) {
+            self.deck_mtime = written;
+        }
+        let Some(watcher) = &self.deck_watcher else {
+            return;
+        };
+        if watcher.events.try_iter().count() > 0 {
+            self.deck_touched = true;
+        }
+        if !self.deck_touched || self.saver.pending > 0 {
+            return;
+        }
+        self.deck_touched = false;
+        let mtime = file_mtime(&self.deck_path);
+        if mtime.is_some() && mtime != self.deck_mtime {
+            warn!(deck = %self.deck_path.display(), "deck changed on disk by another program");
+            self.external_change = true;
+        }
+    }
+
+    fn load_deck_settings(&mut self) {
+        self.next_subscription_check = None; // another deck, other subscriptions
+        self.deck_settings = read_deck_settings(&self.deck_path).unwrap_or_else(|e| {
+            self.notify_error(e);
+            DeckSettings::default()
+        });
+        if let Err(e) = self.deck_settings.answer_regex("") {
+            self.notify_error(format!("Error in answer pattern: {}", e));
+        }
+        let frequency = self.deck_settings.frequency_list.as_deref().map(|path| {
+            load_frequency_list(path)
+                .map_err(|e| format!("Error reading frequency list {}: {}", path.display(), e))
+        });
+        self.frequency = match frequency {
+            Some(Ok(ranks)) => ranks,
+            Some(Err(e)) => {
+                self.notify_error(e);
+                HashMap::new()
+            }
+            None => HashMap::new(),
+        };
+        let strokes = self.deck_settings.stroke_data.as_deref().map(|path| {
+            load_stroke_data(path)
+                .map_err(|e| format!("Error reading stroke data {}: {}", path.display(), e))
+        });
+        self.strokes = match strokes {
+            Some(Ok(strokes)) => strokes,
+            Some(Err(e)) => {
+                self.notify_error(e);
+                HashMap::new()
+            }
+            None => HashMap::new(),
+        };
+        self.rebuild_modes();
+    }
+
+    // The speaking exercise depends on app and deck settings, cloze and conjugation on
+    // the words
+    fn rebuild_modes(&mut self) {
+        self.modes = question_modes(
+            &self.settings.speech_recognition_command,
+            &self.deck_settings.foreign_language,
+            self.deck_settings.direction,
+            &self.words,
+        );
+        self.mode_index = self.mode_index.min(self.modes.len() - 1);
+    }
+
+    fn frequency_rank(&self, word: &Word) -> Option<u32> {
+        self.frequency.get(&word.foreign.to_lowercase()).copied()
+    }
+
+    fn save_deck_settings(&mut self) {
+        if self.read_only {
+            return;
+        }
+        let path = self.sidecar_path("deck.json");
+        match serde_json::to_string_pretty(&self.deck_settings) {
+            Ok(json) => {
+                if let Err(e) = fs::write(&path, json) {
+                    self.notify_error(format!("Error saving to {}: {}", path.display(), e));
+                }
+            }
+            Err(e) => self.notify_error(format!("Error serializing deck settings: {}", e)),
+        }
+    }
+
+    fn journal_answer(&mut self, idx: usize) {
+        if self.read_only {
+            return;
+        }
+        self.assign_ids();
+        let entry = JournalEntry {
+            at: now_secs(),
+            word: self.words[idx].clone(),
+        };
+        let path = self.sidecar_path("journal.jsonl");
+        let result = serde_json::to_string(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|line| {
+                fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .and_then(|mut file| writeln!(file, "{}", line))
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            self.notify_error(format!("Error writing journal {}: {}", path.display(), e));
+        }
+    }
+
+    // Applies journal entries written after the deck file was last saved, then clears the
+    // journal once the deck on disk has caught up
+    fn replay_journal(&mut self) {
+        if self.read_only {
+            return; // the journal belongs to the instance holding the lock
+        }
+        if self.corrupt_deck.is_some() {
+            return; // kept for whichever backup gets restored
+        }
+        let path = self.sidecar_path("journal.jsonl");
+        let Ok(data) = fs::read_to_string(&path) else {
+            return;
+        };
+        let saved_at = fs::metadata(&self.deck_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+
+        let mut replayed = 0;
+        for entry in data
+            .lines()
+            .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok())
+            .filter(|e| e.at >= saved_at)
+        {
+            if let Some(w) = self.words.iter_mut().find(|w| w.id == entry.word.id) {
+                *w = entry.word;
+                replayed += 1;
+            }
+        }
+
+        if replayed > 0 {
+            self.notify(format!(
+                "🩹 Recovered {} answers from the journal",
+                replayed
+            ));
+            self.save();
+            if !self.saver.flush() {
+                return; // keep the journal for the next start
+            }
+        }
+        if let Err(e) = fs::remove_file(&path) {
+            self.notify_error(format!("Error clearing journal {}: {}", path.display(), e));
+        }
+    }
+
+    // Lowers the level of words left unreviewed past their grace period.
+    // Returns how many words changed.
+    fn apply_decay(&mut self) -> usize {
+        if !self.settings.decay_enabled {
+            return 0;
+        }
+
+        let now = now_secs();
+        let mut decayed = 0;
+        for word in self.words.iter_mut().filter(|w| !w.archived) {
+            let since = word.last_reviewed_at.max(word.decayed_at);
+            if since == 0 || word.level <= 1 {
+                continue;
+            }
+
+            let idle_days = now.saturating_sub(since) as f32 / SECS_PER_DAY as f32;
+            let grace = self.settings.grace_days(word.level).max(0.1);
+            let lost = (self.settings.decay_rate * idle_days / grace).floor() as u8;
+            if lost == 0 {
+                continue;
+            }
+
+            word.level = word.level.saturating_sub(lost).max(1);
+            word.decayed_at = now;
+            word.mastered_streak = 0;
+            word.mastered_since = 0;
+            decayed += 1;
+        }
+        decayed
+    }
+
+    // Decks written before ids existed get them on load
+    fn assign_ids(&mut self) {
+        let next = next_word_id(&self.words);
+        for (word, id) in self.words.iter_mut().filter(|w| w.id == 0).zip(next..) {
+            word.id = id;
+        }
+    }
+
+    // A new word gets its id as it joins the deck: the review log, learning steps,
+    // media and session queues all key on it before the next save
+    fn push_word(&mut self, mut word: Word) {
+        word.id = next_word_id(&self.words);
+        self.words.push(word);
+    }
+
+    // Replays the review log: every word gets the level recorded by its latest review.
+    // Returns how many words changed.
+    fn rebuild_levels_from_log(&mut self) -> usize {
+        let latest: HashMap<u64, u8> = self
+            .reviews
+            .iter()
+            // Speaking and cloze answers record their own separate levels, articles none
+            .filter(|e| {
+                e.word_id != 0
+                    && e.level != 0
+                    && !matches!(
+                        e.mode,
+                        ReviewMode::Speak | ReviewMode::Cloze | ReviewMode::Article
+                    )
+            })
+            .map(|e| (e.word_id, e.level))
+            .collect();
+
+        let mut changed = 0;
+        for word in &mut self.words {
+            if let Some(level) = latest.get(&word.id) {
+                let level = (*level).clamp(1, MAX_LEVEL);
+                if word.level != level {
+                    word.level = level;
+                    changed += 1;
+                }
+            }
+        }
+        if changed > 0 {
+            self.mark_dirty();
+        }
+        changed
+    }
+
+    // Reopens the deck, screen and session from the previous run
+    fn resume(&mut self) {
+        let state: Option<ResumeState> = fs::read_to_string(STATE_FILE)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok());
+        let Some(state) = state else {
+            self.load();
+            return;
+        };
+
+        if state.deck.exists() {
+            self.deck_path = state.deck.clone();
+        }
+        self.recent_decks = state.recent_decks.clone();
+        self.recent_decks.retain(|p| *p != self.deck_path);
+        self.recent_decks.insert(0, self.deck_path.clone());
+        self.load();
+
+        self.session = state.session.clone();
+        self.session_id = state.session_id;
+        self.maintenance_queue = state.maintenance_queue.clone();
+        self.maintenance_kept = state.maintenance_kept;
+        self.maintenance_returned = state.maintenance_returned;
+        self.custom_queue = state.custom_queue.clone();
+        self.custom_right = state.custom_right;
+        self.custom_wrong = state.custom_wrong;
+
+        let current = self
+            .words
+            .iter()
+            .position(|w| w.id == state.current_word_id);
+        self.screen = match (state.screen, current) {
+            (Screen::Game, Some(index)) if self.session_pool().contains(&index) => {
+                self.current_word_index = index;
+                self.last_answer_at = now_secs();
+                Screen::Game
+            }
+            (Screen::Game, _) => {
+                self.session = SessionKind::All;
+                Screen::AddWords
+            }
+            (Screen::Present, _) => Screen::AddWords,
+            (screen, _) => screen,
+        };
+        self.resume_state = Some(state);
+    }
+
+    fn save_resume_state(&mut self) {
+        let state = ResumeState {
+            deck: self.deck_path.clone(),
+            recent_decks: self.recent_decks.clone(),
+            screen: self.screen,
+            session: self.session.clone(),
+            session_id: self.session_id,
+            current_word_id: self.words.get(self.current_word_index).map_or(0, |w| w.id),
+            maintenance_queue: self.maintenance_queue.clone(),
+            maintenance_kept: self.maintenance_kept,
+            maintenance_returned: self.maintenance_returned,
+            custom_queue: self.custom_queue.clone(),
+            custom_right: self.custom_right,
+            custom_wrong: self.custom_wrong,
+        };
+        if self.resume_state.as_ref() == Some(&state) {
+            return;
+        }
+
+        // Only retried once the state changes, so a failure is shown once
+        let result = serde_json::to_string_pretty(&state)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(STATE_FILE, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            self.notify_error(format!("Error saving to {}: {}", STATE_FILE, e));
+        }
+        self.resume_state = Some(state);
+    }
+
+    // Files that belong to a deck live next to it: words_data.json -> words_data.<suffix>
+    fn sidecar_path(&self, suffix: &str) -> PathBuf {
+        self.deck_path
+            .with_file_name(format!("{}.{}", deck_name(&self.deck_path), suffix))
+    }
+
+    // The open deck in the backend Settings picks, written the way this deck's settings ask
+    fn deck_storage(&self) -> Result<Box<dyn Storage + Send>, String> {
+        open_storage(
+            self.settings.storage,
+            &self.deck_path,
+            self.deck_settings.compress,
+            self.deck_settings.binary,
+        )
+    }
+
+    // Moves the open deck, words and review log, to the backend just picked in Settings
+    fn switch_storage(&mut self) {
+        if let Err(e) = self.settings.save() {
+            self.notify_error(e);
+        }
+        self.flush_dirty();
+        self.saver.flush();
+        if self.read_only {
+            return;
+        }
+        match self.deck_storage() {
+            Ok(storage) => self.storage = storage,
+            Err(e) => {
+                self.notify_error(format!("Error opening {}: {}", self.deck_path.display(), e));
+                return;
+            }
+        }
+        if let Err(e) = self.storage.replace_events(&self.reviews) {
+            self.notify_error(format!("Error writing review history: {}", e));
+        }
+        self.save();
+        self.notify(format!(
+            "Deck now kept in: {}",
+            self.settings.storage.name()
+        ));
+    }
+
+    fn load_reviews(&mut self) {
+        self.reviews = self.storage.events().unwrap_or_else(|e| {
+            self.notify_error(format!("Error reading review history: {}", e));
+            Vec::new()
+        });
+        self.refresh_recently_failed();
+    }
+
+    fn append_review(&mut self, event: ReviewEvent) {
+        if self.read_only {
+            self.reviews.push(event);
+            self.refresh_recently_failed();
+            return;
+        }
+        if let Err(e) = self.storage.append_event(&event) {
+            self.notify_error(format!("Error writing review history: {}", e));
+        }
+
+        self.reviews.push(event);
+        self.refresh_recently_failed();
+    }
+
+    // Sets the damaged deck aside, then carries on from the chosen backup or from nothing
+    fn resolve_corrupt_deck(&mut self, backup: Option<PathBuf>) {
+        let aside = self.sidecar_path("corrupt.json");
+        if let Err(e) = fs::copy(&self.deck_path, &aside) {
+            self.notify_error(format!(
+                "Error copying the damaged deck to {}: {}",
+                aside.display(),
+                e
+            ));
+            return;
+        }
+        self.corrupt_deck = None;
+
+        let Some(backup) = backup else { return };
+        match read_deck_file(&backup) {
+            Ok(words) => {
+                self.notify(format!(
+                    "♻ Restored {} words from {}",
+                    words.len(),
+                    backup.display()
+                ));
+                self.words = words;
+                self.current_word_index = 0;
+                self.assign_ids();
+                self.replay_journal();
+                self.mark_dirty();
+            }
+            Err(e) => self.notify_error(format!("Error reading {}: {}", backup.display(), e)),
+        }
+    }
+
+    // Switches to another deck file; a path that doesn't exist yet starts an empty deck
+    fn open_deck(&mut self, path: PathBuf) {
+        if path == self.deck_path {
+            return;
+        }
+
+        // Leaving with the prompt open keeps the version on disk, as quitting does
+        if self.external_change {
+            self.dirty_since = None;
+        } else {
+            self.flush_dirty();
+        }
+        self.words.clear();
+        self.media_cache.clear();
+        self.learning.clear(); // keyed by ids of this deck
+        self.previewed.clear();
+        self.deck_path = path.clone();
+        self.load();
+
+        self.recent_decks.retain(|p| *p != path);
+        self.recent_decks.insert(0, path);
+
+        self.current_word_index = 0;
+        self.session = SessionKind::All;
+        self.word_editor = None;
+        self.search_query.clear();
+        self.feedback_message.clear();
+        self.screen = Screen::AddWords;
+    }
+}
+
+// ------------------- Subscriptions -------------------
+const SUBSCRIPTION_REFRESH_SECS: u64 = 6 * 60 * 60;
+const SUBSCRIPTION_RETRY_SECS: u64 = 15 * 60; // after a failed fetch
+const CHANGED_TAG: &str = "changed";
+
+// deck, source, and the source's contents or why they couldn't be read
+type SubscriptionResult = (PathBuf, String, Result<String, String>);
+
+fn fetch_source(source: &str) -> Result<String, String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        ureq::get(source)
+            .timeout(Duration::from_secs(30))
+            .call()
+            .map_err(|e| e.to_string())?
+            .into_string()
+            .map_err(|e| e.to_string())
+    } else {
+        fs::read_to_string(source).map_err(|e| e.to_string())
+    }
+}
+
+impl App {
+    // Subscriptions are looked at when a fetch reports back or their timer runs out, not
+    // on every frame
+    fn subscriptions_tick(&mut self, ctx: &egui::Context) {
+        if self.subscription_results.is_some() {
+            self.refresh_subscriptions(false);
+            return;
+        }
+        match self.next_subscription_check {
+            Some(at) if at > Instant::now() => ctx.request_repaint_after(at - Instant::now()),
+            _ => self.refresh_subscriptions(false),
+        }
+    }
+
+    // Takes in finished fetches, then starts one for the subscriptions that are due (all
+    // of them if `force`). Fetching happens off the UI thread; URLs can be slow.
+    fn refresh_subscriptions(&mut self, force: bool) {
+        if let Some(results) = &self.subscription_results {
+            let mut fetched = Vec::new();
+            let mut finished = false;
+            loop {
+                match results.try_recv() {
+                    Ok(result) => fetched.push(result),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+            for (deck, source, result) in fetched {
+                if deck != self.deck_path {
+                    continue; // the deck was switched meanwhile; it'll come round again
+                }
+                match result {
+                    Ok(content) => self.apply_subscription(&source, &content),
+                    Err(e) => self.notify_error(format!("Error refreshing {}: {}", source, e)),
+                }
+            }
+            if !finished {
+                return;
+            }
+            self.subscription_results = None;
+        }
+
+        // Only a successful fetch moves last_checked, so a failed one comes round again
+        // after SUBSCRIPTION_RETRY_SECS rather than the full refresh interval
+        let now = now_secs();
+        let wait = self
+            .deck_settings
+            .subscriptions
+            .iter()
+            .map(|s| (s.last_checked + SUBSCRIPTION_REFRESH_SECS).saturating_sub(now))
+            .filter(|&secs| secs > 0)
+            .fold(SUBSCRIPTION_RETRY_SECS, u64::min);
+        self.next_subscription_check = Some(Instant::now() + Duration::from_secs(wait));
+
+        if self.read_only {
+            return;
+        }
+        let due: Vec<String> = self
+            .deck_settings
+            .subscriptions
+            .iter()
+            .filter(|s| force || now.saturating_sub(s.last_checked) >= SUBSCRIPTION_REFRESH_SECS)
+            .map(|s| s.source.clone())
+            .collect();
+        if due.is_empty() {
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let deck = self.deck_path.clone();
+        let ctx = self.egui_ctx.clone();
+        thread::spawn(move || {
+            for source in due {
+                let result = fetch_source(&source);
+                if tx.send((deck.clone(), source, result)).is_err() {
+                    return;
+                }
+                if let Some(ctx) = &ctx {
+                    ctx.request_repaint();
+                }
+            }
+        });
+        self.subscription_results = Some(rx);
+    }
+
+    // New words are added unless they were in the source before (so deleting one here
+    // sticks); a translation the source changed since last time replaces ours and the
+    // word gets tagged. If ours was edited here in the meantime it stays, and the
+    // source's version is offered as a conflict instead. Differences that were already
+    // there when subscribing are left.
+    fn apply_subscription(&mut self, source: &str, content: &str) {
+        let Some(index) = self
+            .deck_settings
+            .subscriptions
+            .iter()
+            .position(|s| s.source == source)
+        else {
+            return;
+        };
+        let mut seen = std::mem::take(&mut self.deck_settings.subscriptions[index].seen);
+        let first_new = self.words.len();
+        let mut changed = 0;
+        let mut conflicts = Vec::new();
+        let mut import = ImportIndex::new(&self.words);
+        for word in txt_words(content) {
+            let key = word.foreign.trim().to_lowercase();
+            let before = seen.insert(key, word.translation.clone());
+            let existing = import.position(&word.foreign).map(|i| &mut self.words[i]);
+            match (existing, before) {
+                (None, None) => {
+                    import_word(&mut self.words, &mut import, word, false);
+                }
+                (Some(w), Some(before)) if before != word.translation => {
+                    if w.translation != before {
+                        if w.translation != word.translation {
+                            conflicts.push(MergeConflict {
+                                word_id: w.id,
+                                foreign: w.foreign.clone(),
+                                ours: w.translation.clone(),
+                                theirs: word.translation,
+                            });
+                        }
+                        continue;
+                    }
+                    w.translation = word.translation;
+                    if !w.tags.iter().any(|t| t == CHANGED_TAG) {
+                        w.tags.push(CHANGED_TAG.to_string());
+                    }
+                    changed += 1;
+                }
+                _ => {}
+            }
+        }
+        let subscription = &mut self.deck_settings.subscriptions[index];
+        subscription.seen = seen;
+        subscription.last_checked = now_secs();
+        self.save_deck_settings();
+
+        let added = self.words.len() - first_new;
+        if !conflicts.is_empty() {
+            match &mut self.merge_report {
+                Some(report) => report.conflicts.extend(conflicts),
+                None => {
+                    self.merge_report = Some(MergeReport {
+                        source: source.to_string(),
+                        added,
+                        conflicts,
+                        ..Default::default()
+                    })
+                }
+            }
+        }
+        if added + changed > 0 {
+            self.mark_dirty();
+            self.notify(format!(
+                "🔄 {}: {} new words, {} changed translations (tagged \"{}\")",
+                source, added, changed, CHANGED_TAG
+            ));
+        }
+    }
+}
+
+// ------------------- Toasts -------------------
+// Errors and import results, in the corner of the window instead of only on the terminal
+// GUI users never see. Results fade after TOAST_DURATION; errors stay until closed.
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+struct Toast {
+    text: String,
+    error: bool,
+    shown_at: Instant,
+}
+
+impl Toast {
+    fn new(text: String, error: bool) -> Self {
+        Self {
+            text,
+            error,
+            shown_at: Instant::now(),
+        }
+    }
+}
+
+impl App {
+    fn notify(&mut self, text: String) {
+        info!("{}", text);
+        self.toasts.push(Toast::new(text, false));
+    }
+
+    fn notify_error(&mut self, text: String) {
+        error!("{}", text);
+        self.toasts.push(Toast::new(text, true));
+    }
+
+    fn toasts_ui(&mut self, ctx: &egui::Context) {
+        self.toasts
+            .retain(|t| t.error || t.shown_at.elapsed() < TOAST_DURATION);
+        if self.toasts.is_empty() {
+            return;
+        }
+        let mut closed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (i, toast) in self.toasts.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(360.0);
+                        // Long errors show their first line; Copy details has the rest
+                        let first_line = toast.text.lines().next().unwrap_or_default();
+                        if toast.error {
+                            ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                format!("⚠ {}", first_line),
+                            );
+                        } else {
+                            ui.label(first_line);
+                        }
+                        ui.horizontal(|ui| {
+                            if toast.error && ui.small_button("📋 Copy details").clicked() {
+                                ui.output_mut(|o| o.copied_text = toast.text.clone());
+                            }
+                            if ui.small_button("✖").clicked() {
+                                closed = Some(i);
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(i) = closed {
+            self.toasts.remove(i);
+        }
+        if let Some(fading) = self
+            .toasts
+            .iter()
+            .filter(|t| !t.error)
+            .map(|t| TOAST_DURATION.saturating_sub(t.shown_at.elapsed()))
+            .min()
+        {
+            ctx.request_repaint_after(fading);
+        }
+    }
+}
+
+// ------------------- Background saving -------------------
+enum SaveJob {
+    Words {
+        path: PathBuf, // the deck file, which jobs collapse on whatever the backend
+        storage: Box<dyn Storage + Send>,
+        words: Vec<Word>,
+    },
+    History {
+        path: PathBuf,
+        history: Vec<DailySnapshot>,
+    },
+}
+
+impl SaveJob {
+    fn path(&self) -> &Path {
+        match self {
+            SaveJob::Words { path, .. } | SaveJob::History { path, .. } => path,
+        }
+    }
+
+    fn write(&mut self) -> Result<(), String> {
+        match self {
+            SaveJob::Words { storage, words, .. } => storage.save(words),
+            SaveJob::History { path, history } => {
+                let json = serde_json::to_vec(history).map_err(|e| e.to_string())?;
+                write_atomically(path, &json)
+            }
+        }
+    }
+}
+
+// The file a job wrote and its mtime straight afterwards; None for a job that was
+// collapsed into a newer one
+type SaveResult = Result<Option<(PathBuf, Option<SystemTime>)>, String>;
+
+// Owns the thread that does all deck and history writes. Jobs queued for the same file
+// while the thread is busy collapse into the newest one.
+struct Saver {
+    jobs: Option<Sender<SaveJob>>,
+    done: Receiver<SaveResult>,
+    pending: usize,
+    written: HashMap<PathBuf, Option<SystemTime>>, // mtime of each file right after our write
+    errors: Vec<String>,                           // failed writes the app hasn't shown yet
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Saver {
+    fn spawn() -> Self {
+        let (jobs, job_rx) = mpsc::channel::<SaveJob>();
+        let (done_tx, done) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            while let Ok(first) = job_rx.recv() {
+                let mut batch = vec![first];
+                batch.extend(job_rx.try_iter());
+
+                let total = batch.len();
+                let mut latest: Vec<SaveJob> = Vec::new();
+                for job in batch {
+                    latest.retain(|j| j.path() != job.path());
+                    latest.push(job);
+                }
+                let skipped = total - latest.len();
+
+                for mut job in latest {
+                    let result = job
+                        .write()
+                        .map(|()| Some((job.path().to_path_buf(), file_mtime(job.path()))))
+                        .map_err(|e| format!("Error saving to {}: {}", job.path().display(), e));
+                    match &result {
+                        Ok(_) => debug!(path = %job.path().display(), skipped, "saved"),
+                        Err(e) => error!("{}", e),
+                    }
+                    if done_tx.send(result).is_err() {
+                        return;
+                    }
+                }
+                for _ in 0..skipped {
+                    let _ = done_tx.send(Ok(None));
+                }
+            }
+        });
+
+        Self {
+            jobs: Some(jobs),
+            done,
+            pending: 0,
+            written: HashMap::new(),
+            errors: Vec::new(),
+            thread: Some(thread),
+        }
+    }
+
+    fn submit(&mut self, job: SaveJob) {
+        let Some(jobs) = &self.jobs else { return };
+        match jobs.send(job) {
+            Ok(()) => self.pending += 1,
+            // The thread is gone; fall back to writing in place
+            Err(mpsc::SendError(mut job)) => {
+                if let Err(e) = job.write() {
+                    let error = format!("Error saving to {}: {}", job.path().display(), e);
+                    error!("{}", error);
+                    self.errors.push(error);
+                }
+            }
+        }
+    }
+
+    // Collects finished writes without blocking
+    fn poll(&mut self) {
+        while let Ok(result) = self.done.try_recv() {
+            self.finish(result);
+        }
+    }
+
+    // Blocks until everything queued so far is on disk; false if any write failed
+    fn flush(&mut self) -> bool {
+        let mut ok = true;
+        while self.pending > 0 {
+            match self.done.recv() {
+                Ok(result) => ok &= self.finish(result),
+                Err(_) => {
+                    self.pending = 0;
+                    ok = false;
+                }
+            }
+        }
+        ok
+    }
+
+    fn finish(&mut self, result: SaveResult) -> bool {
+        self.pending = self.pending.saturating_sub(1);
+        match result {
+            Ok(Some((path, mtime))) => {
+                self.written.insert(path, mtime);
+                true
+            }
+            Ok(None) => true,
+            Err(e) => {
+                self.errors.push(e);
+                false
+            }
+        }
+    }
+}
+
+impl Drop for Saver {
+    // Closing the channel lets the thread drain its queue and exit
+    fn drop(&mut self) {
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct DeckWatcher {
+    _watcher: RecommendedWatcher, // stops watching when dropped
+    events: Receiver<()>,
+}
+
+// ------------------- Spreadsheet readers -------------------
+// Both return (header row, data rows) with every cell trimmed.
+
+fn read_csv_table(path: &Path) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| e.to_string())?;
+
+    let headers = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("malformed row: {}", e))?;
+        rows.push(record.iter().map(|c| c.trim().to_string()).collect());
+    }
+
+    Ok((headers, rows))
+}
+
+fn read_xlsx_table(path: &Path) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    let mut workbook: Xlsx<_> = open_workbook(path).map_err(|e: XlsxError| e.to_string())?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| "workbook has no sheets".to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let mut rows = range
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|c| c.to_string().trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .filter(|row| row.iter().any(|c| !c.is_empty()));
+
+    let headers = rows.next().unwrap_or_default();
+    Ok((headers, rows.collect()))
+}
+
+// Unix seconds <-> the UTC date they fall on, for the editor's date pickers
+fn secs_to_date(secs: u64) -> chrono::NaiveDate {
+    // NaiveDate's default is 1970-01-01
+    chrono::NaiveDate::default() + chrono::Days::new(secs / SECS_PER_DAY)
+}
+
+fn date_to_secs(date: chrono::NaiveDate) -> u64 {
+    let days = (date - chrono::NaiveDate::default()).num_days().max(0) as u64;
+    days * SECS_PER_DAY
+}
+
+// Days since the unix epoch -> "YYYY-MM-DD" (proleptic Gregorian, UTC)
+fn format_day(day: u64) -> String {
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+// One bar per hour, the best hour highlighted; hover a bar for its accuracy
+fn hour_chart(ui: &mut egui::Ui, by_hour: &[(usize, usize); 24], best: Option<usize>) {
+    let highest = by_hour.iter().map(|h| h.0).max().unwrap_or(0).max(1);
+    let bar_width = 16.0;
+    let bar_height = 60.0;
+    let label_height = 14.0;
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(bar_width * 24.0, bar_height + label_height),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    let font = egui::FontId::proportional(10.0);
+    for (hour, (answers, _)) in by_hour.iter().enumerate() {
+        let left = rect.left() + hour as f32 * bar_width;
+        let bottom = rect.bottom() - label_height;
+        let height = bar_height * *answers as f32 / highest as f32;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(left + 2.0, bottom - height),
+            egui::pos2(left + bar_width - 2.0, bottom),
+        );
+        let color = if best == Some(hour) {
+            visuals.selection.stroke.color
+        } else {
+            visuals.selection.bg_fill
+        };
+        painter.rect_filled(bar, 2.0, color);
+        if hour % 3 == 0 {
+            painter.text(
+                egui::pos2(bar.center().x, rect.bottom()),
+                egui::Align2::CENTER_BOTTOM,
+                hour.to_string(),
+                font.clone(),
+                visuals.weak_text_color(),
+            );
+        }
+    }
+    if let Some(pos) = response.hover_pos() {
+        let hour = (((pos.x - rect.left()) / bar_width) as usize).min(23);
+        let (answers, right) = by_hour[hour];
+        let accuracy = if answers > 0 {
+            format!(", {:.0}% right", right as f32 * 100.0 / answers as f32)
+        } else {
+            String::new()
+        };
+        response.on_hover_text(format!("{:02}:00: {} answers{}", hour, answers, accuracy));
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    if secs >= 3_600 {
+        format!("{}h {:02}m", secs / 3_600, secs % 3_600 / 60)
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+// ------------------- Anki package reader -------------------
+
+// An .apkg is a zip holding an SQLite collection. Only the first card of each note is
+// read (the forward card); its first two fields become foreign word and translation.
+fn read_apkg_cards(path: &Path) -> Result<Vec<AnkiCard>, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+
+    // Newer exports also ship a legacy collection next to the zstd-compressed one. When
+    // they don't, the collection.anki2 beside collection.anki21b is only a stub holding
+    // a "please update Anki" note, so it must not be imported as the deck.
+    let modern_only = archive.by_name("collection.anki21b").is_ok()
+        && archive.by_name("collection.anki21").is_err();
+    let name = ["collection.anki21", "collection.anki2"]
+        .into_iter()
+        .find(|n| archive.by_name(n).is_ok())
+        .filter(|_| !modern_only)
+        .ok_or_else(|| {
+            "package has no readable collection \
+             (export with \"Support older Anki versions\" enabled)"
+                .to_string()
+        })?;
+
+    let temp_path = std::env::temp_dir().join(format!("woro-import-{}.anki2", std::process::id()));
+    {
+        let mut entry = archive.by_name(name).map_err(|e| e.to_string())?;
+        let mut temp = fs::File::create(&temp_path).map_err(|e| e.to_string())?;
+        io::copy(&mut entry, &mut temp).map_err(|e| e.to_string())?;
+    }
+
+    let cards = read_anki_collection(&temp_path).map_err(|e| e.to_string());
+    let _ = fs::remove_file(&temp_path);
+    cards
+}
+
+// The only note in the stub collection newer Anki versions put in every package
+const ANKI_STUB_NOTE: &str = "Please update to the latest Anki version";
+
+fn read_anki_collection(path: &Path) -> rusqlite::Result<Vec<AnkiCard>> {
+    let conn =
+        rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare(
+        "SELECT n.flds, n.tags, c.type, c.ivl, c.factor
+         FROM cards c JOIN notes n ON c.nid = n.id
+         WHERE c.ord = 0
+         ORDER BY c.id",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        let fields: String = row.get(0)?;
+        let tags: String = row.get(1)?;
+        let card_type: i64 = row.get(2)?;
+        let mut fields = fields.split('\x1f').map(strip_html);
+        Ok(AnkiCard {
+            foreign: fields.next().unwrap_or_default(),
+            translation: fields.next().unwrap_or_default(),
+            tags: tags.split_whitespace().map(str::to_string).collect(),
+            is_new: card_type == 0,
+            interval_days: row.get(3)?,
+            ease_permille: row.get(4)?,
+        })
+    })?;
+
+    let mut cards = Vec::new();
+    for card in rows {
+        let card = card?;
+        if card.foreign.starts_with(ANKI_STUB_NOTE) {
+            continue;
+        }
+        if !card.foreign.is_empty() && !card.translation.is_empty() {
+            cards.push(card);
+        }
+    }
+    Ok(cards)
+}
+
+// Anki fields are HTML; keep the text and decode the common entities
+fn strip_html(field: &str) -> String {
+    let mut text = String::with_capacity(field.len());
+    let mut in_tag = false;
+    for c in field.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// ------------------- Kindle Vocabulary Builder reader -------------------
+
+// Kindle keeps looked-up words in system/vocabulary/vocab.db. Words come without a
+// translation; each gets its dictionary form, language tag and up to three of the
+// sentences it was looked up in.
+fn read_kindle_vocab(path: &Path) -> rusqlite::Result<Vec<Word>> {
+    let conn =
+        rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare(
+        "SELECT w.id, w.word, w.stem, w.lang, l.usage, b.title
+         FROM WORDS w
+         LEFT JOIN LOOKUPS l ON l.word_key = w.id
+         LEFT JOIN BOOK_INFO b ON b.id = l.book_key
+         ORDER BY w.timestamp, l.timestamp",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+        ))
+    })?;
+
+    let mut words: Vec<Word> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut usages: Vec<usize> = Vec::new();
+    for row in rows {
+        let (id, word, stem, lang, usage, title) = row?;
+        let foreign = if stem.trim().is_empty() { word } else { stem };
+        let foreign = foreign.trim().to_string();
+        if foreign.is_empty() {
+            continue;
+        }
+
+        let i = *index.entry(id).or_insert_with(|| {
+            let mut w = Word::new(foreign, String::new());
+            w.tags.push("kindle".to_string());
+            if !lang.is_empty() {
+                w.tags.push(lang);
+            }
+            words.push(w);
+            usages.push(0);
+            words.len() - 1
+        });
+        let usage = usage.split_whitespace().collect::<Vec<_>>().join(" ");
+        if usage.is_empty() || usages[i] >= 3 {
+            continue;
+        }
+        let notes = &mut words[i].notes;
+        if !notes.is_empty() {
+            notes.push('\n');
+        }
+        notes.push_str(&format!("“{}”", usage));
+        if !title.is_empty() {
+            notes.push_str(&format!(" — {}", title));
+        }
+        usages[i] += 1;
+    }
+    Ok(words)
+}
+
+// ------------------- Subtitle mining -------------------
+const MINING_CANDIDATES: usize = 50;
+
+struct MiningCandidate {
+    word: String,
+    count: usize,
+    context: String, // first line it appears in
+    selected: bool,
+}
+
+struct SubtitleMining {
+    source: String,
+    candidates: Vec<MiningCandidate>,
+}
+
+// The spoken lines of an .srt file: cue numbers, timings and formatting tags dropped
+fn srt_lines(content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() || line.contains("-->") || line.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        let mut text = String::new();
+        let mut in_tag = None;
+        for c in line.chars() {
+            match (in_tag, c) {
+                (None, '<') => in_tag = Some('>'),
+                (None, '{') => in_tag = Some('}'),
+                (Some(end), c) if c == end => in_tag = None,
+                (Some(_), _) => {}
+                (None, c) => text.push(c),
+            }
+        }
+        let text = text.trim().trim_start_matches('-').trim();
+        if !text.is_empty() {
+            lines.push(text.to_string());
+        }
+    }
+    lines
+}
+
+// Most frequent words of the subtitles that `known` doesn't contain (lowercase)
+fn mine_words(lines: &[String], known: &HashSet<String>) -> Vec<MiningCandidate> {
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new(); // word -> (count, line)
+    for (i, line) in lines.iter().enumerate() {
+        for token in line.split(|c: char| !(c.is_alphabetic() || c == '\'' || c == '-')) {
+            let token = token
+                .trim_matches(|c: char| c == '\'' || c == '-')
+                .to_lowercase();
+            if token.chars().count() < 2 || known.contains(&token) {
+                continue;
+            }
+            counts.entry(token).or_insert((0, i)).0 += 1;
+        }
+    }
+
+    let mut candidates: Vec<MiningCandidate> = counts
+        .into_iter()
+        .map(|(word, (count, line))| MiningCandidate {
+            word,
+            count,
+            context: lines[line].clone(),
+            selected: false,
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+    candidates.truncate(MINING_CANDIDATES);
+    candidates
+}
+
+// ------------------- Frequency lists -------------------
+
+// One word per line, most frequent first. Extra columns after the word (such as the
+// counts in the FrequencyWords lists, "the 2345678") are ignored.
+fn load_frequency_list(path: &Path) -> io::Result<HashMap<String, u32>> {
+    let data = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let mut ranks = HashMap::new();
+    let words = data
+        .lines()
+        .filter_map(|line| line.split(['\t', ' ', ',']).next())
+        .map(|word| word.trim().to_lowercase())
+        .filter(|word| !word.is_empty());
+    for (rank, word) in (1..).zip(words) {
+        ranks.entry(word).or_insert(rank);
+    }
+    Ok(ranks)
+}
+
+// ------------------- Stroke order -------------------
+const STROKE_SECS: f32 = 0.6; // drawing one stroke
+const STROKE_HOLD_SECS: f32 = 1.5; // the finished character, before starting over
+
+#[derive(Deserialize)]
+struct HanziGraphic {
+    character: String,
+    medians: Vec<Vec<[f32; 2]>>,
+}
+
+// Make Me a Hanzi's graphics.txt: one JSON object per line, each stroke's median as
+// points in a 1024 square with y pointing up from a baseline at 900. Only the medians
+// are kept, scaled to a unit square with y down.
+fn load_stroke_data(path: &Path) -> io::Result<HashMap<char, Vec<Vec<egui::Pos2>>>> {
+    let data = fs::read_to_string(path)?;
+    Ok(data
+        .lines()
+        .filter_map(|line| serde_json::from_str::<HanziGraphic>(line).ok())
+        .filter_map(|graphic| {
+            let character = graphic.character.chars().next()?;
+            let strokes = graphic
+                .medians
+                .iter()
+                .map(|median| {
+                    median
+                        .iter()
+                        .map(|[x, y]| egui::pos2(x / 1024.0, (900.0 - y) / 1024.0))
+                        .collect()
+                })
+                .collect();
+            Some((character, strokes))
+        })
+        .collect())
+}
+
+// Draws the strokes finished `secs` into the animation, the current one partly, and the
+// rest faintly
+fn stroke_order(ui: &mut egui::Ui, strokes: &[Vec<egui::Pos2>], secs: f32, size: f32) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+    let to_screen = |p: &egui::Pos2| rect.min + p.to_vec2() * size;
+    let width = size / 24.0;
+    let faint = egui::Stroke::new(width, ui.visuals().weak_text_color().gamma_multiply(0.3));
+    let ink = egui::Stroke::new(width, ui.visuals().strong_text_color());
+
+    let cycle = strokes.len() as f32 * STROKE_SECS + STROKE_HOLD_SECS;
+    let progress = (secs % cycle) / STROKE_SECS;
+    for (i, stroke) in strokes.iter().enumerate() {
+        let points: Vec<egui::Pos2> = stroke.iter().map(to_screen).collect();
+        let done = (progress - i as f32).clamp(0.0, 1.0);
+        painter.add(egui::Shape::line(points.clone(), faint));
+        if done > 0.0 {
+            painter.add(egui::Shape::line(partial_line(&points, done), ink));
+        }
+    }
+}
+
+// The first `fraction` of a polyline, by length
+fn partial_line(points: &[egui::Pos2], fraction: f32) -> Vec<egui::Pos2> {
+    let total: f32 = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+    let mut left = total * fraction;
+    let mut line: Vec<egui::Pos2> = points.first().copied().into_iter().collect();
+    for w in points.windows(2) {
+        let length = w[0].distance(w[1]);
+        if left >= length {
+            line.push(w[1]);
+            left -= length;
+        } else {
+            if length > 0.0 {
+                line.push(w[0].lerp(w[1], left / length));
+            }
+            break;
+        }
+    }
+    line
+}
+
+// Rounds a rank up to a band that's easy to read at a glance: "top 1000"
+fn frequency_label(rank: Option<u32>) -> String {
+    const BANDS: [u32; 8] = [100, 500, 1_000, 2_000, 5_000, 10_000, 20_000, 50_000];
+    match rank.and_then(|rank| BANDS.iter().find(|&&band| rank <= band)) {
+        Some(band) => format!("📊 top {}", band),
+        None => "📊 rare".to_string(),
+    }
+}
+
+// Black modules on white, with the quiet zone around them that scanners need
+fn qr_image(ui: &mut egui::Ui, qr: &qrcode::QrCode, size: f32) {
+    const QUIET: usize = 4;
+    let width = qr.width();
+    let modules = width + 2 * QUIET;
+    let cell = (size / modules as f32).floor().max(1.0);
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(cell * modules as f32, cell * modules as f32),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    for (i, color) in qr.to_colors().iter().enumerate() {
+        if *color == qrcode::Color::Dark {
+            let x = (i % width + QUIET) as f32 * cell;
+            let y = (i / width + QUIET) as f32 * cell;
+            painter.rect_filled(
+                egui::Rect::from_min_size(rect.min + egui::vec2(x, y), egui::vec2(cell, cell)),
+                0.0,
+                egui::Color32::BLACK,
+            );
+        }
+    }
+}
+
+// ------------------- Level colors -------------------
+// One color per level, red for new words through to green for mastered ones, used
+// wherever a level is shown
+const LEVEL_COLORS: [egui::Color32; MAX_LEVEL as usize] = [
+    egui::Color32::from_rgb(215, 70, 60),
+    egui::Color32::from_rgb(230, 135, 50),
+    egui::Color32::from_rgb(220, 190, 50),
+    egui::Color32::from_rgb(150, 195, 65),
+    egui::Color32::from_rgb(60, 175, 90),
+];
+
+fn level_color(level: u8) -> egui::Color32 {
+    LEVEL_COLORS[level.clamp(1, MAX_LEVEL) as usize - 1]
+}
+
+fn level_badge(ui: &mut egui::Ui, level: u8) -> egui::Response {
+    ui.label(
+        egui::RichText::new(format!(" L{} ", level))
+            .color(egui::Color32::from_gray(20))
+            .background_color(level_color(level)),
+    )
+}
+
+// A bar split into one colored segment per level, as wide as its share of the words
+fn level_bar(ui: &mut egui::Ui, counts: &[usize], text: &str, width: f32) -> egui::Response {
+    let total = counts.iter().sum::<usize>().max(1) as f32;
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, 18.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 3.0, ui.visuals().extreme_bg_color);
+    let mut left = rect.left();
+    for (i, count) in counts.iter().enumerate() {
+        let right = left + width * *count as f32 / total;
+        let segment = egui::Rect::from_min_max(
+            egui::pos2(left, rect.top()),
+            egui::pos2(right, rect.bottom()),
+        );
+        painter.rect_filled(segment, 0.0, level_color(i as u8 + 1));
+        left = right;
+    }
+    painter.text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        text,
+        egui::FontId::proportional(12.0),
+        egui::Color32::from_gray(20),
+    );
+    let hover: Vec<String> = counts
+        .iter()
+        .enumerate()
+        .map(|(i, n)| format!("L{}: {}", i + 1, n))
+        .collect();
+    response.on_hover_text(hover.join(" · "))
+}
+
+// Shows nothing when the deck has no frequency list
+fn frequency_badge(ui: &mut egui::Ui, frequency: &HashMap<String, u32>, text: &str) {
+    if frequency.is_empty() {
+        return;
+    }
+    let rank = frequency.get(&text.to_lowercase()).copied();
+    let hover = match rank {
+        Some(rank) => format!("#{} in the frequency list", rank),
+        None => "Not in the frequency list".to_string(),
+    };
+    ui.weak(frequency_label(rank)).on_hover_text(hover);
+}
+
+// ------------------- Review log export -------------------
+
+// Writes the log in the revlog layout used by FSRS/Anki tooling:
+// card_id, review_time (ms), review_rating (1 = again, 3 = good),
+// review_state (0 = new, 2 = review, 3 = relearning), review_duration (ms).
+// Events from before word ids were logged fall back to a lookup by foreign word.
+fn write_revlog_csv(path: &Path, reviews: &[ReviewEvent], words: &[Word]) -> Result<usize, String> {
+    let mut writer = csv::Writer::from_path(path).map_err(|e| e.to_string())?;
+    writer
+        .write_record([
+            "card_id",
+            "review_time",
+            "review_rating",
+            "review_state",
+            "review_duration",
+        ])
+        .map_err(|e| e.to_string())?;
+
+    let mut last_correct: HashMap<u64, bool> = HashMap::new();
+    let mut count = 0;
+    for event in reviews {
+        let card_id = if event.word_id != 0 {
+            event.word_id
+        } else {
+            match words.iter().find(|w| w.foreign == event.foreign) {
+                Some(word) => word.id,
+                None => continue,
+            }
+        };
+
+        let state = match last_correct.insert(card_id, event.correct) {
+            None => 0,
+            Some(true) => 2,
+            Some(false) => 3,
+        };
+        let rating = if event.correct { 3 } else { 1 };
+
+        writer
+            .write_record([
+                card_id.to_string(),
+                (event.at * 1_000).to_string(),
+                rating.to_string(),
+                state.to_string(),
+                event.response_ms.to_string(),
+            ])
+            .map_err(|e| e.to_string())?;
+        count += 1;
+    }
+
+    writer.flush().map_err(|e| e.to_string())?;
+    Ok(count)
+}
+
+// ------------------- Memrise export -------------------
+
+// Memrise's bulk "add words" box takes one word per line: word, definition and any
+// extra columns separated by tabs, with no quoting, so tabs and line breaks inside a
+// field become spaces.
+fn write_memrise_tsv(path: &Path, words: &[&Word]) -> io::Result<usize> {
+    let clean = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut out = String::new();
+    for word in words {
+        out.push_str(&clean(&word.foreign));
+        out.push('\t');
+        out.push_str(&clean(&word.translation));
+        if !word.notes.is_empty() {
+            out.push('\t');
+            out.push_str(&clean(&word.notes));
+        }
+        out.push('\n');
+    }
+    fs::write(path, out)?;
+    Ok(words.len())
+}
+
+// ------------------- Fuzzy search -------------------
+
+// Search result for one word: its rank and which chars matched in each field
+struct SearchHit {
+    score: u32,
+    foreign: Vec<bool>,
+    translation: Vec<bool>,
+    tags: Vec<bool>, // over `tags.join(", ")`
+    notes: Vec<bool>,
+}
+
+// Every whitespace-separated term has to match somewhere in the word; an empty query
+// matches everything. Terms may match exactly, with a typo or two, or as a subsequence.
+fn fuzzy_search(query: &str, word: &Word) -> Option<SearchHit> {
+    let tags = word.tags.join(", ");
+    let fields: Vec<Vec<char>> = [
+        word.foreign.as_str(),
+        word.translation.as_str(),
+        tags.as_str(),
+        word.notes.as_str(),
+    ]
+    .iter()
+    .map(|f| fold_chars(f))
+    .collect();
+    let mut masks: Vec<Vec<bool>> = fields.iter().map(|f| vec![false; f.len()]).collect();
+
+    let mut score = 0;
+    for term in query.split_whitespace() {
+        let term = fold_chars(term);
+        let mut best = 0;
+        for (text, mask) in fields.iter().zip(masks.iter_mut()) {
+            best = best.max(match_term(&term, text, mask));
+        }
+        if best == 0 {
+            return None;
+        }
+        score += best;
+    }
+
+    let [foreign, translation, tags, notes]: [Vec<bool>; 4] = masks.try_into().ok()?;
+    Some(SearchHit {
+        score,
+        foreign,
+        translation,
+        tags,
+        notes,
+    })
+}
+
+// Rank of a single line of text for the command palette
+fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let text = fold_chars(text);
+    let mut mask = vec![false; text.len()];
+    let mut score = 0;
+    for term in query.split_whitespace() {
+        match match_term(&fold_chars(term), &text, &mut mask) {
+            0 => return None,
+            s => score += s,
+        }
+    }
+    Some(score)
+}
+
+// Lowercases char by char so indices stay aligned with the original text
+fn fold_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+// Scores one search term against one field (0 = no match) and marks the matched chars
+fn match_term(term: &[char], text: &[char], mask: &mut [bool]) -> u32 {
+    if term.is_empty() || text.is_empty() {
+        return 0;
+    }
+
+    // Exact substring, preferring a hit at the start of a word
+    if let Some(start) = text.windows(term.len()).position(|w| w == term) {
+        mask[start..start + term.len()].fill(true);
+        let at_word_start = start == 0 || !text[start - 1].is_alphanumeric();
+        return if at_word_start { 100 } else { 80 };
+    }
+
+    // Typos: compare against every token (and its prefixes, for half-typed words)
+    let max_edits = match term.len() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    };
+    let mut best: Option<(usize, usize, usize)> = None; // (edits, start, end)
+    let mut start = 0;
+    while max_edits > 0 && start < text.len() {
+        if !text[start].is_alphanumeric() {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < text.len() && text[end].is_alphanumeric() {
+            end += 1;
+        }
+
+        let shortest = term.len().saturating_sub(max_edits).max(1);
+        let longest = (term.len() + max_edits).min(end - start);
+        for len in shortest..=longest {
+            let edits = edit_distance(term, &text[start..start + len]);
+            if edits <= max_edits && best.is_none_or(|(b, _, _)| edits < b) {
+                best = Some((edits, start, start + len));
+            }
+        }
+        start = end;
+    }
+    if let Some((edits, start, end)) = best {
+        mask[start..end].fill(true);
+        return 60 - 15 * edits as u32;
+    }
+
+    // Abbreviations: all chars in order within a reasonably tight span
+    if term.len() >= 3 {
+        let mut positions = Vec::with_capacity(term.len());
+        for (i, c) in text.iter().enumerate() {
+            if positions.len() < term.len() && *c == term[positions.len()] {
+                positions.push(i);
+            }
+        }
+        let tight = positions.len() == term.len()
+            && positions[positions.len() - 1] - positions[0] < term.len() * 2;
+        if tight {
+            for i in positions {
+                mask[i] = true;
+            }
+            return 20;
+        }
+    }
+
+    0
+}
+
+// (normal, highlighted) text formats for search results
+fn highlight_formats(ui: &egui::Ui) -> (egui::TextFormat, egui::TextFormat) {
+    let normal = egui::TextFormat {
+        font_id: egui::TextStyle::Body.resolve(ui.style()),
+        color: ui.visuals().text_color(),
+        ..Default::default()
+    };
+    let highlighted = egui::TextFormat {
+        background: ui.visuals().selection.bg_fill,
+        color: ui.visuals().strong_text_color(),
+        ..normal.clone()
+    };
+    (normal, highlighted)
+}
+
+fn append_highlighted(
+    job: &mut egui::text::LayoutJob,
+    text: &str,
+    mask: &[bool],
+    formats: &(egui::TextFormat, egui::TextFormat),
+) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut start = 0;
+    while start < chars.len() {
+        let marked = mask.get(start).copied().unwrap_or(false);
+        let mut end = start;
+        while end < chars.len() && mask.get(end).copied().unwrap_or(false) == marked {
+            end += 1;
+        }
+        let run: String = chars[start..end].iter().collect();
+        let format = if marked { &formats.1 } else { &formats.0 };
+        job.append(&run, 0.0, format.clone());
+        start = end;
+    }
+}
+
+// ------------------- Ruby text -------------------
+// Text at `size` with its readings small above it, or without them if !readings
+fn ruby_label(ui: &mut egui::Ui, text: &str, size: f32, readings: bool) {
+    ui.with_layout(egui::Layout::left_to_right(egui::Align::Max), |ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        let color = ui.visuals().strong_text_color();
+        for (base, reading) in parse_ruby(text) {
+            let Some(reading) = reading.filter(|_| readings) else {
+                ui.label(egui::RichText::new(base).size(size).strong());
+                continue;
+            };
+            let painter = ui.painter();
+            let base =
+                painter.layout_no_wrap(base.to_string(), egui::FontId::proportional(size), color);
+            let reading = painter.layout_no_wrap(
+                reading.to_string(),
+                egui::FontId::proportional(size * 0.45),
+                color,
+            );
+            let width = base.size().x.max(reading.size().x);
+            let (rect, _) = ui.allocate_exact_size(
+                egui::vec2(width, base.size().y + reading.size().y),
+                egui::Sense::hover(),
+            );
+            let reading_at = egui::pos2(rect.center().x - reading.size().x / 2.0, rect.min.y);
+            let base_at = egui::pos2(
+                rect.center().x - base.size().x / 2.0,
+                rect.min.y + reading.size().y,
+            );
+            ui.painter().galley(reading_at, reading, color);
+            ui.painter().galley(base_at, base, color);
+        }
+    });
+}
+
+// Notes and feedback as lightweight Markdown, see markdown_lines; text with readings in
+// it still gets its furigana
+fn markdown_view(ui: &mut egui::Ui, text: &str, size: f32, readings: bool) {
+    for line in markdown_lines(text) {
+        match line {
+            MarkdownLine::Heading(spans) => markdown_spans_row(ui, &spans, size * 1.3, readings),
+            MarkdownLine::Item(marker, spans) => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.add_space(size);
+                    ui.label(egui::RichText::new(marker).size(size));
+                    markdown_spans_row(ui, &spans, size, readings);
+                });
+            }
+            MarkdownLine::Text(spans) if spans.is_empty() => ui.add_space(size * 0.5),
+            MarkdownLine::Text(spans) => markdown_spans_row(ui, &spans, size, readings),
+        }
+    }
+}
+
+fn markdown_spans_row(ui: &mut egui::Ui, spans: &[Span], size: f32, readings: bool) {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        for span in spans {
+            match span {
+                Span::Text(text) if has_ruby(text) => ruby_label(ui, text, size, readings),
+                Span::Text(text) => {
+                    ui.label(egui::RichText::new(text).size(size));
+                }
+                Span::Bold(text) => {
+                    ui.label(egui::RichText::new(text).size(size).strong());
+                }
+                Span::Italic(text) => {
+                    ui.label(egui::RichText::new(text).size(size).italics());
+                }
+                Span::Code(text) => {
+                    ui.label(egui::RichText::new(text).size(size).code());
+                }
+                Span::Link(text, url) => {
+                    ui.hyperlink_to(egui::RichText::new(text).size(size), url);
+                }
+            }
+        }
+    });
+}
+
+// ------------------- Question modes -------------------
+// One kind of exercise. The game screen lets the active mode show the prompt and take
+// the answer, and check_answer lets it grade; a new exercise type implements this and
+// gets listed in question_modes().
+trait QuestionMode {
+    fn kind(&self) -> ReviewMode;
+    fn name(&self) -> &'static str;
+    // `readings`: show furigana over the word, see ruby_label
+    fn present(&self, ui: &mut egui::Ui, word: &Word, readings: bool);
+    fn input(&self, ui: &mut egui::Ui, answer: &mut String) -> AnswerInput;
+    fn grade(&self, word: &Word, answer: &str) -> bool;
+    // Shown after a wrong answer
+    fn expected(&self, word: &Word) -> String;
+    // Whether the word can be asked this way; the game only picks words that can
+    fn fits(&self, _word: &Word) -> bool {
+        true
+    }
+    // A new question is coming up; forget whatever was picked for the last one
+    fn new_question(&self) {}
+    // Shown under the feedback message for the answer last graded
+    fn feedback(&self, _ui: &mut egui::Ui) {}
+}
+
+#[derive(Default)]
+struct AnswerInput {
+    changed: bool,
+    submitted: bool,
+    error: Option<String>, // for a toast
+}
+
+fn question_modes(
+    recognizer: &str,
+    language: &str,
+    direction: Direction,
+    words: &[Word],
+) -> Vec<Box<dyn QuestionMode>> {
+    let mut modes: Vec<Box<dyn QuestionMode>> = vec![Box::new(TranslateMode {
+        direction,
+        backwards: Cell::new(None),
+    })];
+    if words.iter().any(|w| w.cloze.is_some()) {
+        modes.push(Box::new(ClozeMode));
+    }
+    if words.iter().any(|w| !w.conjugations.is_empty()) {
+        modes.push(Box::new(ConjugateMode {
+            asked: RefCell::new(None),
+        }));
+    }
+    let mut articles: Vec<String> = words
+        .iter()
+        .map(|w| w.article.to_lowercase())
+        .filter(|a| !a.is_empty())
+        .collect();
+    articles.sort();
+    articles.dedup();
+    if articles.len() > 1 {
+        modes.push(Box::new(ArticleMode { articles }));
+    }
+    modes.push(Box::new(TypingMode {
+        diff: RefCell::new(Vec::new()),
+    }));
+    if !recognizer.trim().is_empty() {
+        modes.push(Box::new(SpeakMode {
+            recognizer: recognizer.trim().replace("{lang}", language),
+            language: language.to_string(),
+            target: RefCell::new(String::new()),
+            listening: RefCell::new(None),
+            heard: Cell::new(None),
+            graded: Cell::new(None),
+            target_phonemes: Arc::new(Mutex::new(HashMap::new())),
+        }));
+    }
+    if words.iter().any(|w| has_ruby(&w.foreign)) {
+        modes.push(Box::new(ReadingMode {
+            reading: RefCell::new(String::new()),
+            marks: Cell::new(None),
+        }));
+    }
+    if is_character_language(language) {
+        modes.push(Box::new(HandwriteMode {
+            pad: RefCell::new(WritingPad::default()),
+        }));
+    }
+    modes
+}
+
+// Languages written in characters, where drawing the word is worth practising
+fn is_character_language(language: &str) -> bool {
+    let code = language.trim().to_lowercase();
+    ["zh", "ja", "cmn", "yue"]
+        .iter()
+        .any(|c| code == *c || code.starts_with(&format!("{}-", c)))
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Direction {
+    #[default]
+    ForeignToNative,
+    NativeToForeign,
+    Mixed, // either way, at random for each question
+}
+
+impl Direction {
+    const ALL: [Direction; 3] = [
+        Direction::ForeignToNative,
+        Direction::NativeToForeign,
+        Direction::Mixed,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Direction::ForeignToNative => "Word → translation",
+            Direction::NativeToForeign => "Translation → word",
+            Direction::Mixed => "Both, mixed",
+        }
+    }
+}
+
+// Show the foreign word, type its translation; or the other way round, depending on
+// the deck's direction
+struct TranslateMode {
+    direction: Direction,
+    backwards: Cell<Option<bool>>, // this question's way round, once picked
+}
+
+impl TranslateMode {
+    fn backwards(&self) -> bool {
+        match self.direction {
+            Direction::ForeignToNative => false,
+            Direction::NativeToForeign => true,
+            Direction::Mixed => {
+                let backwards = self
+                    .backwards
+                    .get()
+                    .unwrap_or_else(|| rand::thread_rng().gen_bool(0.5));
+                self.backwards.set(Some(backwards));
+                backwards
+            }
+        }
+    }
+}
+
+// The answer box and Check button that the typed exercises share
+fn answer_line(ui: &mut egui::Ui, answer: &mut String) -> AnswerInput {
+    ui.label("Your answer:");
+    let response = ui.text_edit_singleline(answer);
+    let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+    let check = ui.button("✓ Check").clicked();
+    AnswerInput {
+        changed: response.changed(),
+        submitted: enter || check,
+        ..Default::default()
+    }
+}
+
+impl QuestionMode for TranslateMode {
+    fn kind(&self) -> ReviewMode {
+        ReviewMode::Translate
+    }
+
+    fn name(&self) -> &'static str {
+        "✍ Translate"
+    }
+
+    fn present(&self, ui: &mut egui::Ui, word: &Word, readings: bool) {
+        if self.backwards() {
+            ui.label("Which word means this?");
+            ui.label(egui::RichText::new(&word.translation).size(48.0));
+        } else {
+            ui.label("What is the translation of this word?");
+            ruby_label(ui, &word.foreign, 48.0, readings);
+        }
+    }
+
+    fn input(&self, ui: &mut egui::Ui, answer: &mut String) -> AnswerInput {
+        answer_line(ui, answer)
+    }
+
+    fn grade(&self, word: &Word, answer: &str) -> bool {
+        let answer = answer.trim().to_lowercase();
+        let expected = self.expected(word);
+        answer == expected.to_lowercase()
+            || translation_variants(&expected)
+                .iter()
+                .any(|v| v.to_lowercase() == answer)
+    }
+
+    fn expected(&self, word: &Word) -> String {
+        if self.backwards() {
+            ruby_base(&word.foreign)
+        } else {
+            word.translation.clone()
+        }
+    }
+
+    fn new_question(&self) {
+        self.backwards.set(None);
+    }
+}
+
+// Show an example sentence with the word blanked out, type the word
+struct ClozeMode;
+
+impl QuestionMode for ClozeMode {
+    fn kind(&self) -> ReviewMode {
+        ReviewMode::Cloze
+    }
+
+    fn name(&self) -> &'static str {
+        "🧩 Cloze"
+    }
+
+    fn present(&self, ui: &mut egui::Ui, word: &Word, _readings: bool) {
+        let Some(card) = &word.cloze else {
+            ui.label(egui::RichText::new(&word.foreign).size(32.0).strong());
+            ui.weak("This word has no cloze card.");
+            return;
+        };
+        ui.label("Fill in the missing word:");
+        ui.label(egui::RichText::new(blank_out(&card.sentence, &word.foreign)).size(28.0));
+        ui.weak(format!("({})", word.translation));
+    }
+
+    fn input(&self, ui: &mut egui::Ui, answer: &mut String) -> AnswerInput {
+        answer_line(ui, answer)
+    }
+
+    fn grade(&self, word: &Word, answer: &str) -> bool {
+        answer.trim().to_lowercase() == ruby_base(&word.foreign).to_lowercase()
+    }
+
+    fn expected(&self, word: &Word) -> String {
+        ruby_base(&word.foreign)
+    }
+
+    fn fits(&self, word: &Word) -> bool {
+        word.cloze.is_some()
+    }
+}
+
+// Show a verb and one of its forms, type the conjugated word. The form is picked at
+// random when the question comes up and kept until it's answered.
+struct ConjugateMode {
+    asked: RefCell<Option<AskedForm>>,
+}
+
+struct AskedForm {
+    word_id: u64,
+    form: usize,
+    answered: bool,
+}
+
+impl QuestionMode for ConjugateMode {
+    fn kind(&self) -> ReviewMode {
+        ReviewMode::Conjugate
+    }
+
+    fn name(&self) -> &'static str {
+        "🔤 Conjugate"
+    }
+
+    fn present(&self, ui: &mut egui::Ui, word: &Word, readings: bool) {
+        let mut asked = self.asked.borrow_mut();
+        let fresh = asked
+            .as_ref()
+            .is_some_and(|a| a.word_id == word.id && !a.answered);
+        if !fresh && !word.conjugations.is_empty() {
+            *asked = Some(AskedForm {
+                word_id: word.id,
+                form: rand::thread_rng().gen_range(0..word.conjugations.len()),
+                answered: false,
+            });
+        }
+        ruby_label(ui, &word.foreign, 32.0, readings);
+        ui.weak(format!("({})", word.translation));
+        match asked.as_ref().and_then(|a| word.conjugations.get(a.form)) {
+            Some((form, _)) => {
+                ui.label(egui::RichText::new(form).size(24.0).strong());
+            }
+            None => {
+                ui.weak("This word has no conjugation table.");
+            }
+        }
+    }
+
+    fn input(&self, ui: &mut egui::Ui, answer: &mut String) -> AnswerInput {
+        answer_line(ui, answer)
+    }
+
+    fn grade(&self, word: &Word, answer: &str) -> bool {
+        let expected = self.expected(word);
+        if let Some(asked) = self.asked.borrow_mut().as_mut() {
+            asked.answered = true;
+        }
+        !expected.is_empty() && answer.trim().to_lowercase() == expected.to_lowercase()
+    }
+
+    fn expected(&self, word: &Word) -> String {
+        self.asked
+            .borrow()
+            .as_ref()
+            .filter(|a| a.word_id == word.id)
+            .and_then(|a| word.conjugations.get(a.form))
+            .map(|(_, conjugated)| conjugated.clone())
+            .unwrap_or_default()
+    }
+
+    fn fits(&self, word: &Word) -> bool {
+        !word.conjugations.is_empty()
+    }
+}
+
+// Show the word, type it exactly: a spelling drill. A miss shows which characters
+// were off.
+struct TypingMode {
+    diff: RefCell<Vec<CharDiff>>, // of the last miss
+}
+
+impl QuestionMode for TypingMode {
+    fn kind(&self) -> ReviewMode {
+        ReviewMode::Typing
+    }
+
+    fn name(&self) -> &'static str {
+        "⌨ Type it"
+    }
+
+    fn present(&self, ui: &mut egui::Ui, word: &Word, readings: bool) {
+        ui.label("Type this word exactly:");
+        ruby_label(ui, &word.foreign, 48.0, readings);
+        ui.weak(format!("({})", word.translation));
+    }
+
+    fn input(&self, ui: &mut egui::Ui, answer: &mut String) -> AnswerInput {
+        answer_line(ui, answer)
+    }
+
+    fn grade(&self, word: &Word, answer: &str) -> bool {
+        let expected = self.expected(word);
+        let typed = answer.trim();
+        let correct = typed == expected;
+        *self.diff.borrow_mut() = if correct {
+            Vec::new()
+        } else {
+            char_diff(&expected, typed)
+        };
+        correct
+    }
+
+    fn expected(&self, word: &Word) -> String {
+        ruby_base(&word.foreign).trim().to_string()
+    }
+
+    fn feedback(&self, ui: &mut egui::Ui) {
+        let diff = self.diff.borrow();
+        if diff.is_empty() {
+            return;
+        }
+        let font = egui::FontId::monospace(20.0);
+        let text = ui.visuals().text_color();
+        let red = egui::Color32::from_rgb(215, 70, 60);
+        let green = egui::Color32::from_rgb(60, 175, 90);
+        let mut job = egui::text::LayoutJob::default();
+        for part in diff.iter() {
+            let (c, format) = match *part {
+                CharDiff::Same(c) => (c, egui::TextFormat::simple(font.clone(), text)),
+                CharDiff::Extra(c) => (
+                    c,
+                    egui::TextFormat {
+                        strikethrough: egui::Stroke::new(1.5, red),
+                        ..egui::TextFormat::simple(font.clone(), red)
+                    },
+                ),
+                CharDiff::Missing(c) => (
+                    c,
+                    egui::TextFormat {
+                        underline: egui::Stroke::new(1.5, green),
+                        ..egui::TextFormat::simple(font.clone(), green)
+                    },
+                ),
+            };
+            job.append(&c.to_string(), 0.0, format);
+        }
+        ui.label(job);
+        ui.weak("red: typed but not in the word · green: left out");
+    }
+}
+
+// Show a noun, press the button (or number key) of its article; quick by design
+struct ArticleMode {
+    articles: Vec<String>, // every article used in the deck, lowercase
+}
+
+impl QuestionMode for ArticleMode {
+    fn kind(&self) -> ReviewMode {
+        ReviewMode::Article
+    }
+
+    fn name(&self) -> &'static str {
+        "⚥ Article"
+    }
+
+    fn present(&self, ui: &mut egui::Ui, word: &Word, readings: bool) {
+        ui.label("Which article?");
+        ruby_label(ui, &word.foreign, 48.0, readings);
+        ui.weak(format!("({})", word.translation));
+    }
+
+    fn input(&self, ui: &mut egui::Ui, answer: &mut String) -> AnswerInput {
+        const KEYS: [egui::Key; 9] = [
+            egui::Key::Num1,
+            egui::Key::Num2,
+            egui::Key::Num3,
+            egui::Key::Num4,
+            egui::Key::Num5,
+            egui::Key::Num6,
+            egui::Key::Num7,
+            egui::Key::Num8,
+            egui::Key::Num9,
+        ];
+        let mut picked = None;
+        ui.horizontal(|ui| {
+            for (i, article) in self.articles.iter().enumerate() {
+                let key = KEYS.get(i).copied();
+                let button = ui.add(
+                    egui::Button::new(egui::RichText::new(article).size(24.0))
+                        .min_size(egui::vec2(80.0, 48.0)),
+                );
+                let pressed = key.is_some_and(|key| ui.input(|inp| inp.key_pressed(key)));
+                if button.clicked() || pressed {
+                    picked = Some(article.clone());
+                }
+            }
+        });
+        ui.weak("Keys 1, 2, 3… pick an article");
+        match picked {
+            Some(article) => {
+                *answer = article;
+                AnswerInput {
+                    changed: true,
+                    submitted: true,
+                    ..Default::default()
+                }
+            }
+            None => AnswerInput::default(),
+        }
+    }
+
+    fn grade(&self, word: &Word, answer: &str) -> bool {
+        answer.to_lowercase() == word.article.to_lowercase()
+    }
+
+    fn expected(&self, word: &Word) -> String {
+        word.article.clone()
+    }
+
+    fn fits(&self, word: &Word) -> bool {
+        !word.article.is_empty()
+    }
+}
+
+// Show the foreign word, say it. What the recognizer heard is the answer, graded by how
+// close it sounds; see pronunciation_score. Recognizing and scoring both run on the
+// thread the 🎤 button starts.
+struct SpeakMode {
+    recognizer: String,
+    language: String,
+    target: RefCell<String>, // the word on screen, without readings
+    listening: RefCell<Option<Receiver<Result<Heard, String>>>>,
+    heard: Cell<Option<f32>>,  // score of the answer not graded yet
+    graded: Cell<Option<f32>>, // score of the answer last graded
+    // Phoneme spellings of words already asked, so asking again doesn't rerun espeak-ng
+    target_phonemes: Arc<Mutex<HashMap<String, Option<Vec<char>>>>>,
+}
+
+type Heard = (String, f32); // the recognizer's transcript and its score
+
+const PRONUNCIATION_PASS: f32 = 0.7;
+
+impl QuestionMode for SpeakMode {
+    fn kind(&self) -> ReviewMode {
+        ReviewMode::Speak
+    }
+
+    fn name(&self) -> &'static str {
+        "🎤 Speak"
+    }
+
+    fn present(&self, ui: &mut egui::Ui, word: &Word, readings: bool) {
+        ui.label(format!("Say this word ({}):", word.translation));
+        ruby_label(ui, &word.foreign, 48.0, readings);
+        let base = ruby_base(&word.foreign);
+        if *self.target.borrow() != base {
+            *self.target.borrow_mut() = base;
+        }
+    }
+
+    fn input(&self, ui: &mut egui::Ui, answer: &mut String) -> AnswerInput {
+        let mut listening = self.listening.borrow_mut();
+        if let Some(heard) = listening.as_ref().map(|rx| rx.try_recv()) {
+            match heard {
+                Ok(Ok((text, score))) => {
+                    *listening = None;
+                    *answer = text;
+                    self.heard.set(Some(score));
+                    return AnswerInput {
+                        changed: true,
+                        submitted: true,
+                        ..Default::default()
+                    };
+                }
+                Ok(Err(e)) => {
+                    *listening = None;
+                    return AnswerInput {
+                        error: Some(format!("Error running the speech recognizer: {}", e)),
+                        ..Default::default()
+                    };
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Listening…");
+                    });
+                    ui.ctx().request_repaint_after(Duration::from_millis(100));
+                    return AnswerInput::default();
+                }
+                Err(mpsc::TryRecvError::Disconnected) => *listening = None,
+            }
+        }
+
+        if ui.button("🎤 Speak").clicked() {
+            let command = self.recognizer.clone();
+            let target = self.target.borrow().clone();
+            let language = self.language.clone();
+            let cache = Arc::clone(&self.target_phonemes);
+            *listening = Some(spawn_with_result(Some(ui.ctx().clone()), move || {
+                let heard = run_command(&command)?;
+                let expected = cache
+                    .lock()
+                    .ok()
+                    .and_then(|cache| cache.get(&target).cloned());
+                let expected = expected.unwrap_or_else(|| {
+                    let spelled = phonemes(&target, &language);
+                    if let Ok(mut cache) = cache.lock() {
+                        cache.insert(target.clone(), spelled.clone());
+                    }
+                    spelled
+                });
+                let score = pronunciation_score(&target, expected, &heard, &language);
+                Ok((heard, score))
+            }));
+        }
+        AnswerInput::default()
+    }
+
+    // The score came with the answer; see input
+    fn grade(&self, _word: &Word, _answer: &str) -> bool {
+        let score = self.heard.take();
+        self.graded.set(score);
+        score.is_some_and(|score| score >= PRONUNCIATION_PASS)
+    }
+
+    fn expected(&self, word: &Word) -> String {
+        ruby_base(&word.foreign)
+    }
+
+    fn new_question(&self) {
+        self.heard.set(None);
+    }
+
+    fn feedback(&self, ui: &mut egui::Ui) {
+        if let Some(score) = self.graded.get() {
+            ui.label(format!("{:.0}% match", score * 100.0));
+        }
+    }
+}
+
+// Show a word written with readings (日本[にほん]), without them; type both its reading
+// and its meaning. Each box is checked on its own and both have to be right.
+struct ReadingMode {
+    reading: RefCell<String>, // the second answer box; the meaning is the usual answer
+    marks: Cell<Option<(bool, bool)>>, // whether reading and meaning were right, once graded
+}
+
+impl QuestionMode for ReadingMode {
+    fn kind(&self) -> ReviewMode {
+        ReviewMode::Reading
+    }
+
+    fn name(&self) -> &'static str {
+        "📖 Reading + meaning"
+    }
+
+    fn present(&self, ui: &mut egui::Ui, word: &Word, _readings: bool) {
+        ui.label("How is this read, and what does it mean?");
+        ui.label(egui::RichText::new(ruby_base(&word.foreign)).size(48.0));
+    }
+
+    fn input(&self, ui: &mut egui::Ui, answer: &mut String) -> AnswerInput {
+        ui.label("Reading:");
+        let reading = ui.text_edit_singleline(&mut *self.reading.borrow_mut());
+        ui.label("Meaning:");
+        let meaning = ui.text_edit_singleline(answer);
+        // Enter in the first box goes on to the second
+        if reading.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            meaning.request_focus();
+        }
+        let enter = meaning.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        let check = ui.button("✓ Check").clicked();
+        AnswerInput {
+            changed: reading.changed() || meaning.changed(),
+            submitted: enter || check,
+            ..Default::default()
+        }
+    }
+
+    fn grade(&self, word: &Word, answer: &str) -> bool {
+        let squash = |s: &str| s.split_whitespace().collect::<String>().to_lowercase();
+        let reading = squash(&self.reading.borrow()) == squash(&ruby_reading(&word.foreign));
+        let answer = answer.trim().to_lowercase();
+        let meaning = translation_variants(&word.translation)
+            .iter()
+            .any(|v| v.to_lowercase() == answer);
+        self.marks.set(Some((reading, meaning)));
+        reading && meaning
+    }
+
+    // Once graded, marks which of the two was wrong
+    fn expected(&self, word: &Word) -> String {
+        let reading = ruby_reading(&word.foreign);
+        match self.marks.get() {
+            Some((reading_ok, meaning_ok)) => {
+                let mark = |ok| if ok { "✔" } else { "✘" };
+                format!(
+                    "{} {} · {} {}",
+                    reading,
+                    mark(reading_ok),
+                    word.translation,
+                    mark(meaning_ok)
+                )
+            }
+            None => format!("{} · {}", reading, word.translation),
+        }
+    }
+
+    fn fits(&self, word: &Word) -> bool {
+        has_ruby(&word.foreign)
+    }
+
+    fn new_question(&self) {
+        self.reading.borrow_mut().clear();
+        self.marks.set(None);
+    }
+}
+
+// Show the translation, draw the foreign word. There's no recognizer: showing the answer
+// lays the word over the drawing and the learner grades themselves.
+struct HandwriteMode {
+    pad: RefCell<WritingPad>,
+}
+
+#[derive(Default)]
+struct WritingPad {
+    target: String,
+    strokes: Vec<Vec<egui::Vec2>>, // offsets from the canvas corner
+    revealed: bool,
+}
+
+const HANDWRITING_RIGHT: &str = "✓";
+const HANDWRITING_WRONG: &str = "✗";
+
+impl QuestionMode for HandwriteMode {
+    fn kind(&self) -> ReviewMode {
+        ReviewMode::Handwrite
+    }
+
+    fn name(&self) -> &'static str {
+        "🖊 Write"
+    }
+
+    fn present(&self, ui: &mut egui::Ui, word: &Word, _readings: bool) {
+        ui.label("Write the word for:");
+        ui.label(egui::RichText::new(&word.translation).size(32.0).strong());
+        let mut pad = self.pad.borrow_mut();
+        let target = ruby_base(&word.foreign);
+        if pad.target != target {
+            *pad = WritingPad {
+                target,
+                ..Default::default()
+            };
+        }
+    }
+
+    fn input(&self, ui: &mut egui::Ui, answer: &mut String) -> AnswerInput {
+        let mut pad = self.pad.borrow_mut();
+        let sense = if pad.revealed {
+            egui::Sense::hover()
+        } else {
+            egui::Sense::drag()
+        };
+        let (response, painter) = ui.allocate_painter(egui::vec2(260.0, 260.0), sense);
+        let rect = response.rect;
+        let ink = ui.visuals().strong_text_color();
+        painter.rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+        let guide = egui::Stroke::new(1.0, ui.visuals().weak_text_color().gamma_multiply(0.4));
+        painter.hline(rect.x_range(), rect.center().y, guide);
+        painter.vline(rect.center().x, rect.y_range(), guide);
+
+        let mut changed = false;
+        if response.drag_started() {
+            pad.strokes.push(Vec::new());
+            changed = true;
+        }
+        if response.dragged() {
+            if let (Some(pos), Some(stroke)) =
+                (response.interact_pointer_pos(), pad.strokes.last_mut())
+            {
+                stroke.push(pos - rect.min);
+            }
+        }
+        for stroke in &pad.strokes {
+            let points: Vec<egui::Pos2> = stroke.iter().map(|&offset| rect.min + offset).collect();
+            if let [dot] = points.as_slice() {
+                painter.circle_filled(*dot, 3.0, ink);
+            } else {
+                painter.add(egui::Shape::line(points, egui::Stroke::new(6.0, ink)));
+            }
+        }
+        if pad.revealed {
+            let size = (rect.width() / pad.target.chars().count().max(1) as f32).min(200.0);
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                &pad.target,
+                egui::FontId::proportional(size),
+                egui::Color32::from_rgb(220, 60, 60).gamma_multiply(0.5),
+            );
+        }
+
+        let mut grade = None;
+        ui.horizontal(|ui| {
+            if pad.revealed {
+                if ui.button("✓ I got it").clicked() {
+                    grade = Some(HANDWRITING_RIGHT);
+                }
+                if ui.button("✗ Not quite").clicked() {
+                    grade = Some(HANDWRITING_WRONG);
+                }
+            } else {
+                if ui.button("🧽 Clear").clicked() {
+                    pad.strokes.clear();
+                }
+                if ui.button("👁 Show answer").clicked() {
+                    pad.revealed = true;
+                }
+            }
+        });
+        match grade {
+            Some(grade) => {
+                *answer = grade.to_string();
+                pad.strokes.clear();
+                pad.revealed = false;
+                AnswerInput {
+                    changed: true,
+                    submitted: true,
+                    ..Default::default()
+                }
+            }
+            None => AnswerInput {
+                changed,
+                submitted: false,
+                ..Default::default()
             },
-            Err(_e) => { /* first run: ignore */ }
         }
     }
-}
-
-// ------------------- UI Screens -------------------
-impl App {
-    fn add_words_screen(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Add New Words");
-        ui.add_space(10.0);
 
-        // TXT import
-        if ui.button("📁 Import from TXT").clicked() {
-            self.import_from_txt();
+    fn grade(&self, _word: &Word, answer: &str) -> bool {
+        answer == HANDWRITING_RIGHT
+    }
+
+    fn expected(&self, word: &Word) -> String {
+        ruby_base(&word.foreign)
+    }
+}
+
+// Runs an external helper and returns what it printed
+fn run_command(command: &str) -> Result<String, String> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or("no command set")?;
+    let output = std::process::Command::new(program)
+        .args(parts)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Like run_command, but the picture's path goes in whole even if it has spaces
+fn run_ocr(command: &str, image: &Path) -> Result<String, String> {
+    run_filled(command, "{image}", &image.to_string_lossy())
+}
+
+// Splits `command` into arguments first and then puts `value` in for `placeholder`, so
+// the value stays one argument whatever spaces or quotes it holds
+fn run_filled(command: &str, placeholder: &str, value: &str) -> Result<String, String> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or("no command set")?;
+    let output = std::process::Command::new(program)
+        .args(parts.map(|arg| arg.replace(placeholder, value)))
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// espeak-ng's phoneme spelling of `text`, or None if it can't be run
+fn phonemes(text: &str, language: &str) -> Option<Vec<char>> {
+    let mut command = std::process::Command::new("espeak-ng");
+    if !language.trim().is_empty() {
+        command.args(["-v", language.trim()]);
+    }
+    let output = command.args(["-q", "-x", "--"]).arg(text).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let spelled = String::from_utf8_lossy(&output.stdout);
+    Some(spelled.chars().filter(|c| !c.is_whitespace()).collect())
+}
+
+// 0..=1: how close what was heard sounds to the expected word, given the expected word's
+// phonemes. Compares phonemes so a transcript spelled differently but pronounced the same
+// still scores high; falls back to letters when espeak-ng isn't available.
+fn pronunciation_score(
+    expected: &str,
+    expected_phonemes: Option<Vec<char>>,
+    heard: &str,
+    language: &str,
+) -> f32 {
+    let letters = |text: &str| -> Vec<char> {
+        text.to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect()
+    };
+    let (a, b) = match (expected_phonemes, phonemes(heard, language)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => (letters(expected), letters(heard)),
+    };
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    1.0 - edit_distance(&a, &b) as f32 / longest as f32
+}
+
+// ------------------- Grading scripts -------------------
+// A deck can ship a rhai script next to it (words_data.rhai) defining either or both of
+//   fn normalize(answer) { ... }          // returns the answer to grade instead
+//   fn accept(answer, expected) { ... }   // returns true to count a mismatch as correct
+struct GradingScript {
+    engine: rhai::Engine,
+    ast: rhai::AST,
+    has_normalize: bool,
+    has_accept: bool,
+}
+
+impl GradingScript {
+    // Scripts come with shared decks, so a runaway loop or recursion is stopped by these
+    // limits and reported like any other script error instead of hanging the UI
+    fn engine() -> rhai::Engine {
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(100_000);
+        engine.set_max_call_levels(32);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(10_000);
+        engine.set_max_array_size(1_000);
+        engine.set_max_map_size(1_000);
+        engine
+    }
+
+    // Ok(None) when the deck has no script
+    fn load(path: &Path) -> Result<Option<Self>, String> {
+        let Ok(source) = fs::read_to_string(path) else {
+            return Ok(None);
+        };
+        let engine = Self::engine();
+        let ast = engine
+            .compile(&source)
+            .map_err(|e| format!("Error in grading script {}: {}", path.display(), e))?;
+        let defines = |name: &str| ast.iter_functions().any(|f| f.name == name);
+        let has_normalize = defines("normalize");
+        let has_accept = defines("accept");
+        Ok(Some(Self {
+            engine,
+            ast,
+            has_normalize,
+            has_accept,
+        }))
+    }
+
+    fn normalize(&self, answer: &str) -> Result<String, String> {
+        if !self.has_normalize {
+            return Ok(answer.to_string());
+        }
+        let mut scope = rhai::Scope::new();
+        self.engine
+            .call_fn::<String>(&mut scope, &self.ast, "normalize", (answer.to_string(),))
+            .map_err(|e| format!("Error in grading script normalize(): {}", e))
+    }
+
+    fn accept(&self, answer: &str, expected: &str) -> Result<bool, String> {
+        if !self.has_accept {
+            return Ok(false);
+        }
+        let mut scope = rhai::Scope::new();
+        self.engine
+            .call_fn::<bool>(
+                &mut scope,
+                &self.ast,
+                "accept",
+                (answer.to_string(), expected.to_string()),
+            )
+            .map_err(|e| format!("Error in grading script accept(): {}", e))
+    }
+}
+
+// ------------------- Clipboard capture -------------------
+const CLIPBOARD_POLL: Duration = Duration::from_secs(1);
+
+struct ClipboardCapture {
+    clipboard: arboard::Clipboard,
+    last: String, // what was on the clipboard at the previous poll
+    polled_at: Instant,
+}
+
+// No inbox file yet is an empty inbox
+fn load_inbox() -> Result<Vec<InboxItem>, String> {
+    match fs::read_to_string(INBOX_FILE) {
+        Ok(data) => {
+            serde_json::from_str(&data).map_err(|e| format!("Error parsing {}: {}", INBOX_FILE, e))
+        }
+        Err(_e) => Ok(Vec::new()),
+    }
+}
+
+fn save_inbox(inbox: &[InboxItem]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(inbox).map_err(|e| e.to_string())?;
+    fs::write(INBOX_FILE, json).map_err(|e| format!("Error saving to {}: {}", INBOX_FILE, e))
+}
+
+// Copied text worth queueing: a word or short phrase, stripped of surrounding punctuation
+fn capturable(text: &str) -> Option<String> {
+    let text = text
+        .trim()
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .trim();
+    let words = text.split_whitespace().count();
+    if text.is_empty() || words > 4 || text.chars().count() > 60 {
+        return None;
+    }
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+// ------------------- Media -------------------
+const MEDIA_CACHE_SIZE: usize = 64; // decoded pictures kept in memory
+
+enum MediaSlot {
+    Loading,
+    Ready(egui::TextureHandle),
+    Failed(String),
+}
+
+type Decoded = (u64, Result<egui::ColorImage, String>); // word id, its picture
+
+// Pictures by word id. Nothing is read at startup; a picture is decoded on a worker
+// thread the first time it's asked for, and the least recently shown ones are dropped.
+struct MediaCache {
+    slots: HashMap<u64, MediaSlot>,
+    order: Vec<u64>, // least recently used first
+    decoded_tx: Sender<Decoded>,
+    decoded: Receiver<Decoded>,
+    errors: Vec<String>, // pictures that failed to load, for the app to show
+}
+
+impl MediaCache {
+    fn new() -> Self {
+        let (decoded_tx, decoded) = mpsc::channel();
+        Self {
+            slots: HashMap::new(),
+            order: Vec::new(),
+            decoded_tx,
+            decoded,
+            errors: Vec::new(),
+        }
+    }
+
+    // Ok(None) while the picture loads (a repaint follows), Err once it failed to
+    fn get(
+        &mut self,
+        ctx: &egui::Context,
+        id: u64,
+        path: &Path,
+    ) -> Result<Option<egui::TextureHandle>, String> {
+        for (done_id, image) in self.decoded.try_iter() {
+            // Forgotten while it was decoding
+            let Some(slot) = self.slots.get_mut(&done_id) else {
+                continue;
+            };
+            *slot = match image {
+                Ok(image) => MediaSlot::Ready(ctx.load_texture(
+                    format!("media-{}", done_id),
+                    image,
+                    Default::default(),
+                )),
+                Err(e) => {
+                    self.errors.push(e.clone());
+                    ctx.request_repaint(); // for the toast
+                    MediaSlot::Failed(e)
+                }
+            };
+        }
+
+        self.order.retain(|&o| o != id);
+        self.order.push(id);
+        if let Entry::Vacant(slot) = self.slots.entry(id) {
+            slot.insert(MediaSlot::Loading);
+            let tx = self.decoded_tx.clone();
+            let ctx = ctx.clone();
+            let path = path.to_path_buf();
+            thread::spawn(move || {
+                let image = image::open(&path)
+                    .map(|image| {
+                        let rgba = image.to_rgba8();
+                        let size = [rgba.width() as usize, rgba.height() as usize];
+                        egui::ColorImage::from_rgba_unmultiplied(size, &rgba)
+                    })
+                    .map_err(|e| format!("Error loading {}: {}", path.display(), e));
+                let _ = tx.send((id, image));
+                ctx.request_repaint();
+            });
+        }
+        while self.order.len() > MEDIA_CACHE_SIZE {
+            let oldest = self.order.remove(0);
+            self.slots.remove(&oldest);
+        }
+
+        match self.slots.get(&id) {
+            Some(MediaSlot::Ready(texture)) => Ok(Some(texture.clone())),
+            Some(MediaSlot::Failed(e)) => Err(e.clone()),
+            _ => Ok(None),
+        }
+    }
+
+    fn forget(&mut self, id: u64) {
+        self.slots.remove(&id);
+        self.order.retain(|&o| o != id);
+    }
+
+    fn clear(&mut self) {
+        self.slots.clear();
+        self.order.clear();
+    }
+}
+
+// What's in a deck's media folder against what its words point to
+struct MediaScan {
+    files: Vec<(String, u64, bool)>, // file name, size in bytes, used by some word
+    missing: Vec<(u64, String)>,     // word id, file name it points to
+    confirm_delete: bool,            // asking before the orphans go
+}
+
+// `editing` is the picture in the open word editor, not saved to its word yet
+fn scan_media(dir: &Path, words: &[Word], editing: Option<&str>) -> MediaScan {
+    let used: HashSet<&str> = words
+        .iter()
+        .filter(|w| !w.image.is_empty())
+        .map(|w| w.image.as_str())
+        .chain(editing)
+        .collect();
+    let mut files: Vec<(String, u64, bool)> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+                .map(|e| {
+                    let name = e.file_name().to_string_lossy().into_owned();
+                    let size = e.metadata().map_or(0, |m| m.len());
+                    let in_use = used.contains(name.as_str());
+                    (name, size, in_use)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+
+    let missing = words
+        .iter()
+        .filter(|w| !w.image.is_empty() && !dir.join(&w.image).exists())
+        .map(|w| (w.id, w.image.clone()))
+        .collect();
+    MediaScan {
+        files,
+        missing,
+        confirm_delete: false,
+    }
+}
+
+// Copies a file into the media folder as "<word id>-<name>" and returns that name
+fn copy_into_media(source: &Path, dir: &Path, id: u64) -> io::Result<String> {
+    let file_name = source
+        .file_name()
+        .map_or_else(|| "picture".into(), |n| n.to_string_lossy().into_owned());
+    let name = format!("{}-{}", id, file_name);
+    fs::create_dir_all(dir)?;
+    fs::copy(source, dir.join(&name))?;
+    Ok(name)
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.0} KB", b as f64 / (1 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
+
+// ------------------- Speech -------------------
+// Everything spoken goes through espeak-ng
+const DEFAULT_SPEECH_RATE: u32 = 175; // espeak-ng's own default
+const DEFAULT_SPEECH_PITCH: u32 = 50;
+
+#[derive(Clone)]
+struct Voice {
+    language: String, // empty = espeak-ng's default voice
+    rate: u32,
+    pitch: u32,
+}
+
+impl Voice {
+    fn command(&self) -> std::process::Command {
+        let mut command = std::process::Command::new("espeak-ng");
+        if !self.language.is_empty() {
+            command.args(["-v", &self.language]);
+        }
+        command
+            .arg("-s")
+            .arg(self.rate.to_string())
+            .arg("-p")
+            .arg(self.pitch.to_string());
+        command
+    }
+
+    // For the "play slowly" button
+    fn slow(&self) -> Voice {
+        Voice {
+            rate: (self.rate * 3 / 5).max(80),
+            ..self.clone()
+        }
+    }
+}
+
+// Says `text` out loud without waiting for it to finish
+fn speak(text: &str, voice: &Voice) -> Result<std::process::Child, String> {
+    voice
+        .command()
+        .arg("--")
+        .arg(text)
+        .spawn()
+        .map_err(|e| format!("Error running espeak-ng: {}", e))
+}
+
+// ------------------- Audio playlist -------------------
+const PLAYLIST_GAP_SECS: f32 = 1.5; // between a word and its translation
+const PLAYLIST_NEXT_SECS: f32 = 3.0; // before the next word
+
+// Numbers the temporary WAV files, so two renderings never share one
+static SPEECH_FILES: AtomicU64 = AtomicU64::new(0);
+
+// Speaks `text` into a WAV file with espeak-ng and returns its samples
+fn espeak_samples(text: &str, voice: &Voice) -> Result<(hound::WavSpec, Vec<i16>), String> {
+    let tmp = std::env::temp_dir().join(format!(
+        "woro-speech-{}-{}.wav",
+        std::process::id(),
+        SPEECH_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = read_espeak_wav(text, voice, &tmp);
+    let _ = fs::remove_file(&tmp);
+    result
+}
+
+fn read_espeak_wav(
+    text: &str,
+    voice: &Voice,
+    tmp: &Path,
+) -> Result<(hound::WavSpec, Vec<i16>), String> {
+    let output = voice
+        .command()
+        .arg("-w")
+        .arg(tmp)
+        .arg("--")
+        .arg(text)
+        .output()
+        .map_err(|e| format!("couldn't run espeak-ng ({}); is it installed?", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let reader = hound::WavReader::open(tmp).map_err(|e| e.to_string())?;
+    let spec = reader.spec();
+    let samples = reader
+        .into_samples::<i16>()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok((spec, samples))
+}
+
+// Where the playlist's samples go: an .ogg or .mp3 path is encoded by ffmpeg, which keeps
+// a long playlist small enough to carry around; anything else is written as WAV
+enum PlaylistWriter {
+    Wav(hound::WavWriter<io::BufWriter<fs::File>>),
+    Encoded {
+        ffmpeg: std::process::Child,
+        input: io::BufWriter<std::process::ChildStdin>,
+        spec: hound::WavSpec,
+    },
+}
+
+impl PlaylistWriter {
+    fn create(path: &Path, spec: hound::WavSpec) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+        let codec = match extension.as_deref() {
+            Some("ogg") => "libvorbis",
+            Some("mp3") => "libmp3lame",
+            _ => {
+                return hound::WavWriter::create(path, spec)
+                    .map(Self::Wav)
+                    .map_err(|e| e.to_string())
+            }
+        };
+        let mut ffmpeg = std::process::Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-f", "s16le", "-ar"])
+            .arg(spec.sample_rate.to_string())
+            .arg("-ac")
+            .arg(spec.channels.to_string())
+            .args(["-i", "-", "-c:a", codec, "-q:a", "4"])
+            .arg(path)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("couldn't run ffmpeg ({}); is it installed?", e))?;
+        let Some(input) = ffmpeg.stdin.take() else {
+            let _ = ffmpeg.kill();
+            return Err("couldn't pass audio to ffmpeg".to_string());
+        };
+        Ok(Self::Encoded {
+            ffmpeg,
+            input: io::BufWriter::new(input),
+            spec,
+        })
+    }
+
+    fn spec(&self) -> hound::WavSpec {
+        match self {
+            Self::Wav(writer) => writer.spec(),
+            Self::Encoded { spec, .. } => *spec,
+        }
+    }
+
+    fn write_sample(&mut self, sample: i16) -> Result<(), String> {
+        match self {
+            Self::Wav(writer) => writer.write_sample(sample).map_err(|e| e.to_string()),
+            Self::Encoded { input, .. } => input
+                .write_all(&sample.to_le_bytes())
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            Self::Wav(writer) => writer.finalize().map_err(|e| e.to_string()),
+            Self::Encoded { ffmpeg, input, .. } => {
+                // Closing its input tells ffmpeg the audio is complete
+                let flushed = input.into_inner().map(drop).map_err(|e| e.to_string());
+                let output = ffmpeg.wait_with_output().map_err(|e| e.to_string())?;
+                if !output.status.success() {
+                    return Err(format!(
+                        "ffmpeg failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                flushed
+            }
+        }
+    }
+
+    // Stops the encoder so nothing writes the file after it is removed
+    fn abandon(self) {
+        if let Self::Encoded { mut ffmpeg, .. } = self {
+            let _ = ffmpeg.kill();
+            let _ = ffmpeg.wait();
+        }
+    }
+}
+
+// One audio file: each word, a pause, its translation, a longer pause. Returns how many
+// words went in. A failed export leaves no half-written file behind.
+fn write_audio_playlist(
+    path: &Path,
+    words: &[(String, String)],
+    foreign_voice: &Voice,
+    native_voice: &Voice,
+) -> Result<usize, String> {
+    let mut writer = None;
+    let result = render_playlist(&mut writer, path, words, foreign_voice, native_voice);
+    let Some(writer) = writer else {
+        return result.map(|()| words.len()); // nothing was written
+    };
+    let result = match result {
+        Ok(()) => writer.finish(),
+        Err(e) => {
+            writer.abandon();
+            Err(e)
+        }
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+    result.map(|()| words.len())
+}
+
+fn render_playlist(
+    writer: &mut Option<PlaylistWriter>,
+    path: &Path,
+    words: &[(String, String)],
+    foreign_voice: &Voice,
+    native_voice: &Voice,
+) -> Result<(), String> {
+    for (foreign, translation) in words {
+        for (text, voice, pause) in [
+            (foreign, foreign_voice, PLAYLIST_GAP_SECS),
+            (translation, native_voice, PLAYLIST_NEXT_SECS),
+        ] {
+            let (spec, samples) = espeak_samples(text, voice)?;
+            if writer.is_none() {
+                *writer = Some(PlaylistWriter::create(path, spec)?);
+            }
+            let Some(out) = writer.as_mut() else { break };
+            if out.spec() != spec {
+                return Err(format!("\"{}\" came out in a different audio format", text));
+            }
+            let silence = (spec.sample_rate as f32 * pause) as usize * spec.channels as usize;
+            for sample in samples.into_iter().chain(std::iter::repeat_n(0, silence)) {
+                out.write_sample(sample)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// ------------------- Hands-free review -------------------
+const HANDS_FREE_THINK_SECS: u64 = 4; // silence between question and answer
+
+#[derive(Clone, Copy, PartialEq)]
+enum HandsFreeStep {
+    Asking,   // speaking the word
+    Thinking, // quiet, time to recall
+    Telling,  // speaking the translation
+    Waiting,  // for "knew it" / "again"
+}
+
+// The game read aloud: word, pause, translation, then a key or a spoken command grades it
+struct HandsFree {
+    step: HandsFreeStep,
+    since: Instant,
+    speech: Option<std::process::Child>,
+    // Voice commands go through the speech recognizer; off without one or after it fails
+    voice: bool,
+    listening: Option<Receiver<Result<String, String>>>,
+}
+
+// A spoken grade: "correct" means the word was known, "again" asks it again
+fn voice_command(heard: &str) -> Option<bool> {
+    heard
+        .to_lowercase()
+        .split(|c: char| !c.is_alphabetic())
+        .find_map(|word| match word {
+            "correct" => Some(true),
+            "again" => Some(false),
+            _ => None,
+        })
+}
+
+impl HandsFree {
+    fn speaking(&mut self) -> bool {
+        self.speech
+            .as_mut()
+            .is_some_and(|child| matches!(child.try_wait(), Ok(None)))
+    }
+
+    fn say(&mut self, step: HandsFreeStep, text: &str, voice: &Voice) -> Result<(), String> {
+        self.stop_speaking();
+        self.listening = None;
+        self.step = step;
+        self.since = Instant::now();
+        self.speech = Some(speak(text, voice)?);
+        Ok(())
+    }
+
+    fn stop_speaking(&mut self) {
+        if let Some(mut child) = self.speech.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    // The commands are English, whatever language the deck is in
+    fn listen(&mut self, recognizer: &str, ctx: &egui::Context) {
+        if !self.voice || self.listening.is_some() {
+            return;
+        }
+        let command = recognizer.trim().replace("{lang}", "en");
+        self.listening = Some(spawn_with_result(Some(ctx.clone()), move || {
+            run_command(&command)
+        }));
+    }
+}
+
+// ------------------- Dictation -------------------
+// Random numbers, dates and times read out in the deck's language; no words needed
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum DictationKind {
+    #[default]
+    Number,
+    Date,
+    Time,
+}
+
+struct Dictation {
+    kind: DictationKind,
+    max_number: u32,
+    prompt: String, // what gets spoken and the expected transcription
+    answer: String,
+    feedback: String,
+    right: u32,
+    asked: u32,
+}
+
+impl Default for Dictation {
+    fn default() -> Self {
+        Self {
+            kind: DictationKind::Number,
+            max_number: 1000,
+            prompt: String::new(),
+            answer: String::new(),
+            feedback: String::new(),
+            right: 0,
+            asked: 0,
+        }
+    }
+}
+
+impl Dictation {
+    fn next(&mut self) {
+        let mut rng = rand::thread_rng();
+        self.prompt = match self.kind {
+            DictationKind::Number => rng.gen_range(0..=self.max_number).to_string(),
+            DictationKind::Date => {
+                let day = chrono::NaiveDate::from_ymd_opt(1950, 1, 1).unwrap_or_default()
+                    + chrono::Duration::days(rng.gen_range(0..365 * 100));
+                day.format("%-d.%-m.%Y").to_string()
+            }
+            DictationKind::Time => {
+                format!("{}:{:02}", rng.gen_range(0..24), rng.gen_range(0..60))
+            }
+        };
+        self.answer.clear();
+    }
+}
+
+// ------------------- REST API -------------------
+// An HTTP request waiting for the UI thread, which owns the deck
+struct ApiRequest {
+    method: String,
+    path: String,
+    body: String,
+    reply: Sender<(u16, String)>,
+}
+
+// Only this computer can connect unless Settings opens the API to the network, and then
+// every request must carry the token. The quiz page is static and served right here.
+fn start_api_server(
+    port: u16,
+    token: Option<String>,
+    ctx: egui::Context,
+) -> Result<Receiver<ApiRequest>, String> {
+    let host = if token.is_some() {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        Ipv4Addr::LOCALHOST
+    };
+    let server = tiny_http::Server::http((host, port))
+        .map_err(|e| format!("Error starting API server on port {}: {}", port, e))?;
+    info!(%host, port, "API listening");
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let url = request.url().to_string();
+            let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+            let path = path.to_string();
+            let authorized = token.as_deref().is_none_or(|token| {
+                let bearer = format!("Bearer {}", token);
+                query
+                    .split('&')
+                    .any(|pair| pair == format!("token={}", token))
+                    || request
+                        .headers()
+                        .iter()
+                        .any(|h| h.field.equiv("Authorization") && h.value.as_str() == bearer)
+            });
+
+            let (status, content_type, body) = if !authorized {
+                (
+                    401,
+                    "application/json",
+                    r#"{"error":"missing or wrong token"}"#.to_string(),
+                )
+            } else if request.method() == &tiny_http::Method::Get && path == "/" {
+                (200, "text/html; charset=utf-8", API_QUIZ_PAGE.to_string())
+            } else {
+                let mut body = String::new();
+                let _ = io::Read::read_to_string(request.as_reader(), &mut body);
+                let (reply, answer) = mpsc::channel();
+                let api_request = ApiRequest {
+                    method: request.method().as_str().to_uppercase(),
+                    path,
+                    body,
+                    reply,
+                };
+                if tx.send(api_request).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+                let (status, json) = answer
+                    .recv_timeout(Duration::from_secs(5))
+                    .unwrap_or((503, r#"{"error":"woro is busy"}"#.to_string()));
+                (status, "application/json", json)
+            };
+
+            let mut response = tiny_http::Response::from_string(body).with_status_code(status);
+            if let Ok(header) =
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes())
+            {
+                response.add_header(header);
+            }
+            if let Err(e) = request.respond(response) {
+                warn!("Error answering API request: {}", e);
+            }
+        }
+    });
+    Ok(rx)
+}
+
+// Where a phone on the same network would reach this computer, if it has a network at
+// all. Connecting a UDP socket only picks the route; nothing is sent.
+fn lan_address() -> Option<std::net::IpAddr> {
+    static ADDRESS: std::sync::OnceLock<Option<std::net::IpAddr>> = std::sync::OnceLock::new();
+    *ADDRESS.get_or_init(|| {
+        let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+        socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9)).ok()?;
+        socket.local_addr().ok().map(|a| a.ip())
+    })
+}
+
+// A translate-only quiz on the API, for a phone browser: the word, a box for its
+// translation, the verdict. The token, if any, comes along from the page's own URL.
+const API_QUIZ_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>woro quiz</title>
+<style>
+  body { font-family: sans-serif; max-width: 28em; margin: 2em auto; padding: 0 1em; text-align: center; }
+  #word { font-size: 2.5em; margin: 0.5em 0; }
+  input, button { font-size: 1.2em; padding: 0.3em 0.6em; }
+  #verdict { min-height: 1.5em; margin-top: 1em; }
+</style>
+</head>
+<body>
+<div id="due"></div>
+<div id="word">…</div>
+<form id="form">
+  <input id="answer" autocomplete="off" autocapitalize="off" placeholder="translation">
+  <button>Check</button>
+</form>
+<div id="verdict"></div>
+<script>
+const token = new URLSearchParams(location.search).get("token");
+const headers = token ? { "Authorization": "Bearer " + token } : {};
+let card = null;
+
+async function next() {
+  const res = await fetch("/due", { headers });
+  const data = await res.json();
+  card = data.card;
+  document.getElementById("due").textContent = data.due + " due";
+  document.getElementById("word").textContent = card ? card.foreign : "Nothing due 🎉";
+  document.getElementById("answer").value = "";
+  document.getElementById("answer").focus();
+}
+
+document.getElementById("form").addEventListener("submit", async (e) => {
+  e.preventDefault();
+  if (!card) return next();
+  const answer = document.getElementById("answer").value;
+  const res = await fetch("/answer", {
+    method: "POST",
+    headers,
+    body: JSON.stringify({ id: card.id, answer }),
+  });
+  const data = await res.json();
+  document.getElementById("verdict").textContent = data.error
+    ? data.error
+    : (data.correct ? "✅ " : "❌ ") + data.expected + " (level " + data.level + ")";
+  next();
+});
+
+next();
+</script>
+</body>
+</html>
+"#;
+
+// The API's own quiz, apart from whatever the window is asking
+#[derive(Default)]
+struct ApiQuiz {
+    session: u64,                  // review log session of API answers, 0 = none yet
+    asked: Option<(u64, Instant)>, // word id /due handed out last, for the response time
+}
+
+#[derive(Deserialize)]
+struct ApiAnswer {
+    id: u64,
+    answer: String,
+}
+
+// ------------------- AnkiConnect -------------------
+const ANKI_CONNECT_URL: &str = "http://127.0.0.1:8765";
+
+// Copying words into a running Anki through the AnkiConnect add-on. Anki may be slow or
+// closed, so every request runs on its own thread and reports back through a channel.
+struct AnkiPush {
+    anki_decks: Vec<String>,
+    deck: String,
+    include_archived: bool,
+    status: Option<String>,
+    decks_rx: Option<Receiver<Vec<String>>>,
+    sending: Option<Receiver<Result<(usize, usize), String>>>,
+}
+
+// Runs `job` off the UI thread and wakes the UI with its result
+fn spawn_with_result<T: Send + 'static>(
+    ctx: Option<egui::Context>,
+    job: impl FnOnce() -> T + Send + 'static,
+) -> Receiver<T> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(job());
+        if let Some(ctx) = ctx {
+            ctx.request_repaint();
+        }
+    });
+    rx
+}
+
+// Anki fields are HTML, so text going into one must not be read as markup
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("<br>"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn anki_connect(action: &str, params: serde_json::Value) -> Result<serde_json::Value, String> {
+    let request = serde_json::json!({ "action": action, "version": 6, "params": params });
+    let response: serde_json::Value = ureq::post(ANKI_CONNECT_URL)
+        .timeout(Duration::from_secs(30))
+        .send_json(request)
+        .map_err(|e| format!("couldn't reach AnkiConnect (is Anki running?): {}", e))?
+        .into_json()
+        .map_err(|e| e.to_string())?;
+    match response.get("error").and_then(|e| e.as_str()) {
+        Some(error) => Err(error.to_string()),
+        None => Ok(response["result"].clone()),
+    }
+}
+
+// Adds one Basic note per word; returns (added, skipped) where skipped notes were
+// already in Anki
+fn push_words_to_anki(deck: &str, words: &[Word]) -> Result<(usize, usize), String> {
+    anki_connect("createDeck", serde_json::json!({ "deck": deck }))?;
+    let notes: Vec<_> = words
+        .iter()
+        .map(|w| {
+            let back = if w.notes.is_empty() {
+                escape_html(&w.translation)
+            } else {
+                format!(
+                    "{}<br><i>{}</i>",
+                    escape_html(&w.translation),
+                    escape_html(&w.notes)
+                )
+            };
+            let mut tags = w.tags.clone();
+            tags.push("woro".to_string());
+            serde_json::json!({
+                "deckName": deck,
+                "modelName": "Basic",
+                "fields": { "Front": escape_html(&w.foreign), "Back": back },
+                "tags": tags,
+                "options": { "allowDuplicate": false },
+            })
+        })
+        .collect();
+
+    // addNotes fails as a whole on a duplicate, so sort those out first
+    let can_add = anki_connect("canAddNotes", serde_json::json!({ "notes": notes }))?;
+    let notes: Vec<_> = notes
+        .into_iter()
+        .zip(can_add.as_array().into_iter().flatten())
+        .filter(|(_, ok)| ok.as_bool() == Some(true))
+        .map(|(note, _)| note)
+        .collect();
+    let skipped = words.len() - notes.len();
+    if notes.is_empty() {
+        return Ok((0, skipped));
+    }
+
+    let ids = anki_connect("addNotes", serde_json::json!({ "notes": notes }))?;
+    let added = ids
+        .as_array()
+        .map_or(0, |ids| ids.iter().filter(|id| !id.is_null()).count());
+    Ok((added, skipped + notes.len() - added))
+}
+
+// ------------------- UI Screens -------------------
+impl App {
+    fn add_words_screen(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Add New Words");
+        ui.add_space(10.0);
+
+        // File import
+        ui.horizontal(|ui| {
+            if ui.button("📁 Import from TXT").clicked() {
+                self.import_from_txt();
+            }
+            if ui.button("📊 Import CSV / Excel").clicked() {
+                self.import_spreadsheet();
+            }
+            if ui.button("🃏 Import Anki deck").clicked() {
+                self.import_anki();
+            }
+            if ui
+                .button("🔗 Import from code")
+                .on_hover_text("A code from someone's Share as QR, or a link to a word list")
+                .clicked()
+            {
+                self.code_import = Some(String::new());
+            }
+            if self.ocr_run.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("📷 Reading the photo…");
+                });
+            } else if ui
+                .add_enabled(
+                    !self.settings.ocr_command.trim().is_empty(),
+                    egui::Button::new("📷 Import from photo"),
+                )
+                .on_hover_text("Read a photo of a vocabulary list with the OCR command in Settings")
+                .clicked()
+            {
+                self.import_from_photo();
+            }
+            if ui
+                .button("📖 Import Kindle vocab")
+                .on_hover_text("vocab.db from the Kindle's system folder; creates a new deck")
+                .clicked()
+            {
+                self.import_kindle();
+            }
+            if ui
+                .button("🎬 Mine subtitles")
+                .on_hover_text("List the most frequent words of an .srt file you don't have yet")
+                .clicked()
+            {
+                self.mine_subtitles();
+            }
+            let capturing = self.capture.is_some();
+            if ui
+                .selectable_label(capturing, "📋 Capture from clipboard")
+                .on_hover_text(
+                    "While on, words and short phrases you copy anywhere land in the inbox",
+                )
+                .clicked()
+            {
+                self.toggle_capture();
+            }
+        });
+
+        if !self.inbox.is_empty() || self.capture.is_some() {
+            ui.add_space(6.0);
+            self.inbox_panel(ui);
+        }
+
+        ui.add_space(10.0);
+        egui::Grid::new("add_word_grid")
+            .num_columns(2)
+            .spacing([10.0, 8.0])
+            .show(ui, |ui| {
+                ui.label("Foreign word:");
+                ui.text_edit_singleline(&mut self.new_foreign);
+                ui.end_row();
+
+                ui.label("Translation:");
+                let response = ui.text_edit_singleline(&mut self.new_translation);
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    self.add_word();
+                }
+                ui.end_row();
+            });
+
+        ui.add_space(8.0);
+        if ui.button("➕ Add Word").clicked() {
+            self.add_word();
+        }
+
+        if self.words.iter().any(|w| !w.archived)
+            && ui.button(self.game_button_label("🎮 Go to Game")).clicked()
+        {
+            self.start_game(SessionKind::All);
+        }
+
+        ui.separator();
+        ui.heading("Your Words");
+
+        if self.words.is_empty() {
+            ui.label("No words yet. Add some words to start learning!");
+        } else {
+            let mut practice_failed = false;
+            let mut review_stale = false;
+            let mut cognate_action = None; // Some(true) = archive, Some(false) = level up
+            let mut archive_mastered = false;
+            let mut start_maintenance = false;
+            let mastered_active = self
+                .words
+                .iter()
+                .filter(|w| !w.archived && w.level >= MAX_LEVEL)
+                .count();
+            ui.horizontal(|ui| {
+                ui.label("Show:");
+                ui.selectable_value(&mut self.word_view, WordView::All, "All");
+                ui.selectable_value(
+                    &mut self.word_view,
+                    WordView::RecentlyAdded,
+                    "🆕 Recently added",
+                );
+                ui.selectable_value(
+                    &mut self.word_view,
+                    WordView::RecentlyFailed,
+                    "❌ Recently failed",
+                );
+                ui.selectable_value(
+                    &mut self.word_view,
+                    WordView::NotReviewed,
+                    "⏳ Not reviewed",
+                );
+                if self
+                    .words
+                    .iter()
+                    .any(|w| w.tags.iter().any(|t| t == COGNATE_TAG))
+                {
+                    ui.selectable_value(&mut self.word_view, WordView::Cognates, "🆓 Cognates")
+                        .on_hover_text("Words that look almost the same as their translation");
+                }
+                ui.selectable_value(&mut self.word_view, WordView::Archived, "📦 Archive");
+                if self.word_view == WordView::Archived
+                    && ui
+                        .button("🧹 Maintenance review")
+                        .on_hover_text("Quiz a few mastered words; forgotten ones come back")
+                        .clicked()
+                {
+                    start_maintenance = true;
+                }
+                if self.word_view == WordView::All
+                    && mastered_active > 0
+                    && ui
+                        .button(format!("📦 Archive mastered ({})", mastered_active))
+                        .clicked()
+                {
+                    archive_mastered = true;
+                }
+                if self.word_view == WordView::RecentlyAdded {
+                    ui.label("in the last");
+                    ui.add(
+                        egui::DragValue::new(&mut self.recent_days)
+                            .clamp_range(1..=365)
+                            .suffix(" days"),
+                    );
+                }
+                if self.word_view == WordView::NotReviewed {
+                    ui.label("in");
+                    ui.add(
+                        egui::DragValue::new(&mut self.stale_days)
+                            .clamp_range(1..=3650)
+                            .suffix(" days"),
+                    );
+                    if ui
+                        .button("⏳ Review oldest first")
+                        .on_hover_text(
+                            "Whatever their level, the longest-unreviewed words come first",
+                        )
+                        .clicked()
+                    {
+                        review_stale = true;
+                    }
+                }
+                if self.word_view == WordView::RecentlyFailed
+                    && !self.recently_failed.is_empty()
+                    && ui.button("🎯 Practice these").clicked()
+                {
+                    practice_failed = true;
+                }
+                if self.word_view == WordView::Cognates {
+                    if ui
+                        .button("📦 Skip all")
+                        .on_hover_text("Archive them; they won't be asked")
+                        .clicked()
+                    {
+                        cognate_action = Some(true);
+                    }
+                    if ui
+                        .button("⏩ Ask less")
+                        .on_hover_text(format!(
+                            "Start them at level {}, one right answer from mastered",
+                            MAX_LEVEL - 1
+                        ))
+                        .clicked()
+                    {
+                        cognate_action = Some(false);
+                    }
+                }
+            });
+            if let Some(archive) = cognate_action {
+                for word in self
+                    .words
+                    .iter_mut()
+                    .filter(|w| !w.archived && w.tags.iter().any(|t| t == COGNATE_TAG))
+                {
+                    if archive {
+                        word.archived = true;
+                    } else {
+                        word.level = word.level.max(MAX_LEVEL - 1);
+                    }
+                }
+                self.mark_dirty();
+            }
+            if practice_failed {
+                self.start_game(SessionKind::RecentlyFailed);
+            }
+            if review_stale {
+                self.start_game(SessionKind::Stale);
+            }
+            if start_maintenance {
+                self.start_maintenance();
+            }
+            if archive_mastered {
+                for word in self.words.iter_mut().filter(|w| w.level >= MAX_LEVEL) {
+                    word.archived = true;
+                }
+                self.mark_dirty();
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("🔍");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.search_query)
+                        .hint_text("Search words, translations, notes, tags"),
+                );
+                ui.selectable_value(&mut self.word_table, false, "☰ List");
+                ui.selectable_value(&mut self.word_table, true, "▦ Table");
+            });
+
+            let mut hits: Vec<(usize, SearchHit)> = self
+                .words
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| self.in_word_view(w))
+                .filter_map(|(i, w)| fuzzy_search(&self.search_query, w).map(|hit| (i, hit)))
+                .collect();
+            if !self.search_query.trim().is_empty() {
+                hits.sort_by_key(|(_, hit)| std::cmp::Reverse(hit.score));
+            }
+
+            if hits.is_empty() {
+                ui.label("No matching words.");
+            } else {
+                let mut set_level = false;
+                ui.horizontal(|ui| {
+                    ui.label(format!("Set all {} shown words to level", hits.len()));
+                    ui.add(egui::DragValue::new(&mut self.bulk_level).clamp_range(1..=MAX_LEVEL));
+                    set_level = ui
+                        .button("Apply")
+                        .on_hover_text("e.g. mark a chapter you already know as mastered")
+                        .clicked();
+                });
+                if set_level {
+                    let level = self.bulk_level;
+                    for (i, _) in &hits {
+                        let word = &mut self.words[*i];
+                        if word.level == level {
+                            continue;
+                        }
+                        word.level = level;
+                        word.mastered_streak = 0;
+                        word.mastered_since = if level >= MAX_LEVEL { now_secs() } else { 0 };
+                    }
+                    self.mark_dirty();
+                }
+            }
+
+            if self.word_table {
+                let rows: Vec<usize> = hits.iter().map(|(i, _)| *i).collect();
+                self.word_table(ui, rows);
+                return;
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    let mut to_delete: Option<usize> = None;
+                    let mut to_edit: Option<usize> = None;
+                    let mut to_toggle_archive: Option<usize> = None;
+                    let mut row_action: Option<(usize, WordRowAction)> = None;
+                    let formats = highlight_formats(ui);
+
+                    for (i, hit) in &hits {
+                        let word = &self.words[*i];
+                        ui.horizontal(|ui| {
+                            let mut job = egui::text::LayoutJob::default();
+                            job.append("🔹 ", 0.0, formats.0.clone());
+                            append_highlighted(&mut job, &word.foreign, &hit.foreign, &formats);
+                            job.append(" = ", 0.0, formats.0.clone());
+                            append_highlighted(
+                                &mut job,
+                                &word.translation,
+                                &hit.translation,
+                                &formats,
+                            );
+                            let mut entry =
+                                ui.add(egui::Label::new(job).sense(egui::Sense::click()));
+                            if !word.notes.is_empty() {
+                                entry = entry.on_hover_ui(|ui| {
+                                    markdown_view(ui, &word.notes, 14.0, true);
+                                });
+                            }
+                            entry.context_menu(|ui| {
+                                if let Some(action) = word_row_menu(ui, word) {
+                                    row_action = Some((*i, action));
+                                }
+                            });
+                            level_badge(ui, word.level);
+                            if is_snoozed(word, now_secs()) {
+                                ui.weak("💤").on_hover_text(format!(
+                                    "Snoozed until {}",
+                                    format_day(word.snoozed_until / SECS_PER_DAY)
+                                ));
+                            }
+                            frequency_badge(ui, &self.frequency, &word.foreign);
+                            if !word.tags.is_empty() {
+                                let mut job = egui::text::LayoutJob::default();
+                                job.append("🏷 ", 0.0, formats.0.clone());
+                                append_highlighted(
+                                    &mut job,
+                                    &word.tags.join(", "),
+                                    &hit.tags,
+                                    &formats,
+                                );
+                                ui.label(job);
+                            }
+                            if word.archived {
+                                if ui.button("↩ Unarchive").clicked() {
+                                    to_toggle_archive = Some(*i);
+                                }
+                            } else if word.level >= MAX_LEVEL && ui.button("📦 Archive").clicked()
+                            {
+                                to_toggle_archive = Some(*i);
+                            }
+                            if ui.button("✏ Edit").clicked() {
+                                to_edit = Some(*i);
+                            }
+                            if ui.button("🗑 Delete").clicked() {
+                                to_delete = Some(*i);
+                            }
+                        });
+
+                        // Surface the note when the match came from it
+                        if hit.notes.contains(&true) {
+                            let mut job = egui::text::LayoutJob::default();
+                            job.append("    📝 ", 0.0, formats.0.clone());
+                            append_highlighted(&mut job, &word.notes, &hit.notes, &formats);
+                            ui.label(job);
+                        }
+                    }
+
+                    if let Some(index) = to_edit {
+                        self.edit_word(index);
+                    }
+                    if let Some((index, action)) = row_action {
+                        self.word_row_action(index, action);
+                    }
+                    if let Some(index) = to_toggle_archive {
+                        self.words[index].archived = !self.words[index].archived;
+                        self.mark_dirty();
+                    }
+                    if let Some(index) = to_delete {
+                        let removed = self.words.remove(index);
+                        // The next word added may get its id
+                        self.learning.remove(&removed.id);
+                        self.previewed.remove(&removed.id);
+                        self.word_editor = None;
+                        self.mark_dirty();
+                    }
+                });
+        }
+    }
+
+    // Sortable table of the given words with their dates; click a header to sort
+    fn word_table(&mut self, ui: &mut egui::Ui, mut rows: Vec<usize>) {
+        use egui_extras::{Column, TableBuilder};
+
+        if let Some((column, ascending)) = self.table_sort {
+            let words = &self.words;
+            rows.sort_by(|&a, &b| {
+                let (a, b) = (&words[a], &words[b]);
+                let order = match column {
+                    WordColumn::Foreign => a.foreign.to_lowercase().cmp(&b.foreign.to_lowercase()),
+                    WordColumn::Translation => a
+                        .translation
+                        .to_lowercase()
+                        .cmp(&b.translation.to_lowercase()),
+                    WordColumn::Level => a.level.cmp(&b.level),
+                    WordColumn::Created => a.created_at.cmp(&b.created_at),
+                    WordColumn::LastReviewed => a.last_reviewed_at.cmp(&b.last_reviewed_at),
+                };
+                if ascending {
+                    order
+                } else {
+                    order.reverse()
+                }
+            });
+        }
+
+        let date = |secs: u64| {
+            if secs == 0 {
+                "—".to_string()
+            } else {
+                format_day(secs / SECS_PER_DAY)
+            }
+        };
+        let mut sort_by = None;
+        let mut to_edit = None;
+        let mut row_action = None;
+        TableBuilder::new(ui)
+            .striped(true)
+            .max_scroll_height(320.0)
+            .column(
+                Column::initial(self.word_column_widths[0])
+                    .at_least(60.0)
+                    .resizable(true),
+            )
+            .column(
+                Column::initial(self.word_column_widths[1])
+                    .at_least(60.0)
+                    .resizable(true),
+            )
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                for (i, (column, title)) in [
+                    (WordColumn::Foreign, "Word"),
+                    (WordColumn::Translation, "Translation"),
+                    (WordColumn::Level, "Level"),
+                    (WordColumn::Created, "Added"),
+                    (WordColumn::LastReviewed, "Last reviewed"),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    header.col(|ui| {
+                        // Remember dragged widths so they survive a restart
+                        if let Some(width) = self.word_column_widths.get_mut(i) {
+                            *width = ui.max_rect().width();
+                        }
+                        let arrow = match self.table_sort {
+                            Some((c, true)) if c == column => " ⏶",
+                            Some((c, false)) if c == column => " ⏷",
+                            _ => "",
+                        };
+                        if ui.button(format!("{}{}", title, arrow)).clicked() {
+                            sort_by = Some(column);
+                        }
+                    });
+                }
+                header.col(|_| {});
+            })
+            .body(|body| {
+                body.rows(self.layout.row_height(), rows.len(), |mut row| {
+                    let index = rows[row.index()];
+                    let word = &self.words[index];
+                    row.col(|ui| {
+                        ui.add(egui::Label::new(&word.foreign).sense(egui::Sense::click()))
+                            .on_hover_text("Right-click for more")
+                            .context_menu(|ui| {
+                                if let Some(action) = word_row_menu(ui, word) {
+                                    row_action = Some((index, action));
+                                }
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.label(&word.translation);
+                    });
+                    row.col(|ui| {
+                        level_badge(ui, word.level);
+                    });
+                    row.col(|ui| {
+                        ui.label(date(word.created_at));
+                    });
+                    row.col(|ui| {
+                        ui.label(date(word.last_reviewed_at));
+                    });
+                    row.col(|ui| {
+                        if ui.small_button("✏").clicked() {
+                            to_edit = Some(index);
+                        }
+                    });
+                });
+            });
+
+        if let Some(column) = sort_by {
+            // Same column again flips the direction
+            self.table_sort = match self.table_sort {
+                Some((c, ascending)) if c == column => Some((column, !ascending)),
+                _ => Some((column, true)),
+            };
+        }
+        if let Some(index) = to_edit {
+            self.edit_word(index);
+        }
+        if let Some((index, action)) = row_action {
+            self.word_row_action(index, action);
+        }
+    }
+
+    fn word_row_action(&mut self, index: usize, action: WordRowAction) {
+        match action {
+            WordRowAction::Edit => self.edit_word(index),
+            WordRowAction::ResetLevel => {
+                let word = &mut self.words[index];
+                word.level = 1;
+                word.mastered_streak = 0;
+                word.mastered_since = 0;
+                self.mark_dirty();
+            }
+        }
+    }
+
+    fn game_screen(&mut self, ui: &mut egui::Ui) {
+        if self.words.is_empty() {
+            ui.heading("No words yet!");
+            ui.label("Go to 'Add Words' and add some words first.");
+            return;
+        }
+
+        ui.heading("🎮 Game Mode");
+        match &self.session {
+            SessionKind::All => {}
+            SessionKind::Tagged(tag) => {
+                ui.label(format!("🏷 Only words tagged \"{}\"", tag));
+            }
+            SessionKind::RecentlyFailed => {
+                ui.label("🎯 Words you recently got wrong");
+            }
+            SessionKind::Maintenance => {
+                ui.label(format!(
+                    "🧹 Maintenance review: {} words left",
+                    self.maintenance_queue.len()
+                ));
+            }
+            SessionKind::Custom { counts } => {
+                ui.label(format!(
+                    "🧪 Custom session{}: {} words left",
+                    if *counts {
+                        ""
+                    } else {
+                        " (practice, levels unchanged)"
+                    },
+                    self.custom_queue.len()
+                ));
+            }
+            SessionKind::Stale => {
+                ui.label(format!(
+                    "⏳ Not reviewed in {} days, oldest first: {} words left",
+                    self.stale_days,
+                    self.session_pool().len()
+                ));
+            }
+        }
+
+        // The last answer of a session gets its result shown before the summary
+        let answered = self.question == QuestionState::ShowingResult;
+        if !answered && self.session == SessionKind::Stale && self.session_pool().is_empty() {
+            ui.add_space(10.0);
+            ui.label(format!(
+                "✨ Done! Every word has been reviewed in the last {} days.",
+                self.stale_days
+            ));
+            self.session_summary(ui);
+            if !self.feedback_message.is_empty() {
+                markdown_view(ui, &self.feedback_message, 14.0, !self.hide_readings);
+            }
+            ui.add_space(8.0);
+            if ui.button("➕ Back to words").clicked() {
+                self.screen = Screen::AddWords;
+            }
+            return;
+        }
+
+        if let SessionKind::Custom { counts } = self.session {
+            // A word snoozed since it started is left out too
+            if !answered && (self.custom_queue.is_empty() || self.session_pool().is_empty()) {
+                ui.add_space(10.0);
+                ui.label(format!(
+                    "Done! {} right, {} wrong.",
+                    self.custom_right, self.custom_wrong
+                ));
+                self.session_summary(ui);
+                if !counts {
+                    ui.weak("Practice run: your levels weren't changed.");
+                }
+                if !self.feedback_message.is_empty() {
+                    markdown_view(ui, &self.feedback_message, 14.0, !self.hide_readings);
+                }
+                ui.add_space(8.0);
+                if ui.button("➕ Back to words").clicked() {
+                    self.screen = Screen::AddWords;
+                }
+                return;
+            }
+        }
+
+        if !answered
+            && self.session == SessionKind::Maintenance
+            && self.maintenance_queue.is_empty()
+        {
+            ui.add_space(10.0);
+            ui.label(format!(
+                "Done! {} words still remembered, {} back in rotation.",
+                self.maintenance_kept, self.maintenance_returned
+            ));
+            self.session_summary(ui);
+            if !self.feedback_message.is_empty() {
+                markdown_view(ui, &self.feedback_message, 14.0, !self.hide_readings);
+            }
+            ui.add_space(8.0);
+            if ui.button("➕ Back to words").clicked() {
+                self.screen = Screen::AddWords;
+            }
+            return;
+        }
+        ui.add_space(6.0);
+
+        // Progress (archived words are done and don't count)
+        let mut counts = [0usize; MAX_LEVEL as usize];
+        for word in self.words.iter().filter(|w| !w.archived) {
+            counts[word.level.clamp(1, MAX_LEVEL) as usize - 1] += 1;
+        }
+        let mastered = counts[MAX_LEVEL as usize - 1];
+        let total: usize = counts.iter().sum();
+        ui.horizontal(|ui| {
+            ui.label("Mastery:");
+            level_bar(
+                ui,
+                &counts,
+                &format!("{}/{} mastered", mastered, total),
+                220.0,
+            );
+        });
+        self.level_histogram(ui);
+
+        ui.separator();
+        ui.add_space(10.0);
+
+        if self.modes.len() > 1 {
+            let before = self.mode_index;
+            ui.horizontal(|ui| {
+                for (i, mode) in self.modes.iter().enumerate() {
+                    ui.selectable_value(&mut self.mode_index, i, mode.name());
+                }
+            });
+            // The word may not fit the new mode, and cloze cards keep their own schedule
+            if self.mode_index != before {
+                self.next_question();
+            }
+            ui.add_space(6.0);
+        }
+        if self.words.iter().any(|w| has_ruby(&w.foreign)) {
+            ui.checkbox(&mut self.hide_readings, "Hide readings")
+                .on_hover_text("Leave out the furigana over the words");
+        }
+
+        let word = &self.words[self.current_word_index];
+        if self.settings.preview_new_words
+            && self.question == QuestionState::Asking
+            && self.hands_free.is_none()
+            && word.last_reviewed_at == 0
+            && !self.previewed.contains(&word.id)
+        {
+            self.preview_new_word(ui);
+            return;
+        }
+        let mode = &self.modes[self.mode_index];
+        mode.present(ui, word, !self.hide_readings);
+        if !word.image.is_empty() {
+            let path = self.sidecar_path("media").join(&word.image);
+            match self.media_cache.get(ui.ctx(), word.id, &path) {
+                Ok(Some(texture)) => {
+                    ui.add(
+                        egui::Image::from_texture(egui::load::SizedTexture::from_handle(&texture))
+                            .max_height(200.0),
+                    );
+                }
+                Ok(None) => {
+                    ui.spinner();
+                }
+                Err(e) => {
+                    ui.colored_label(egui::Color32::RED, "🖼 ⚠ Picture can't be shown")
+                        .on_hover_text(e);
+                }
+            }
+        }
+        let mut play = None;
+        ui.horizontal(|ui| {
+            level_badge(ui, word.level).on_hover_text("Level");
+            if word.speaking_level > 0 {
+                ui.label(format!("🎤 {}", word.speaking_level))
+                    .on_hover_text("Speaking level");
+            }
+            frequency_badge(ui, &self.frequency, &word.foreign);
+            if ui.small_button("🔊").on_hover_text("Say it").clicked() {
+                play = Some(self.deck_settings.foreign_voice());
+            }
+            if ui
+                .small_button("🐢")
+                .on_hover_text("Say it slowly")
+                .clicked()
+            {
+                play = Some(self.deck_settings.foreign_voice().slow());
+            }
+        });
+        if let Some(voice) = play {
+            let text = ruby_base(&word.foreign);
+            self.say(&text, voice);
+        }
+        let word = &self.words[self.current_word_index];
+        // The writing exercise asks for the word, so the panel would give it away
+        if self.modes[self.mode_index].kind() != ReviewMode::Handwrite {
+            self.stroke_order_panel(ui, &ruby_base(&word.foreign));
+        }
+
+        ui.add_space(12.0);
+        if let Some(hands_free) = &self.hands_free {
+            let step = match hands_free.step {
+                HandsFreeStep::Asking => "🔊 …",
+                HandsFreeStep::Thinking => "🤔 Think of the translation…",
+                HandsFreeStep::Telling | HandsFreeStep::Waiting => word.translation.as_str(),
+            };
+            ui.label(egui::RichText::new(step).size(20.0));
+            if hands_free.voice {
+                ui.weak(
+                    "Space / Enter or say \"correct\": I knew it · Backspace or \"again\": again",
+                );
+            } else {
+                ui.weak("Space / Enter: I knew it · Backspace: again");
+            }
+            let mut stop = false;
+            ui.horizontal(|ui| {
+                stop = ui.button("⏹ Stop hands-free").clicked();
+            });
+            if !self.feedback_message.is_empty() {
+                markdown_view(ui, &self.feedback_message, 14.0, !self.hide_readings);
+            }
+            if stop {
+                self.stop_hands_free();
+            }
+            return;
+        }
+        if ui
+            .button("🎧 Hands-free")
+            .on_hover_text("Hear the word, then its translation; grade yourself with a key")
+            .clicked()
+        {
+            self.start_hands_free();
+        }
+        if ui
+            .button("🎬 Present")
+            .on_hover_text("Cycle through the words full screen, no answers: for a projector")
+            .clicked()
+        {
+            self.start_presentation();
+        }
+        if self.question == QuestionState::ShowingResult {
+            ui.label(format!("Your answer: {}", self.user_answer));
+            ui.add_space(10.0);
+            markdown_view(ui, &self.feedback_message, 14.0, !self.hide_readings);
+            self.modes[self.mode_index].feedback(ui);
+            let next =
+                ui.button("▶ Next").clicked() || ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if next {
+                self.question = QuestionState::Next;
+                self.step_question();
+            }
+            return;
+        }
+        if self.question == QuestionState::SecondChance {
+            if let Some(sentence) = cloze_sentence(&self.words[self.current_word_index]) {
+                // Asked for the word itself, the sentence mustn't give it away
+                let word = &self.words[self.current_word_index];
+                let sentence = blank_out(&sentence, &self.modes[self.mode_index].expected(word));
+                ui.label("💬");
+                ruby_label(ui, &sentence, 20.0, !self.hide_readings);
+                ui.add_space(6.0);
+            }
+        }
+        let mode = &self.modes[self.mode_index];
+        let input = mode.input(ui, &mut self.user_answer);
+        if let Some(e) = input.error {
+            self.notify_error(e);
+        }
+
+        // Clear feedback on input change
+        if input.changed {
+            self.feedback_message.clear();
+        }
+
+        if input.submitted {
+            self.check_answer();
+            self.step_question();
+        }
+
+        ui.add_space(10.0);
+        if !self.feedback_message.is_empty() {
+            markdown_view(ui, &self.feedback_message, 14.0, !self.hide_readings);
+            self.modes[self.mode_index].feedback(ui);
+        }
+    }
+
+    // A never-seen word gets shown with its answer first, rather than asked blind
+    fn preview_new_word(&mut self, ui: &mut egui::Ui) {
+        let word = &self.words[self.current_word_index];
+        ui.label("🆕 New word");
+        ruby_label(ui, &word.foreign, 48.0, !self.hide_readings);
+        ui.label(egui::RichText::new(&word.translation).size(28.0));
+        markdown_view(ui, &word.notes, 16.0, !self.hide_readings);
+        ui.add_space(12.0);
+        let mut play = false;
+        let mut next = false;
+        ui.horizontal(|ui| {
+            play = ui.button("🔊").on_hover_text("Say it").clicked();
+            next = ui.button("▶ Got it").clicked();
+        });
+        ui.weak("Enter: got it");
+        next |= ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if play {
+            let text = ruby_base(&word.foreign);
+            self.say(&text, self.deck_settings.foreign_voice());
+        }
+        if next {
+            self.previewed
+                .insert(self.words[self.current_word_index].id);
+            self.pick_random_word();
+        }
+    }
+
+    fn nav_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(self.screen == Screen::AddWords, "➕ Words")
+                .clicked()
+            {
+                self.screen = Screen::AddWords;
+            }
+            let in_game = matches!(self.screen, Screen::Game | Screen::End);
+            if ui
+                .add_enabled(
+                    !self.words.is_empty(),
+                    egui::SelectableLabel::new(in_game, self.game_button_label("🎮 Game")),
+                )
+                .clicked()
+                && !in_game
+            {
+                self.start_game(SessionKind::All);
+            }
+            if ui
+                .selectable_label(self.screen == Screen::Stats, "📈 Stats")
+                .clicked()
+            {
+                self.screen = Screen::Stats;
+            }
+            if ui
+                .selectable_label(self.screen == Screen::Insights, "💡 Insights")
+                .clicked()
+            {
+                self.screen = Screen::Insights;
+            }
+            if ui
+                .selectable_label(self.screen == Screen::Settings, "⚙ Settings")
+                .clicked()
+            {
+                self.screen = Screen::Settings;
+            }
+
+            let mut action = None;
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let unsaved = if self.has_unsaved_changes() {
+                    "● "
+                } else {
+                    ""
+                };
+                let deck_label = format!("📂 {}{}", unsaved, deck_name(&self.deck_path));
+                ui.menu_button(deck_label, |ui| {
+                    for deck in self.recent_decks.iter().filter(|d| **d != self.deck_path) {
+                        if ui.button(format!("↪ {}", deck_name(deck))).clicked() {
+                            action = Some(PaletteAction::SwitchDeck(deck.clone()));
+                        }
+                    }
+                    if self.recent_decks.len() > 1 {
+                        ui.separator();
+                    }
+                    if ui.button("📂 Open deck…").clicked() {
+                        action = Some(PaletteAction::OpenDeck);
+                    }
+                    if ui.button("🆕 New deck…").clicked() {
+                        action = Some(PaletteAction::NewDeck);
+                    }
+                    if ui.button("🔀 Merge from file…").clicked() {
+                        action = Some(PaletteAction::MergeDeck);
+                    }
+                    if ui.button("✂ Split deck…").clicked() {
+                        action = Some(PaletteAction::SplitDeck);
+                    }
+                    if ui.button("📱 Share as QR…").clicked() {
+                        action = Some(PaletteAction::ShareQr);
+                    }
+                    ui.separator();
+                    if ui
+                        .add_enabled(
+                            self.has_unsaved_changes(),
+                            egui::Button::new("💾 Save now").shortcut_text("Ctrl+S"),
+                        )
+                        .clicked()
+                    {
+                        action = Some(PaletteAction::SaveNow);
+                    }
+                    if action.is_some() {
+                        ui.close_menu();
+                    }
+                });
+            });
+            if let Some(action) = action {
+                self.run_palette_action(action);
+            }
+        });
+    }
+
+    fn settings_screen(&mut self, ui: &mut egui::Ui) {
+        ui.heading("⚙ Settings");
+        ui.add_space(6.0);
+
+        let mut changed = false;
+
+        ui.strong("🎨 Appearance");
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            for (theme, label) in [
+                (Theme::System, "System"),
+                (Theme::Dark, "🌙 Dark"),
+                (Theme::Light, "☀ Light"),
+            ] {
+                if ui.selectable_value(&mut self.theme, theme, label).changed() {
+                    self.theme_dirty = true;
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Accent:");
+            let before = self.accent;
+            ui.selectable_value(&mut self.accent, None, "Default");
+            for (name, rgb) in ACCENT_PRESETS {
+                let [r, g, b] = rgb;
+                let swatch = egui::RichText::new("⬤").color(egui::Color32::from_rgb(r, g, b));
+                ui.selectable_value(&mut self.accent, Some(rgb), swatch)
+                    .on_hover_text(name);
+            }
+            let mut custom = self.accent.unwrap_or(ACCENT_PRESETS[0].1);
+            if ui
+                .color_edit_button_srgb(&mut custom)
+                .on_hover_text("Pick your own")
+                .changed()
+            {
+                self.accent = Some(custom);
+            }
+            if self.accent != before {
+                self.theme_dirty = true;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Layout:");
+            for (layout, label) in [
+                (Layout::Normal, "Normal"),
+                (Layout::Compact, "🗜 Compact"),
+                (Layout::LargePrint, "🔍 Large print"),
+            ] {
+                if ui
+                    .selectable_value(&mut self.layout, layout, label)
+                    .changed()
+                {
+                    self.theme_dirty = true;
+                }
+            }
+        });
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut self.settings.api_enabled, "🌐 Serve an HTTP API on port")
+                .on_hover_text("A quiz page at /, and GET /deck, GET /due, POST /answer, GET /reviews. Takes effect the next time woro starts")
+                .changed();
+            changed |= ui
+                .add(egui::DragValue::new(&mut self.settings.api_port).clamp_range(1024..=65535))
+                .changed();
+        });
+        if self.settings.api_enabled {
+            ui.horizontal(|ui| {
+                ui.add_space(16.0);
+                if ui
+                    .checkbox(
+                        &mut self.settings.api_lan,
+                        "Reachable from other devices on the network",
+                    )
+                    .on_hover_text(
+                        "Otherwise only this computer can connect. Other devices need the \
+                         link below, which carries a secret token.",
+                    )
+                    .changed()
+                {
+                    if self.settings.api_lan && self.settings.api_token.is_empty() {
+                        self.settings.api_token =
+                            format!("{:032x}", rand::thread_rng().gen::<u128>());
+                    }
+                    changed = true;
+                }
+            });
+            let host = if self.settings.api_lan {
+                lan_address().map_or("<this computer>".to_string(), |ip| ip.to_string())
+            } else {
+                Ipv4Addr::LOCALHOST.to_string()
+            };
+            let mut link = format!("http://{}:{}/", host, self.settings.api_port);
+            if self.settings.api_lan {
+                link += &format!("?token={}", self.settings.api_token);
+            }
+            ui.horizontal(|ui| {
+                ui.add_space(16.0);
+                ui.label("Quiz page:");
+                ui.monospace(&link);
+                if ui.small_button("📋").on_hover_text("Copy").clicked() {
+                    ui.output_mut(|o| o.copied_text = link.clone());
+                }
+                if self.settings.api_lan && ui.small_button("New token").clicked() {
+                    self.settings.api_token = format!("{:032x}", rand::thread_rng().gen::<u128>());
+                    changed = true;
+                }
+            });
+        }
+        changed |= ui
+            .checkbox(
+                &mut self.settings.single_instance,
+                "Only run one woro at a time (launching again brings this window forward)",
+            )
+            .on_hover_text("Takes effect the next time woro starts")
+            .changed();
+        let mut storage = self.settings.storage;
+        ui.horizontal(|ui| {
+            ui.label("💾 Keep decks in:");
+            egui::ComboBox::from_id_source("storage_kind")
+                .selected_text(storage.name())
+                .show_ui(ui, |ui| {
+                    for kind in StorageKind::ALL {
+                        ui.selectable_value(&mut storage, kind, kind.name());
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "A SQLite database sits next to the deck file (<deck>.sqlite); the open \
+                     deck moves over right away",
+                );
+        });
+        if storage != self.settings.storage {
+            self.settings.storage = storage;
+            self.switch_storage();
+        }
+        ui.add_space(10.0);
+
+        let recognizer_edited = ui
+            .horizontal(|ui| {
+                ui.label("🎤 Speech recognition command:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.settings.speech_recognition_command)
+                        .hint_text("e.g. listen.sh {lang}")
+                        .desired_width(220.0),
+                )
+                .on_hover_text(
+                    "Records you and prints what it heard. Setting it adds a 🎤 Speak exercise \
+                     that scores your pronunciation, and hands-free review listens for \
+                     \"correct\" / \"again\".",
+                )
+                .lost_focus()
+            })
+            .inner;
+        changed |= recognizer_edited;
+        changed |= ui
+            .horizontal(|ui| {
+                ui.label("🔤 Conjugation command:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.settings.conjugation_command)
+                        .hint_text("e.g. conjugate.sh {word}")
+                        .desired_width(220.0),
+                )
+                .on_hover_text(
+                    "Prints a verb's forms as \"form = conjugated\" lines; the word editor \
+                     can fetch a table with it.",
+                )
+                .lost_focus()
+            })
+            .inner;
+        changed |= ui
+            .horizontal(|ui| {
+                ui.label("📷 OCR command:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.settings.ocr_command)
+                        .hint_text("e.g. tesseract {image} stdout -l spa+eng")
+                        .desired_width(220.0),
+                )
+                .on_hover_text(
+                    "Prints the text in a picture, for importing a photo of a vocabulary \
+                     list. Tesseract works offline; a script calling a cloud OCR service \
+                     works too.",
+                )
+                .lost_focus()
+            })
+            .inner;
+        ui.add_space(10.0);
+
+        ui.strong("📥 Import");
+        changed |= ui
+            .checkbox(
+                &mut self.settings.import_overwrite_translations,
+                "Replace the translation of words that are already in the deck",
+            )
+            .on_hover_text("Either way they keep their level and review history")
+            .changed();
+        ui.add_space(10.0);
+
+        ui.strong(format!("📂 This deck ({})", deck_name(&self.deck_path)));
+        let edited = ui
+            .horizontal(|ui| {
+                ui.label("Answer pattern:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.deck_settings.answer_pattern)
+                        .hint_text("(to )?{}")
+                        .desired_width(200.0),
+                )
+                .on_hover_text(
+                    "A regular expression the whole answer must match; {} stands for the \
+                     expected translation. Leave empty for exact answers only.",
+                )
+                .lost_focus()
+            })
+            .inner;
+        if edited {
+            self.save_deck_settings();
+        }
+
+        let mut scheduler = self.deck_settings.scheduler;
+        ui.horizontal(|ui| {
+            ui.label("Scheduler:");
+            egui::ComboBox::from_id_source("deck_scheduler")
+                .selected_text(scheduler.name())
+                .show_ui(ui, |ui| {
+                    for kind in SchedulerKind::ALL {
+                        ui.selectable_value(&mut scheduler, kind, kind.name());
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Levels: woro's review intervals above. SM-2: intervals that grow with \
+                     each right answer, per word. Leitner boxes: a miss goes back to box 1.",
+                );
+        });
+        if scheduler != self.deck_settings.scheduler {
+            self.deck_settings.scheduler = scheduler;
+            self.save_deck_settings();
+        }
+
+        let mut direction = self.deck_settings.direction;
+        ui.horizontal(|ui| {
+            ui.label("Asks:");
+            egui::ComboBox::from_id_source("deck_direction")
+                .selected_text(direction.name())
+                .show_ui(ui, |ui| {
+                    for d in Direction::ALL {
+                        ui.selectable_value(&mut direction, d, d.name());
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Which way the Translate exercise goes: recognising the word, producing \
+                     it, or a mix",
+                );
+        });
+        if direction != self.deck_settings.direction {
+            self.deck_settings.direction = direction;
+            self.save_deck_settings();
+            self.rebuild_modes();
+        }
+
+        let edited = ui
+            .horizontal(|ui| {
+                ui.label("Speech languages:");
+                let foreign = ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.deck_settings.foreign_language)
+                            .hint_text("words, e.g. es")
+                            .desired_width(90.0),
+                    )
+                    .lost_focus();
+                let native = ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.deck_settings.native_language)
+                            .hint_text("translations, e.g. en")
+                            .desired_width(90.0),
+                    )
+                    .lost_focus();
+                foreign || native
+            })
+            .inner;
+        if edited {
+            self.save_deck_settings();
+            self.rebuild_modes();
+        }
+        let mut test_voice = false;
+        let edited = ui
+            .horizontal(|ui| {
+                ui.label("Speech rate:");
+                let rate = ui.add(
+                    egui::DragValue::new(&mut self.deck_settings.speech_rate)
+                        .clamp_range(80..=450)
+                        .suffix(" wpm"),
+                );
+                ui.label("pitch:");
+                let pitch = ui.add(
+                    egui::DragValue::new(&mut self.deck_settings.speech_pitch).clamp_range(0..=99),
+                );
+                test_voice = ui.button("▶ Test").clicked();
+                rate.drag_stopped()
+                    || rate.lost_focus()
+                    || pitch.drag_stopped()
+                    || pitch.lost_focus()
+            })
+            .inner;
+        if edited {
+            self.save_deck_settings();
+        }
+        if test_voice {
+            let sample = self
+                .words
+                .first()
+                .map_or("woro", |w| w.foreign.as_str())
+                .to_string();
+            self.say(&sample, self.deck_settings.foreign_voice());
+        }
+
+        let mut frequency_changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Frequency list:");
+            match &self.deck_settings.frequency_list {
+                Some(path) => {
+                    ui.label(format!(
+                        "{} ({} words)",
+                        deck_name(path),
+                        self.frequency.len()
+                    ));
+                }
+                None => {
+                    ui.weak("none");
+                }
+            }
+            if ui
+                .button("Choose…")
+                .on_hover_text("A text file with one word per line, most frequent first")
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Word list", &["txt", "csv", "tsv"])
+                    .set_title("Select frequency list")
+                    .pick_file()
+                {
+                    self.deck_settings.frequency_list = Some(path);
+                    frequency_changed = true;
+                }
+            }
+            if self.deck_settings.frequency_list.is_some() && ui.button("✖").clicked() {
+                self.deck_settings.frequency_list = None;
+                frequency_changed = true;
+            }
+        });
+        if ui
+            .add_enabled(
+                self.deck_settings.frequency_list.is_some(),
+                egui::Checkbox::new(
+                    &mut self.deck_settings.frequency_order,
+                    "Introduce new words most frequent first",
+                ),
+            )
+            .changed()
+        {
+            self.save_deck_settings();
+        }
+        if frequency_changed {
+            self.save_deck_settings();
+            self.load_deck_settings();
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Subscriptions:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_subscription)
+                    .hint_text("https://… or a file path")
+                    .desired_width(200.0),
+            )
+            .on_hover_text(
+                "A TXT word list to follow: new words are added and changed translations \
+                 taken over and tagged for review, every few hours",
+            );
+            if ui.button("Choose…").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Text Files", &["txt"])
+                    .set_title("Subscribe to word list")
+                    .pick_file()
+                {
+                    self.new_subscription = path.display().to_string();
+                }
+            }
+            let source = self.new_subscription.trim().to_string();
+            if source.starts_with("http://") {
+                ui.colored_label(egui::Color32::from_rgb(220, 160, 40), "⚠ not encrypted")
+                    .on_hover_text(
+                        "Anyone on the network could change the list on its way; \
+                         use https:// if the site offers it",
+                    );
+            }
+            let known = self
+                .deck_settings
+                .subscriptions
+                .iter()
+                .any(|s| s.source == source);
+            if ui
+                .add_enabled(
+                    !source.is_empty() && !known,
+                    egui::Button::new("➕ Subscribe"),
+                )
+                .clicked()
+            {
+                self.deck_settings.subscriptions.push(Subscription {
+                    source,
+                    last_checked: 0,
+                    seen: HashMap::new(),
+                });
+                self.new_subscription.clear();
+                self.save_deck_settings();
+                self.next_subscription_check = None;
+            }
+        });
+        let mut unsubscribe = None;
+        for (i, subscription) in self.deck_settings.subscriptions.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add_space(16.0);
+                ui.label(&subscription.source);
+                if subscription.source.starts_with("http://") {
+                    ui.colored_label(egui::Color32::from_rgb(220, 160, 40), "⚠")
+                        .on_hover_text("Fetched without encryption");
+                }
+                if subscription.last_checked > 0 {
+                    ui.weak(format!(
+                        "checked {}",
+                        format_day(subscription.last_checked / SECS_PER_DAY)
+                    ));
+                }
+                if ui.small_button("✖").on_hover_text("Unsubscribe").clicked() {
+                    unsubscribe = Some(i);
+                }
+            });
+        }
+        if let Some(i) = unsubscribe {
+            self.deck_settings.subscriptions.remove(i);
+            self.save_deck_settings();
+        }
+        if !self.deck_settings.subscriptions.is_empty()
+            && ui
+                .add_enabled(
+                    self.subscription_results.is_none(),
+                    egui::Button::new("🔄 Refresh now"),
+                )
+                .clicked()
+        {
+            self.refresh_subscriptions(true);
+        }
+
+        let mut strokes_changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Stroke order data:");
+            match &self.deck_settings.stroke_data {
+                Some(path) => {
+                    ui.label(format!(
+                        "{} ({} characters)",
+                        deck_name(path),
+                        self.strokes.len()
+                    ));
+                }
+                None => {
+                    ui.weak("none");
+                }
+            }
+            if ui
+                .button("Choose…")
+                .on_hover_text("Make Me a Hanzi's graphics.txt, for Chinese or Japanese decks")
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Make Me a Hanzi", &["txt"])
+                    .set_title("Select stroke data")
+                    .pick_file()
+                {
+                    self.deck_settings.stroke_data = Some(path);
+                    strokes_changed = true;
+                }
+            }
+            if self.deck_settings.stroke_data.is_some() && ui.button("✖").clicked() {
+                self.deck_settings.stroke_data = None;
+                strokes_changed = true;
+            }
+        });
+        if strokes_changed {
+            self.save_deck_settings();
+            self.load_deck_settings();
+        }
+        if ui
+            .checkbox(
+                &mut self.deck_settings.compress,
+                "Compress the deck file (gzip)",
+            )
+            .on_hover_text("Smaller files for decks with long notes; woro reads both kinds")
+            .changed()
+        {
+            self.save_deck_settings();
+            self.mark_dirty(); // rewrite it in the new format
+        }
+        if ui
+            .checkbox(
+                &mut self.deck_settings.binary,
+                "Save in a fast binary format",
+            )
+            .on_hover_text(
+                "For very large decks. Other programs can't read it; use Export as JSON for them",
+            )
+            .changed()
+        {
+            self.save_deck_settings();
+            self.mark_dirty();
+        }
+        if let Err(e) = self.deck_settings.answer_regex("") {
+            ui.colored_label(egui::Color32::RED, format!("Invalid pattern: {}", e));
+        }
+        ui.add_space(10.0);
+
+        ui.strong("📅 Review schedule");
+        egui::Grid::new("schedule_grid")
+            .num_columns(2)
+            .spacing([10.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("Grace period at level 1:");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.settings.decay_base_days)
+                            .clamp_range(0.5..=60.0)
+                            .speed(0.1)
+                            .suffix(" days"),
+                    )
+                    .changed();
+                ui.end_row();
+
+                ui.label("Growth per level:");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.settings.decay_growth)
+                            .clamp_range(1.0..=5.0)
+                            .speed(0.05)
+                            .prefix("×"),
+                    )
+                    .changed();
+                ui.end_row();
+            });
+        let curve: Vec<String> = (1..=MAX_LEVEL)
+            .map(|level| format!("L{} {:.0}d", level, self.settings.grace_days(level)))
+            .collect();
+        ui.weak(format!(
+            "Grace periods: {} (words are due halfway through)",
+            curve.join(" · ")
+        ));
+        ui.add_space(6.0);
+
+        ui.horizontal(|ui| {
+            ui.label("Learning steps:").on_hover_text(
+                "A new word comes back after this many questions each time you get it \
+                 right, and only reaches level 2 once it's through them all",
+            );
+            let steps = &mut self.settings.learning_steps;
+            for step in steps.iter_mut() {
+                changed |= ui
+                    .add(egui::DragValue::new(step).clamp_range(1..=200).suffix(" q"))
+                    .changed();
+            }
+            if ui.small_button("➕").clicked() {
+                let last = steps.last().copied().unwrap_or(1);
+                steps.push((last * 3).min(200));
+                changed = true;
+            }
+            if !steps.is_empty() && ui.small_button("➖").clicked() {
+                steps.pop();
+                changed = true;
+            }
+            if steps.is_empty() {
+                ui.weak("off");
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Right answers in a row to level up:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.answers_to_level_up)
+                        .clamp_range(1..=10),
+                )
+                .changed();
+        });
+        changed |= ui
+            .checkbox(
+                &mut self.settings.preview_new_words,
+                "👀 Show new words with their translation once before asking them",
+            )
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut self.settings.wait_after_answer,
+                "⏸ Keep each answer on screen until Enter",
+            )
+            .on_hover_text("Otherwise the next word comes right away, with the result under it")
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("🎬 Presenting: each word for");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.present_word_secs)
+                        .clamp_range(1..=60)
+                        .suffix(" s"),
+                )
+                .changed();
+            ui.label("then its translation for");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.present_translation_secs)
+                        .clamp_range(1..=60)
+                        .suffix(" s"),
+                )
+                .changed();
+        });
+        changed |= ui
+            .checkbox(
+                &mut self.settings.second_chance,
+                "💬 After a wrong answer, show the example sentence and let me try once more",
+            )
+            .on_hover_text(
+                "Uses the first line of the notes that contains the word. Right the second \
+                 time: no level up, but no level down either",
+            )
+            .changed();
+        changed |= ui
+            .checkbox(
+                &mut self.settings.decay_enabled,
+                "📉 Lower the level of words left unreviewed past their grace period",
+            )
+            .changed();
+        ui.add_enabled_ui(self.settings.decay_enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Decay rate:");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.settings.decay_rate)
+                            .clamp_range(0.1..=5.0)
+                            .speed(0.05)
+                            .suffix(" levels per grace period"),
+                    )
+                    .changed();
+            });
+        });
+        ui.add_space(10.0);
+
+        ui.strong("📦 Archive");
+        changed |= ui
+            .checkbox(
+                &mut self.settings.auto_archive,
+                "Archive words automatically once they stay mastered",
+            )
+            .changed();
+        ui.add_enabled_ui(self.settings.auto_archive, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("after");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.settings.auto_archive_reviews)
+                            .clamp_range(1..=50),
+                    )
+                    .changed();
+                ui.label("correct answers in a row at max level, over at least");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut self.settings.auto_archive_days)
+                            .clamp_range(0..=365)
+                            .suffix(" days"),
+                    )
+                    .changed();
+            });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Maintenance reviews ask");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.maintenance_sample)
+                        .clamp_range(1..=100),
+                )
+                .changed();
+            ui.label("mastered words; a forgotten word drops to level");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.maintenance_fail_level)
+                        .clamp_range(1..=MAX_LEVEL - 1),
+                )
+                .changed();
+        });
+
+        if changed {
+            if let Err(e) = self.settings.save() {
+                self.notify_error(e);
+            }
+        }
+        if recognizer_edited {
+            self.rebuild_modes();
+        }
+    }
+
+    fn stats_screen(&mut self, ui: &mut egui::Ui) {
+        ui.heading("📈 Statistics");
+        ui.add_space(6.0);
+
+        let mastered = self.words.iter().filter(|w| w.level >= MAX_LEVEL).count();
+        let archived = self.words.iter().filter(|w| w.archived).count();
+        let total = self.words.len();
+        ui.label(format!(
+            "{} words, {} mastered ({} archived)",
+            total, mastered, archived
+        ));
+        ui.label(format!("🔥 Study streak: {} days", self.study_streak()));
+        let articles: Vec<&ReviewEvent> = self
+            .reviews
+            .iter()
+            .filter(|e| e.mode == ReviewMode::Article)
+            .collect();
+        if !articles.is_empty() {
+            let right = articles.iter().filter(|e| e.correct).count();
+            ui.label(format!(
+                "⚥ Articles: {:.0}% right ({} answers)",
+                right as f32 * 100.0 / articles.len() as f32,
+                articles.len()
+            ));
+        }
+        ui.add_space(6.0);
+
+        ui.label("Last 7 days:");
+        egui::Grid::new("recent_days_grid")
+            .num_columns(4)
+            .spacing([16.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Day");
+                ui.strong("Reviews");
+                ui.strong("Accuracy");
+                ui.strong("Study time");
+                ui.end_row();
+
+                let today = now_secs() / SECS_PER_DAY;
+                for snapshot in self.history.iter().rev().take_while(|s| s.day + 7 > today) {
+                    ui.label(format_day(snapshot.day));
+                    ui.label(snapshot.reviews.to_string());
+                    if snapshot.reviews > 0 {
+                        ui.label(format!(
+                            "{:.0}%",
+                            snapshot.correct as f32 * 100.0 / snapshot.reviews as f32
+                        ));
+                    } else {
+                        ui.label("–");
+                    }
+                    ui.label(format_duration(snapshot.study_secs));
+                    ui.end_row();
+                }
+            });
+        ui.add_space(6.0);
+
+        ui.label("Words per level:");
+        self.level_histogram(ui);
+
+        ui.separator();
+        ui.label("Mastery over time:");
+        if self.history.len() < 2 {
+            ui.weak("Keep practising on different days to see your progress curve.");
+        }
+
+        // x = days relative to today, so the axis reads "-30 … 0"
+        let today = (now_secs() / SECS_PER_DAY) as f64;
+        let mastered_points: PlotPoints = self
+            .history
+            .iter()
+            .map(|s| [s.day as f64 - today, s.mastered as f64])
+            .collect();
+        let total_points: PlotPoints = self
+            .history
+            .iter()
+            .map(|s| [s.day as f64 - today, s.total as f64])
+            .collect();
+
+        Plot::new("mastery_over_time")
+            .height(220.0)
+            .legend(Legend::default())
+            .allow_scroll(false)
+            .x_axis_label("days from today")
+            .y_axis_label("words")
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(total_points).name("Total"));
+                plot_ui.line(Line::new(mastered_points).name("Mastered"));
+            });
+
+        ui.separator();
+        ui.label(format!(
+            "Review log: {} answers recorded",
+            self.reviews.len()
+        ));
+        if ui
+            .button("🩹 Rebuild levels from review log")
+            .on_hover_text("Restores each word's level from its most recent review")
+            .clicked()
+        {
+            let changed = self.rebuild_levels_from_log();
+            self.notify(format!(
+                "✅ Restored levels of {} words from the review log",
+                changed
+            ));
+        }
+        if ui
+            .add_enabled(
+                !self.reviews.is_empty(),
+                egui::Button::new("📤 Export review log (CSV)"),
+            )
+            .on_hover_text("card_id, review_time, review_rating, review_state, review_duration — the revlog columns FSRS and Anki analysis tools read")
+            .clicked()
+        {
+            self.export_review_log();
+        }
+        if ui
+            .add_enabled(
+                !self.words.is_empty(),
+                egui::Button::new("📤 Send words to Anki…"),
+            )
+            .on_hover_text("Needs Anki running with the AnkiConnect add-on")
+            .clicked()
+        {
+            self.run_palette_action(PaletteAction::PushToAnki);
+        }
+        if ui
+            .add_enabled(
+                !self.words.is_empty(),
+                egui::Button::new("📝 Export as TXT"),
+            )
+            .on_hover_text(
+                "The same format TXT import reads, so the file can be edited and imported again",
+            )
+            .clicked()
+        {
+            self.export_txt();
+        }
+        if ui
+            .add_enabled(
+                !self.words.is_empty(),
+                egui::Button::new("🗄 Export as JSON"),
+            )
+            .on_hover_text(
+                "The regular deck format, even if this deck is saved compressed or binary",
+            )
+            .clicked()
+        {
+            self.export_json();
+        }
+        if ui
+            .add_enabled(
+                !self.words.is_empty(),
+                egui::Button::new("🔊 Export audio playlist"),
+            )
+            .on_hover_text(
+                "Each word spoken, then its translation, with pauses (WAV, needs espeak-ng). \
+                 Set the speech languages in Settings.",
+            )
+            .clicked()
+        {
+            self.export_audio();
+        }
+        if ui
+            .add_enabled(
+                !self.words.is_empty(),
+                egui::Button::new("📤 Export for Memrise (TSV)"),
+            )
+            .on_hover_text("Paste the file's contents into a Memrise course's bulk add words box")
+            .clicked()
+        {
+            self.export_memrise();
+        }
+    }
+
+    // Small bar chart: how many words sit at each level
+    // Worked out from this deck's review log on this computer; nothing is sent anywhere
+    fn insights_screen(&mut self, ui: &mut egui::Ui) {
+        ui.heading("💡 Insights");
+        ui.weak("From this deck's review log, worked out on this computer only.");
+        ui.add_space(6.0);
+        if self.reviews.is_empty() {
+            ui.label("Answer some questions first.");
+            return;
+        }
+
+        let utc_offset = i64::from(chrono::Local::now().offset().local_minus_utc());
+        let insights = Insights::from_reviews(&self.reviews, utc_offset);
+        let best_hour = insights.best_hour();
+        match best_hour {
+            Some(hour) => {
+                let (answers, right) = insights.by_hour[hour];
+                ui.label(format!(
+                    "🕗 Best study hour: {:02}:00–{:02}:00 ({:.0}% right over {} answers)",
+                    hour,
+                    (hour + 1) % 24,
+                    right as f32 * 100.0 / answers as f32,
+                    answers
+                ));
+            }
+            None => {
+                ui.label(format!(
+                    "🕗 Best study hour: not enough answers yet ({} in one hour needed)",
+                    MIN_ANSWERS_FOR_BEST_HOUR
+                ));
+            }
+        }
+        ui.label(format!(
+            "⏱ Average session: {}, {:.1} answers ({} sessions)",
+            format_duration(insights.average_session_secs),
+            insights.average_session_answers,
+            insights.sessions
+        ));
+        ui.add_space(6.0);
+
+        ui.label("Answers by hour of day:");
+        hour_chart(ui, &insights.by_hour, best_hour);
+        ui.add_space(6.0);
+
+        ui.label("Retention by level (right answers, by the level going in):");
+        egui::Grid::new("retention_grid")
+            .num_columns(3)
+            .spacing([16.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Level");
+                ui.strong("Answers");
+                ui.strong("Retention");
+                ui.end_row();
+                for (i, (answers, right)) in insights.by_level.iter().enumerate() {
+                    level_badge(ui, i as u8 + 1);
+                    ui.label(answers.to_string());
+                    if *answers > 0 {
+                        ui.label(format!("{:.0}%", *right as f32 * 100.0 / *answers as f32));
+                    } else {
+                        ui.label("–");
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn level_histogram(&self, ui: &mut egui::Ui) {
+        let mut counts = [0usize; MAX_LEVEL as usize];
+        for word in self.words.iter().filter(|w| !w.archived) {
+            let level = word.level.clamp(1, MAX_LEVEL);
+            counts[level as usize - 1] += 1;
+        }
+        let highest = counts.iter().copied().max().unwrap_or(0).max(1);
+
+        let bar_width = 36.0;
+        let bar_height = 48.0;
+        let label_height = 14.0;
+        let size = egui::vec2(
+            bar_width * MAX_LEVEL as f32,
+            bar_height + label_height * 2.0,
+        );
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        let font = egui::FontId::proportional(11.0);
+
+        for (i, count) in counts.iter().enumerate() {
+            let left = rect.left() + i as f32 * bar_width;
+            let bottom = rect.bottom() - label_height;
+            let height = bar_height * *count as f32 / highest as f32;
+            let bar = egui::Rect::from_min_max(
+                egui::pos2(left + 4.0, bottom - height),
+                egui::pos2(left + bar_width - 4.0, bottom),
+            );
+            painter.rect_filled(bar, 2.0, level_color(i as u8 + 1));
+            painter.text(
+                egui::pos2(bar.center().x, bar.top() - 1.0),
+                egui::Align2::CENTER_BOTTOM,
+                count.to_string(),
+                font.clone(),
+                visuals.text_color(),
+            );
+            painter.text(
+                egui::pos2(bar.center().x, rect.bottom()),
+                egui::Align2::CENTER_BOTTOM,
+                format!("L{}", i + 1),
+                font.clone(),
+                visuals.weak_text_color(),
+            );
+        }
+
+        response.on_hover_text("Words per level");
+    }
+
+    fn ambiguous_lines_window(&mut self, ctx: &egui::Context) {
+        if self.ambiguous_lines.is_empty() {
+            return;
+        }
+
+        let mut import = false;
+        let mut cancel = false;
+        egui::Window::new("Where Does the Translation Start?")
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} lines have several words and no separator. Drag each split point:",
+                    self.ambiguous_lines.len()
+                ));
+                ui.weak("Tip: write such lines as \"buenos días\" \"good morning\" or use = / tab");
+                ui.add_space(6.0);
+                egui::ScrollArea::vertical()
+                    .max_height(360.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("ambiguous_grid")
+                            .num_columns(3)
+                            .spacing([10.0, 4.0])
+                            .show(ui, |ui| {
+                                for line in &mut self.ambiguous_lines {
+                                    ui.checkbox(&mut line.keep, "");
+                                    let last = line.tokens.len() - 1;
+                                    ui.add_enabled(
+                                        line.keep,
+                                        egui::Slider::new(&mut line.split, 1..=last)
+                                            .show_value(false),
+                                    );
+                                    ui.label(format!(
+                                        "{}  =  {}",
+                                        line.tokens[..line.split].join(" "),
+                                        line.tokens[line.split..].join(" ")
+                                    ));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                ui.add_space(8.0);
+                let kept = self.ambiguous_lines.iter().filter(|l| l.keep).count();
+                ui.horizontal(|ui| {
+                    if ui.button(format!("➕ Add {} words", kept)).clicked() {
+                        import = true;
+                    }
+                    if ui.button("Skip all").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if import {
+            let imported = std::mem::take(&mut self.ambiguous_lines)
+                .into_iter()
+                .filter(|line| line.keep)
+                .map(|line| {
+                    let foreign = line.tokens[..line.split].join(" ");
+                    let translation = line.tokens[line.split..].join(" ");
+                    let mut word = Word::new(foreign, translation);
+                    word.tags = line.tags;
+                    (line.deck, word)
+                })
+                .collect();
+            self.preview_import("split lines".to_string(), imported, 0);
+        } else if cancel {
+            self.ambiguous_lines.clear();
+        }
+    }
+
+    fn column_mapping_window(&mut self, ctx: &egui::Context) {
+        let Some(mapping) = &mut self.column_mapping else {
+            return;
+        };
+
+        let mut import = false;
+        let mut cancel = false;
+
+        egui::Window::new("Map Columns")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} rows found. Choose what each column contains:",
+                    mapping.rows.len()
+                ));
+                ui.add_space(6.0);
+
+                egui::Grid::new("column_mapping_grid")
+                    .num_columns(3)
+                    .spacing([10.0, 6.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Column");
+                        ui.strong("Example");
+                        ui.strong("Field");
+                        ui.end_row();
+
+                        for (i, header) in mapping.headers.iter().enumerate() {
+                            ui.label(header);
+                            let sample = mapping
+                                .rows
+                                .first()
+                                .and_then(|row| row.get(i))
+                                .map(String::as_str)
+                                .unwrap_or("");
+                            ui.label(sample);
+                            egui::ComboBox::from_id_source(("column_field", i))
+                                .selected_text(mapping.fields[i].label())
+                                .show_ui(ui, |ui| {
+                                    for field in ImportField::ALL {
+                                        ui.selectable_value(
+                                            &mut mapping.fields[i],
+                                            field,
+                                            field.label(),
+                                        );
+                                    }
+                                });
+                            ui.end_row();
+                        }
+                    });
+
+                ui.add_space(8.0);
+                let ready = mapping.fields.contains(&ImportField::Foreign)
+                    && mapping.fields.contains(&ImportField::Translation);
+                if !ready {
+                    ui.label("Assign both a foreign word and a translation column.");
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(ready, egui::Button::new("👀 Preview"))
+                        .clicked()
+                    {
+                        import = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if import {
+            if let Some(mapping) = self.column_mapping.take() {
+                self.apply_column_mapping(mapping);
+            }
+        } else if cancel {
+            self.column_mapping = None;
+        }
+    }
+
+    fn import_preview_window(&mut self, ctx: &egui::Context) {
+        use egui_extras::{Column, TableBuilder};
+
+        let Some(preview) = &mut self.import_preview else {
+            return;
+        };
+        let in_deck: HashSet<String> = self
+            .words
+            .iter()
+            .map(|w| w.foreign.trim().to_lowercase())
+            .collect();
+
+        let mut import = false;
+        let mut cancel = false;
+        egui::Window::new(format!("👀 Import Preview: {}", preview.source))
+            .collapsible(false)
+            .resizable(true)
+            .default_width(640.0)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} words read, {} lines skipped. Fix anything that came out wrong and \
+                     untick rows to leave out.",
+                    preview.rows.len(),
+                    preview.skipped
+                ));
+                ui.horizontal(|ui| {
+                    ui.label("Tags for all:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut preview.tags)
+                            .hint_text("chapter 3, verbs")
+                            .desired_width(200.0),
+                    );
+                    if ui.small_button("All").clicked() {
+                        preview.rows.iter_mut().for_each(|r| r.keep = true);
+                    }
+                    if ui.small_button("None").clicked() {
+                        preview.rows.iter_mut().for_each(|r| r.keep = false);
+                    }
+                });
+                ui.add_space(6.0);
+
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .max_scroll_height(360.0)
+                    .column(Column::auto())
+                    .column(Column::initial(140.0).resizable(true))
+                    .column(Column::initial(140.0).resizable(true))
+                    .column(Column::initial(100.0).resizable(true))
+                    .column(Column::initial(120.0).resizable(true))
+                    .column(Column::remainder())
+                    .header(20.0, |mut header| {
+                        for title in ["", "Word", "Translation", "Tags", "Notes", ""] {
+                            header.col(|ui| {
+                                ui.strong(title);
+                            });
+                        }
+                    })
+                    .body(|body| {
+                        body.rows(22.0, preview.rows.len(), |mut row| {
+                            let r = &mut preview.rows[row.index()];
+                            row.col(|ui| {
+                                ui.checkbox(&mut r.keep, "");
+                            });
+                            for text in [&mut r.foreign, &mut r.translation, &mut r.tags] {
+                                row.col(|ui| {
+                                    ui.add_enabled(
+                                        r.keep,
+                                        egui::TextEdit::singleline(text)
+                                            .desired_width(f32::INFINITY),
+                                    );
+                                });
+                            }
+                            row.col(|ui| {
+                                ui.add_enabled(
+                                    r.keep,
+                                    egui::TextEdit::singleline(&mut r.notes)
+                                        .desired_width(f32::INFINITY),
+                                );
+                            });
+                            row.col(|ui| {
+                                if r.foreign.trim().is_empty() || r.translation.trim().is_empty() {
+                                    ui.colored_label(ui.visuals().warn_fg_color, "incomplete");
+                                } else if let Some(deck) = &r.deck {
+                                    ui.weak(format!("→ {}", deck));
+                                } else if in_deck.contains(&r.foreign.trim().to_lowercase()) {
+                                    ui.weak("in deck");
+                                }
+                            });
+                        });
+                    });
+
+                ui.add_space(8.0);
+                let kept = preview.rows.iter().filter(|r| r.keep).count();
+                ui.horizontal(|ui| {
+                    if ui.button(format!("✅ Import {} words", kept)).clicked() {
+                        import = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if import {
+            let Some(preview) = self.import_preview.take() else {
+                return;
+            };
+            let common = split_tags(&preview.tags);
+            let mut incomplete = 0;
+            let mut imported = Vec::new();
+            for row in preview.rows.into_iter().filter(|r| r.keep) {
+                let (foreign, translation) = (row.foreign.trim(), row.translation.trim());
+                if foreign.is_empty() || translation.is_empty() {
+                    incomplete += 1;
+                    continue;
+                }
+                let mut word = Word::new(foreign.to_string(), translation.to_string());
+                word.tags = split_tags(&row.tags);
+                for tag in &common {
+                    if !word.tags.contains(tag) {
+                        word.tags.push(tag.clone());
+                    }
+                }
+                word.notes = row.notes.trim().to_string();
+                imported.push((row.deck, word));
+            }
+            let added = self.add_to_decks(imported);
+            self.notify(format!(
+                "✅ Added {} words from {}{}",
+                added,
+                preview.source,
+                if incomplete > 0 {
+                    format!(", left out {} incomplete rows", incomplete)
+                } else {
+                    String::new()
+                }
+            ));
+        } else if cancel {
+            self.import_preview = None;
+        }
+    }
+
+    fn split_deck_window(&mut self, ctx: &egui::Context) {
+        let tags = self.all_tags();
+        let Some(filter) = &mut self.split_filter else {
+            return;
+        };
+        let now = now_secs();
+        let matching = self.words.iter().filter(|w| filter.matches(w, now)).count();
+
+        let mut split = false;
+        let mut cancel = false;
+
+        egui::Window::new("✂ Split Deck")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Move every word matching these filters into a new deck:");
+                ui.add_space(6.0);
+
+                egui::Grid::new("split_deck_grid")
+                    .num_columns(2)
+                    .spacing([10.0, 8.0])
+                    .show(ui, |ui| {
+                        ui.label("Tag:");
+                        egui::ComboBox::from_id_source("split_tag")
+                            .selected_text(filter.tag.as_deref().unwrap_or("(any)"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut filter.tag, None, "(any)");
+                                for tag in &tags {
+                                    ui.selectable_value(&mut filter.tag, Some(tag.clone()), tag);
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Level:");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut filter.min_level)
+                                    .clamp_range(1..=MAX_LEVEL),
+                            );
+                            ui.label("to");
+                            ui.add(
+                                egui::DragValue::new(&mut filter.max_level)
+                                    .clamp_range(1..=MAX_LEVEL),
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("Added:");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut filter.recent_only, "only in the last");
+                            ui.add_enabled(
+                                filter.recent_only,
+                                egui::DragValue::new(&mut filter.recent_days)
+                                    .clamp_range(1..=365)
+                                    .suffix(" days"),
+                            );
+                        });
+                        ui.end_row();
+                    });
+
+                ui.add_space(8.0);
+                ui.label(format!("{} of {} words match.", matching, self.words.len()));
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(matching > 0, egui::Button::new("✂ Split into new deck…"))
+                        .clicked()
+                    {
+                        split = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if split {
+            if let Some(filter) = self.split_filter.take() {
+                self.split_deck(&filter);
+            }
+        } else if cancel {
+            self.split_filter = None;
+        }
+    }
+
+    fn session_builder_window(&mut self, ctx: &egui::Context) {
+        let tags = self.all_tags();
+        let mode_names: Vec<&str> = self.modes.iter().map(|m| m.name()).collect();
+        let Some(builder) = &mut self.session_builder else {
+            return;
+        };
+        let now = now_secs();
+        let matching = self
+            .words
+            .iter()
+            .filter(|w| builder.matches(w, now, &self.recently_failed))
+            .count();
+
+        let mut start = false;
+        let mut cancel = false;
+
+        egui::Window::new("🧪 Custom Session")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("session_builder_grid")
+                    .num_columns(2)
+                    .spacing([10.0, 8.0])
+                    .show(ui, |ui| {
+                        ui.label("Tags:");
+                        ui.horizontal_wrapped(|ui| {
+                            if tags.is_empty() {
+                                ui.weak("(no tags in this deck)");
+                            }
+                            for tag in &tags {
+                                let mut on = builder.tags.contains(tag);
+                                if ui.toggle_value(&mut on, tag).changed() {
+                                    if on {
+                                        builder.tags.push(tag.clone());
+                                    } else {
+                                        builder.tags.retain(|t| t != tag);
+                                    }
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Level:");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut builder.min_level)
+                                    .clamp_range(1..=MAX_LEVEL),
+                            );
+                            ui.label("to");
+                            ui.add(
+                                egui::DragValue::new(&mut builder.max_level)
+                                    .clamp_range(1..=MAX_LEVEL),
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("Only:");
+                        ui.checkbox(&mut builder.recently_failed, "recently failed words");
+                        ui.end_row();
+
+                        ui.label("How many:");
+                        ui.add(
+                            egui::DragValue::new(&mut builder.limit)
+                                .clamp_range(0..=10_000)
+                                .custom_formatter(|n, _| {
+                                    if n == 0.0 {
+                                        "all".to_string()
+                                    } else {
+                                        format!("{} at random", n)
+                                    }
+                                }),
+                        );
+                        ui.end_row();
+
+                        ui.label("Mode:");
+                        egui::ComboBox::from_id_source("session_builder_mode")
+                            .selected_text(
+                                mode_names.get(builder.mode_index).copied().unwrap_or(""),
+                            )
+                            .show_ui(ui, |ui| {
+                                for (i, name) in mode_names.iter().enumerate() {
+                                    ui.selectable_value(&mut builder.mode_index, i, *name);
+                                }
+                            });
+                        ui.end_row();
+                    });
+
+                ui.add_space(6.0);
+                ui.checkbox(
+                    &mut builder.counts,
+                    "Count towards levels and the review schedule",
+                )
+                .on_hover_text("Off: a practice run, your levels stay as they are");
+
+                ui.add_space(8.0);
+                let asked = match builder.limit {
+                    0 => matching,
+                    n => matching.min(n as usize),
+                };
+                ui.label(format!(
+                    "{} words match, {} will be asked.",
+                    matching, asked
+                ));
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(matching > 0, egui::Button::new("▶ Start"))
+                        .clicked()
+                    {
+                        start = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if start {
+            if let Some(builder) = self.session_builder.take() {
+                self.start_custom_session(&builder);
+            }
+        } else if cancel {
+            self.session_builder = None;
+        }
+    }
+
+    // Archived words stay home; the class gets what's being learned
+    fn deck_share_code(&self) -> Result<String, String> {
+        let words: Vec<Word> = self.words.iter().filter(|w| !w.archived).cloned().collect();
+        share_code(&deck_name(&self.deck_path), &words)
+    }
+
+    fn share_qr_window(&mut self, ctx: &egui::Context) {
+        let Some(share) = &mut self.share_qr else {
+            return;
+        };
+
+        let mut open = true;
+        let mut payload = None;
+        egui::Window::new("📱 Share as QR")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Or a link to a hosted copy:");
+                    let edit = ui.add(
+                        egui::TextEdit::singleline(&mut share.url)
+                            .hint_text("https://… (an exported TXT)")
+                            .desired_width(220.0),
+                    );
+                    if edit.changed() {
+                        payload = Some(share.url.trim().to_string());
+                    }
+                });
+                ui.add_space(6.0);
+                match (&share.payload, &share.qr) {
+                    (Ok(code), Some(qr)) => {
+                        let size = ui.available_width().clamp(200.0, 600.0);
+                        qr_image(ui, qr, size);
+                        ui.horizontal(|ui| {
+                            ui.weak(format!("{} characters", code.len()));
+                            if ui.button("📋 Copy code").clicked() {
+                                ui.output_mut(|o| o.copied_text = code.clone());
+                            }
+                        });
+                    }
+                    (Ok(_), None) => {
+                        ui.label("This doesn't fit in a QR code.");
+                    }
+                    (Err(e), _) => {
+                        ui.label(e);
+                    }
+                }
+            });
+
+        if let Some(url) = payload {
+            let code = if url.is_empty() {
+                self.deck_share_code()
+            } else {
+                Ok(url)
+            };
+            if let Some(share) = &mut self.share_qr {
+                share.set_payload(code);
+            }
+        }
+        if !open {
+            self.share_qr = None;
+        }
+    }
+
+    fn code_import_window(&mut self, ctx: &egui::Context) {
+        let Some(code) = &mut self.code_import else {
+            return;
+        };
+
+        let fetched = self.code_fetch.as_ref().and_then(|rx| rx.try_recv().ok());
+        let fetching = self.code_fetch.is_some() && fetched.is_none();
+        let mut import = false;
+        let mut cancel = false;
+        egui::Window::new("🔗 Import from Code")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Paste the text of a scanned QR code: a woro: code or a link.");
+                ui.add(
+                    egui::TextEdit::multiline(code)
+                        .hint_text("woro:… or https://…")
+                        .desired_rows(4)
+                        .desired_width(380.0),
+                );
+                ui.horizontal(|ui| {
+                    import = ui
+                        .add_enabled(
+                            !code.trim().is_empty() && !fetching,
+                            egui::Button::new("👀 Preview"),
+                        )
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                    if fetching {
+                        ui.spinner();
+                        ui.label("Downloading…");
+                    }
+                });
+            });
+
+        if let Some(result) = fetched {
+            self.code_fetch = None;
+            let link = self.code_import.clone().unwrap_or_default();
+            match result {
+                Ok(content) => {
+                    self.code_import = None;
+                    self.parse_txt_content(link.trim().to_string(), &content);
+                }
+                Err(e) => self.notify_error(format!("Couldn't import that code: {}", e)),
+            }
+        }
+        if import {
+            let code = self.code_import.clone().unwrap_or_default();
+            if let Err(e) = self.import_from_code(&code) {
+                self.notify_error(format!("Couldn't import that code: {}", e));
+            }
+        }
+        if cancel {
+            // A download still running is dropped with its channel
+            self.code_import = None;
+            self.code_fetch = None;
+        }
+    }
+
+    // A share code (see share_code) goes into the import preview right away; a link to a
+    // TXT list is downloaded first, see code_import_window
+    fn import_from_code(&mut self, code: &str) -> Result<(), String> {
+        let code = code.trim();
+        if code.starts_with(SHARE_CODE_PREFIX) {
+            let content = decode_share_code(code)?;
+            self.code_import = None;
+            self.parse_txt_content("shared code".to_string(), &content);
+        } else if code.starts_with("http://") || code.starts_with("https://") {
+            let link = code.to_string();
+            self.code_fetch = Some(spawn_with_result(self.egui_ctx.clone(), move || {
+                fetch_source(&link)
+            }));
+        } else {
+            return Err("it's neither a woro: code nor an http(s) link".to_string());
+        }
+        Ok(())
+    }
+
+    fn media_manager_window(&mut self, ctx: &egui::Context) {
+        let dir = self.sidecar_path("media");
+        let Some(scan) = &mut self.media_manager else {
+            return;
+        };
+
+        let mut close = false;
+        let mut clean = false;
+        let mut relink = None;
+        egui::Window::new("🖼 Media")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let total: u64 = scan.files.iter().map(|f| f.1).sum();
+                let orphans: Vec<&(String, u64, bool)> =
+                    scan.files.iter().filter(|f| !f.2).collect();
+                ui.label(format!(
+                    "{} files, {} in total",
+                    scan.files.len(),
+                    format_size(total)
+                ));
+                ui.add_space(6.0);
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("media_files")
+                            .num_columns(3)
+                            .spacing([10.0, 4.0])
+                            .show(ui, |ui| {
+                                for (name, size, in_use) in &scan.files {
+                                    ui.label(name);
+                                    ui.label(format_size(*size));
+                                    if *in_use {
+                                        ui.weak("in use");
+                                    } else {
+                                        ui.colored_label(
+                                            egui::Color32::from_rgb(220, 160, 40),
+                                            "orphaned",
+                                        );
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                if !scan.missing.is_empty() {
+                    ui.add_space(6.0);
+                    ui.label(format!(
+                        "⚠ {} words point to missing files:",
+                        scan.missing.len()
+                    ));
+                    for (id, name) in &scan.missing {
+                        ui.horizontal(|ui| {
+                            let word = self.words.iter().find(|w| w.id == *id);
+                            ui.label(format!(
+                                "{}: {}",
+                                word.map_or("?", |w| w.foreign.as_str()),
+                                name
+                            ));
+                            if ui.small_button("Relink…").clicked() {
+                                relink = Some(*id);
+                            }
+                        });
+                    }
+                }
+
+                ui.add_space(8.0);
+                let orphaned: u64 = orphans.iter().map(|f| f.1).sum();
+                let orphan_count = orphans.len();
+                if scan.confirm_delete {
+                    ui.label(format!(
+                        "Delete {} files ({}) for good?",
+                        orphan_count,
+                        format_size(orphaned)
+                    ));
+                    ui.horizontal(|ui| {
+                        clean = ui.button("🗑 Delete").clicked();
+                        if ui.button("Cancel").clicked() {
+                            scan.confirm_delete = false;
+                        }
+                    });
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            orphan_count > 0,
+                            egui::Button::new(format!(
+                                "🗑 Delete {} orphaned ({})",
+                                orphan_count,
+                                format_size(orphaned)
+                            )),
+                        )
+                        .on_hover_text("Files no word uses any more")
+                        .clicked()
+                    {
+                        scan.confirm_delete = true;
+                    }
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if clean {
+            let unused: Vec<String> = scan
+                .files
+                .iter()
+                .filter(|f| !f.2)
+                .map(|f| f.0.clone())
+                .collect();
+            for name in unused {
+                if let Err(e) = fs::remove_file(dir.join(&name)) {
+                    self.notify_error(format!("Error deleting {}: {}", name, e));
+                }
+            }
+        }
+        if let Some(id) = relink {
+            self.relink_image(id, &dir);
+        }
+        if close {
+            self.media_manager = None;
+        } else if clean || relink.is_some() {
+            self.media_manager = Some(self.scan_media());
+        }
+    }
+
+    fn scan_media(&self) -> MediaScan {
+        let editing = self.word_editor.as_ref().map(|e| e.image.as_str());
+        scan_media(&self.sidecar_path("media"), &self.words, editing)
+    }
+
+    // Points a word whose picture went missing at a newly picked file
+    fn relink_image(&mut self, id: u64, dir: &Path) {
+        let Some(source) = rfd::FileDialog::new()
+            .add_filter("Images", &["png", "jpg", "jpeg", "gif", "webp", "bmp"])
+            .set_title("Find the missing picture")
+            .pick_file()
+        else {
+            return;
+        };
+        let Some(word) = self.words.iter_mut().find(|w| w.id == id) else {
+            return;
+        };
+        match copy_into_media(&source, dir, id) {
+            Ok(name) => {
+                word.image = name;
+                self.media_cache.forget(id);
+                self.mark_dirty();
+            }
+            Err(e) => self.notify_error(format!("Error copying {}: {}", source.display(), e)),
+        }
+    }
+
+    fn corrupt_deck_window(&mut self, ctx: &egui::Context) {
+        let Some(corrupt) = &self.corrupt_deck else {
+            return;
+        };
+
+        let mut choice: Option<Option<PathBuf>> = None; // Some(None) = start empty
+        egui::Window::new("⚠ Deck Can't Be Read")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} is damaged and couldn't be loaded:",
+                    self.deck_path.display()
+                ));
+                ui.weak(&corrupt.error);
+                ui.add_space(6.0);
+                if corrupt.backups.is_empty() {
+                    ui.label("There is no usable backup of it.");
+                }
+                for (path, count, modified) in &corrupt.backups {
+                    let label = format!(
+                        "♻ Restore the backup from {} ({} words)",
+                        format_day(modified / SECS_PER_DAY),
+                        count
+                    );
+                    if ui.button(label).clicked() {
+                        choice = Some(Some(path.clone()));
+                    }
+                }
+                if ui.button("Start with an empty deck").clicked() {
+                    choice = Some(None);
+                }
+                ui.add_space(6.0);
+                ui.weak(format!(
+                    "Either way the damaged file is kept as {}.corrupt.json",
+                    deck_name(&self.deck_path)
+                ));
+            });
+
+        if let Some(backup) = choice {
+            self.resolve_corrupt_deck(backup);
+        }
+    }
+
+    fn external_change_window(&mut self, ctx: &egui::Context) {
+        if !self.external_change {
+            return;
+        }
+
+        let mut reload = false;
+        let mut keep = false;
+        egui::Window::new("Deck Changed on Disk")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "\"{}\" was changed by another program.",
+                    deck_name(&self.deck_path)
+                ));
+                if self.dirty_since.is_some() {
+                    ui.label("⚠ Reloading discards your unsaved changes.");
+                }
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("🔄 Reload").clicked() {
+                        reload = true;
+                    }
+                    if ui.button("Keep my version").clicked() {
+                        keep = true;
+                    }
+                });
+            });
+
+        if reload {
+            self.dirty_since = None;
+            self.words.clear();
+            self.load();
+            if self.screen == Screen::Game {
+                self.pick_random_word();
+            }
+        } else if keep {
+            // Ours overwrites theirs on the next save
+            self.external_change = false;
+            self.deck_mtime = file_mtime(&self.deck_path);
+            self.mark_dirty();
+        }
+    }
+
+    fn anki_push_window(&mut self, ctx: &egui::Context) {
+        let Some(push) = &mut self.anki_push else {
+            return;
+        };
+        if let Some(decks) = push.decks_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            push.anki_decks = decks;
+            push.decks_rx = None;
+        }
+        if let Some(result) = push.sending.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            push.status = Some(match result {
+                Ok((added, skipped)) => {
+                    format!("✅ Added {} notes; {} were already in Anki", added, skipped)
+                }
+                Err(e) => format!("❌ {}", e),
+            });
+            push.sending = None;
+        }
+
+        let mut send = false;
+        let mut close = false;
+        egui::Window::new("Send to Anki")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Adds each word as a Basic note (front: word, back: translation).");
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    ui.label("Anki deck:");
+                    ui.text_edit_singleline(&mut push.deck);
+                    if !push.anki_decks.is_empty() {
+                        egui::ComboBox::from_id_source("anki_decks")
+                            .selected_text("existing…")
+                            .show_ui(ui, |ui| {
+                                for deck in &push.anki_decks {
+                                    ui.selectable_value(&mut push.deck, deck.clone(), deck);
+                                }
+                            });
+                    }
+                });
+                ui.checkbox(&mut push.include_archived, "Include archived words");
+                if push.sending.is_some() {
+                    ui.add_space(6.0);
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Sending to Anki…");
+                    });
+                } else if let Some(status) = &push.status {
+                    ui.add_space(6.0);
+                    ui.label(status);
+                }
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    let ready = !push.deck.trim().is_empty() && push.sending.is_none();
+                    if ui
+                        .add_enabled(ready, egui::Button::new("📤 Send"))
+                        .clicked()
+                    {
+                        send = true;
+                    }
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if send {
+            let words: Vec<Word> = self
+                .words
+                .iter()
+                .filter(|w| push.include_archived || !w.archived)
+                .cloned()
+                .collect();
+            let deck = push.deck.trim().to_string();
+            push.sending = Some(spawn_with_result(self.egui_ctx.clone(), move || {
+                push_words_to_anki(&deck, &words)
+            }));
+        }
+        if close {
+            self.anki_push = None;
+        }
+    }
+
+    fn subtitle_mining_window(&mut self, ctx: &egui::Context) {
+        let Some(mining) = &mut self.subtitle_mining else {
+            return;
+        };
+
+        let mut import = false;
+        let mut cancel = false;
+        egui::Window::new("New Words in Subtitles")
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The {} most frequent words in \"{}\" that aren't in your deck yet:",
+                    mining.candidates.len(),
+                    mining.source
+                ));
+                ui.horizontal(|ui| {
+                    if ui.small_button("Select all").clicked() {
+                        mining.candidates.iter_mut().for_each(|c| c.selected = true);
+                    }
+                    if ui.small_button("Select none").clicked() {
+                        mining
+                            .candidates
+                            .iter_mut()
+                            .for_each(|c| c.selected = false);
+                    }
+                });
+                ui.add_space(4.0);
+                egui::ScrollArea::vertical()
+                    .max_height(360.0)
+                    .show(ui, |ui| {
+                        for candidate in &mut mining.candidates {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(
+                                    &mut candidate.selected,
+                                    format!("{} ×{}", candidate.word, candidate.count),
+                                );
+                                frequency_badge(ui, &self.frequency, &candidate.word);
+                                ui.weak(&candidate.context);
+                            });
+                        }
+                    });
+                ui.add_space(8.0);
+                let selected = mining.candidates.iter().filter(|c| c.selected).count();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            selected > 0,
+                            egui::Button::new(format!("📥 Send {} to inbox", selected)),
+                        )
+                        .on_hover_text("Translate them there, then add them to the deck")
+                        .clicked()
+                    {
+                        import = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if import {
+            if let Some(mining) = self.subtitle_mining.take() {
+                let now = now_secs();
+                self.inbox.extend(
+                    mining
+                        .candidates
+                        .into_iter()
+                        .filter(|c| c.selected)
+                        .map(|c| InboxItem {
+                            text: c.word,
+                            translation: String::new(),
+                            captured_at: now,
+                            context: c.context,
+                        }),
+                );
+                self.save_inbox();
+            }
+        } else if cancel {
+            self.subtitle_mining = None;
+        }
+    }
+
+    fn merge_report_window(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.merge_report else {
+            return;
+        };
+
+        let mut close = false;
+        let mut take_theirs = None;
+        egui::Window::new("Merge Complete")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Merged \"{}\" into this deck:", report.source));
+                ui.add_space(6.0);
+                ui.label(format!("➕ {} new words added", report.added));
+                ui.label(format!("🔁 {} duplicates combined", report.duplicates));
+                ui.label(format!("⬆ {} levels raised", report.level_raised));
+                ui.label(format!("🏷 {} words gained tags", report.tags_merged));
+                ui.label(format!("📝 {} words gained notes", report.notes_merged));
+                if !report.conflicts.is_empty() {
+                    ui.add_space(6.0);
+                    ui.label(format!(
+                        "⚠ {} words have a different translation there; yours were kept:",
+                        report.conflicts.len()
+                    ));
+                    egui::ScrollArea::vertical()
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("merge_conflicts")
+                                .num_columns(4)
+                                .spacing([10.0, 4.0])
+                                .show(ui, |ui| {
+                                    for (i, conflict) in report.conflicts.iter().enumerate() {
+                                        ui.label(&conflict.foreign);
+                                        ui.label(format!("ours: {}", conflict.ours));
+                                        ui.label(format!("theirs: {}", conflict.theirs));
+                                        if ui.small_button("Use theirs").clicked() {
+                                            take_theirs = Some(i);
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                }
+                ui.add_space(8.0);
+                if ui.button("OK").clicked() {
+                    close = true;
+                }
+            });
+
+        if let Some(i) = take_theirs {
+            if let Some(report) = &mut self.merge_report {
+                let conflict = report.conflicts.remove(i);
+                let word = self
+                    .words
+                    .iter_mut()
+                    .find(|w| w.id == conflict.word_id && w.foreign == conflict.foreign);
+                if let Some(word) = word {
+                    word.translation = conflict.theirs;
+                    self.mark_dirty();
+                }
+            }
+        }
+        if close {
+            self.merge_report = None;
+        }
+    }
+
+    fn anki_import_window(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &mut self.anki_import else {
+            return;
+        };
+
+        let mut import = false;
+        let mut cancel = false;
+
+        egui::Window::new("Import Anki Deck")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let reviewed = pending.cards.iter().filter(|c| !c.is_new).count();
+                ui.label(format!(
+                    "{} cards found ({} already studied in Anki).",
+                    pending.cards.len(),
+                    reviewed
+                ));
+                ui.add_space(6.0);
+                ui.checkbox(
+                    &mut pending.seed_levels,
+                    "Start levels from Anki's intervals and ease",
+                )
+                .on_hover_text("Otherwise every card starts at level 1, like any other import");
+                if pending.seed_levels {
+                    let mut per_level = [0usize; MAX_LEVEL as usize];
+                    for card in &pending.cards {
+                        per_level[card.seeded_level() as usize - 1] += 1;
+                    }
+                    let summary: Vec<String> = per_level
+                        .iter()
+                        .enumerate()
+                        .map(|(i, n)| format!("L{}: {}", i + 1, n))
+                        .collect();
+                    ui.weak(summary.join("   "));
+                }
+
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("✅ Import").clicked() {
+                        import = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if import {
+            if let Some(pending) = self.anki_import.take() {
+                self.apply_anki_import(pending);
+            }
+        } else if cancel {
+            self.anki_import = None;
+        }
+    }
+
+    fn word_editor_window(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.word_editor else {
+            return;
+        };
+
+        let mut save = false;
+        let mut cancel = false;
+        let mut pick_image = false;
+        let mut fetch_conjugations = false;
+        let fetch_command = &self.settings.conjugation_command;
+        let mut fetch_error = None;
+        if let Some(result) = editor.fetching.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            editor.fetching = None;
+            match result {
+                Ok(table) => editor.conjugations = table.trim().to_string(),
+                Err(e) => fetch_error = Some(e),
+            }
+        }
+
+        egui::Window::new("✏ Edit Word")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("word_editor_grid")
+                    .num_columns(2)
+                    .spacing([10.0, 8.0])
+                    .show(ui, |ui| {
+                        ui.label("Foreign word:");
+                        ui.text_edit_singleline(&mut editor.foreign);
+                        ui.end_row();
+
+                        ui.label("Translation:");
+                        ui.text_edit_singleline(&mut editor.translation);
+                        ui.end_row();
+
+                        ui.label("Tags:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut editor.tags)
+                                .hint_text("comma separated"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Notes:");
+                        ui.text_edit_multiline(&mut editor.notes);
+                        ui.end_row();
+
+                        ui.label("⚥ Article:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut editor.article)
+                                .hint_text("der, die, das…")
+                                .desired_width(80.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("🔤 Conjugations:");
+                        ui.vertical(|ui| {
+                            ui.add(
+                                egui::TextEdit::multiline(&mut editor.conjugations)
+                                    .hint_text("yo, pretérito = hablé")
+                                    .desired_rows(3),
+                            );
+                            if editor.fetching.is_some() {
+                                ui.spinner();
+                            } else if !fetch_command.trim().is_empty()
+                                && ui
+                                    .button("⬇ Fetch")
+                                    .on_hover_text("Ask the conjugation command from Settings")
+                                    .clicked()
+                            {
+                                fetch_conjugations = true;
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("🖼 Picture:");
+                        ui.horizontal(|ui| {
+                            if editor.image.is_empty() {
+                                ui.weak("none");
+                            } else {
+                                ui.label(&editor.image);
+                            }
+                            if ui.button("Choose…").clicked() {
+                                pick_image = true;
+                            }
+                            if !editor.image.is_empty() && ui.button("✖").clicked() {
+                                editor.image.clear();
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("💤 Snooze:");
+                        ui.horizontal(|ui| {
+                            let mut snoozed = editor.snooze.is_some();
+                            if ui.checkbox(&mut snoozed, "until").changed() {
+                                editor.snooze =
+                                    snoozed.then(|| secs_to_date(now_secs() + 7 * SECS_PER_DAY));
+                            }
+                            if let Some(date) = &mut editor.snooze {
+                                ui.add(
+                                    egui_extras::DatePickerButton::new(date).id_source("snooze"),
+                                );
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("📅 Schedule:");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut editor.pinned_daily, "📌 Every day")
+                                .on_hover_text("Due daily, whatever its level");
+                            let mut due = editor.due.is_some();
+                            if ui
+                                .checkbox(&mut due, "Next due on")
+                                .on_hover_text("Overrides the schedule until the next review")
+                                .changed()
+                            {
+                                editor.due = due.then(|| secs_to_date(now_secs()));
+                            }
+                            if let Some(date) = &mut editor.due {
+                                ui.add(egui_extras::DatePickerButton::new(date).id_source("due"));
+                            }
+                        });
+                        ui.end_row();
+                    });
+
+                ui.add_space(8.0);
+                let valid =
+                    !editor.foreign.trim().is_empty() && !editor.translation.trim().is_empty();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(valid, egui::Button::new("💾 Save"))
+                        .clicked()
+                    {
+                        save = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if save {
+            if let Some(editor) = self.word_editor.take() {
+                if let Some(word) = self.words.get_mut(editor.index) {
+                    word.foreign = editor.foreign.trim().to_string();
+                    word.translation = editor.translation.trim().to_string();
+                    word.tags = split_tags(&editor.tags);
+                    word.notes = editor.notes.trim().to_string();
+                    word.snoozed_until = editor.snooze.map_or(0, date_to_secs);
+                    word.due_at = editor.due.map_or(0, date_to_secs);
+                    word.pinned_daily = editor.pinned_daily;
+                    word.conjugations = parse_conjugations(&editor.conjugations);
+                    word.article = editor.article.trim().to_string();
+                    if word.image != editor.image {
+                        word.image = editor.image;
+                        self.media_cache.forget(word.id);
+                    }
+                    self.mark_dirty();
+                    self.rebuild_modes();
+                }
+            }
+        } else if cancel {
+            self.word_editor = None;
+        } else if pick_image {
+            self.attach_image();
+        } else if fetch_conjugations {
+            let command = self.settings.conjugation_command.clone();
+            let verb = editor.foreign.trim().to_string();
+            editor.fetching = Some(spawn_with_result(Some(ctx.clone()), move || {
+                run_filled(&command, "{word}", &verb)
+            }));
+        }
+        if let Some(e) = fetch_error {
+            self.notify_error(format!("Error fetching conjugations: {}", e));
+        }
+    }
+
+    // Copies a picked picture into the deck's media folder for the word being edited
+    fn attach_image(&mut self) {
+        let Some(source) = rfd::FileDialog::new()
+            .add_filter("Images", &["png", "jpg", "jpeg", "gif", "webp", "bmp"])
+            .set_title("Choose a picture")
+            .pick_file()
+        else {
+            return;
+        };
+        let dir = self.sidecar_path("media");
+        // The file is named after the word's id, so the word needs one first
+        self.assign_ids();
+        let Some(editor) = &mut self.word_editor else {
+            return;
+        };
+        let id = self.words.get(editor.index).map_or(0, |w| w.id);
+        match copy_into_media(&source, &dir, id) {
+            Ok(name) => editor.image = name,
+            Err(e) => self.notify_error(format!("Error copying {}: {}", source.display(), e)),
+        }
+    }
+
+    fn command_palette(&mut self, ctx: &egui::Context) {
+        let toggle = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::K);
+        if ctx.input_mut(|i| i.consume_shortcut(&toggle)) {
+            self.palette_open = !self.palette_open;
+            self.palette_query.clear();
+            self.palette_selected = 0;
+        }
+        if !self.palette_open {
+            return;
+        }
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.palette_open = false;
+            return;
+        }
+
+        let mut entries: Vec<(u32, PaletteAction, String)> = self
+            .palette_entries()
+            .into_iter()
+            .filter_map(|(action, label)| {
+                fuzzy_score(&self.palette_query, &label).map(|score| (score, action, label))
+            })
+            .collect();
+        if !self.palette_query.trim().is_empty() {
+            entries.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+        }
+        entries.truncate(12);
+
+        let (down, up, enter) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowDown),
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::Enter),
+            )
+        });
+        if down {
+            self.palette_selected += 1;
+        }
+        if up {
+            self.palette_selected = self.palette_selected.saturating_sub(1);
+        }
+        self.palette_selected = self.palette_selected.min(entries.len().saturating_sub(1));
+
+        let mut chosen: Option<PaletteAction> = None;
+        egui::Window::new("command_palette")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                ui.set_width(420.0);
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.palette_query)
+                        .hint_text("Jump to a word, deck or action…")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.palette_selected = 0;
+                }
+
+                ui.separator();
+                if entries.is_empty() {
+                    ui.label("No matches");
+                }
+                for (i, (_, action, label)) in entries.iter().enumerate() {
+                    if ui
+                        .selectable_label(i == self.palette_selected, label)
+                        .clicked()
+                    {
+                        chosen = Some(action.clone());
+                    }
+                }
+            });
+
+        if enter {
+            chosen = entries
+                .get(self.palette_selected)
+                .map(|(_, action, _)| action.clone());
+        }
+        if let Some(action) = chosen {
+            self.palette_open = false;
+            self.run_palette_action(action);
+        }
+    }
+
+    fn start_presentation(&mut self) {
+        let mut order: Vec<usize> = (0..self.words.len())
+            .filter(|&i| !self.words[i].archived && !self.words[i].translation.is_empty())
+            .collect();
+        if order.is_empty() {
+            self.notify_error("No words to present".to_string());
+            return;
+        }
+        order.shuffle(&mut self.rng);
+        self.presentation = Some(Presentation {
+            order,
+            position: 0,
+            showing_translation: false,
+            since: Instant::now(),
+            paused: false,
+            back_to: self.screen,
+        });
+        self.screen = Screen::Present;
+        if let Some(ctx) = &self.egui_ctx {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(true));
+        }
+    }
+
+    fn stop_presentation(&mut self, ctx: &egui::Context) {
+        if let Some(presentation) = self.presentation.take() {
+            self.screen = presentation.back_to;
+        } else {
+            self.screen = Screen::AddWords;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+    }
+
+    fn present_screen(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        let Some(p) = &mut self.presentation else {
+            self.stop_presentation(&ctx);
+            return;
+        };
+
+        let (stop, pause, back, forward) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::Escape),
+                i.key_pressed(egui::Key::Space),
+                i.key_pressed(egui::Key::ArrowLeft),
+                i.key_pressed(egui::Key::ArrowRight),
+            )
+        });
+        if stop {
+            self.stop_presentation(&ctx);
+            return;
+        }
+        p.paused ^= pause;
+
+        // Word, then translation, then the next word; the arrow keys skip ahead or back
+        let step = Duration::from_secs(u64::from(if p.showing_translation {
+            self.settings.present_translation_secs
+        } else {
+            self.settings.present_word_secs
+        }));
+        let elapsed = p.since.elapsed();
+        if forward || (!p.paused && elapsed >= step) {
+            if p.showing_translation || forward {
+                p.position = (p.position + 1) % p.order.len();
+                p.showing_translation = false;
+            } else {
+                p.showing_translation = true;
+            }
+            p.since = Instant::now();
+        } else if back {
+            p.position = (p.position + p.order.len() - 1) % p.order.len();
+            p.showing_translation = false;
+            p.since = Instant::now();
+        } else if !p.paused {
+            ctx.request_repaint_after(step - elapsed);
+        }
+
+        let Some(word) = self.words.get(p.order[p.position]) else {
+            self.stop_presentation(&ctx);
+            return;
+        };
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() * 0.3);
+            ruby_label(ui, &word.foreign, 72.0, !self.hide_readings);
+            ui.add_space(24.0);
+            let translation = if p.showing_translation {
+                word.translation.as_str()
+            } else {
+                " "
+            };
+            ui.label(egui::RichText::new(translation).size(48.0));
+        });
+        ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
+            ui.weak(format!(
+                "{}/{}{} · Space: pause · ←/→: back/next · Esc: stop",
+                p.position + 1,
+                p.order.len(),
+                if p.paused { " ⏸" } else { "" }
+            ));
+        });
+    }
+
+    fn dictation_screen(&mut self, ui: &mut egui::Ui) {
+        ui.heading("🔢 Dictation");
+        ui.label("Listen and type what you hear, in digits.");
+        ui.add_space(8.0);
+        let kind = self.dictation.kind;
+        ui.horizontal(|ui| {
+            let d = &mut self.dictation;
+            ui.selectable_value(&mut d.kind, DictationKind::Number, "Numbers");
+            ui.selectable_value(&mut d.kind, DictationKind::Date, "Dates");
+            ui.selectable_value(&mut d.kind, DictationKind::Time, "Times");
+            if d.kind == DictationKind::Number {
+                ui.label("up to");
+                ui.add(egui::DragValue::new(&mut d.max_number).clamp_range(10..=10_000_000));
+            }
+        });
+        let mut say = false;
+        if self.dictation.prompt.is_empty() || self.dictation.kind != kind {
+            self.dictation.next();
+            say = true;
+        }
+        ui.add_space(12.0);
+
+        let mut check = false;
+        ui.horizontal(|ui| {
+            if ui.button("🔊 Again").clicked() {
+                say = true;
+            }
+            if ui.button("🐢 Slowly").clicked() {
+                let text = self.dictation.prompt.clone();
+                self.say(&text, self.deck_settings.foreign_voice().slow());
+            }
+        });
+        ui.label("Your answer:");
+        let response = ui.text_edit_singleline(&mut self.dictation.answer);
+        if response.changed() {
+            self.dictation.feedback.clear();
+        }
+        check |= response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        check |= ui.button("✓ Check").clicked();
+        if check {
+            let d = &mut self.dictation;
+            let heard = d.prompt.clone();
+            d.asked += 1;
+            if digit_groups(&d.answer) == digit_groups(&heard) {
+                d.right += 1;
+                d.feedback = format!("✅ CORRECT! {}", heard);
+            } else {
+                d.feedback = format!("❌ WRONG! It was {}", heard);
+            }
+            d.next();
+            say = true;
+            response.request_focus();
+        }
+        if say {
+            let text = self.dictation.prompt.clone();
+            self.say(&text, self.deck_settings.foreign_voice());
+        }
+
+        ui.add_space(10.0);
+        if !self.dictation.feedback.is_empty() {
+            ui.label(&self.dictation.feedback);
+        }
+        if self.dictation.asked > 0 {
+            ui.weak(format!(
+                "{} of {} right",
+                self.dictation.right, self.dictation.asked
+            ));
+        }
+    }
+
+    fn end_screen(&mut self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.add_space(40.0);
+            ui.label(egui::RichText::new("🎉🎉🎉").size(72.0));
+            ui.add_space(12.0);
+
+            ui.label(
+                egui::RichText::new("CONGRATULATIONS!")
+                    .size(40.0)
+                    .strong()
+                    .color(egui::Color32::GOLD),
+            );
+            ui.add_space(8.0);
+            ui.label(
+                egui::RichText::new("ALL WORDS MASTERED!")
+                    .size(26.0)
+                    .color(egui::Color32::GREEN),
+            );
+
+            ui.add_space(20.0);
+            ui.label(
+                egui::RichText::new(format!("You mastered {} words!", self.words.len())).size(18.0),
+            );
+            ui.add_space(12.0);
+            self.session_summary(ui);
+
+            ui.add_space(30.0);
+            let pool = self.session_pool();
+            let mut tags: Vec<String> = pool
+                .iter()
+                .flat_map(|&i| self.words[i].tags.clone())
+                .collect();
+            tags.sort();
+            tags.dedup();
+            if !tags.is_empty() {
+                ui.label("Reset only words tagged:");
+                ui.horizontal_wrapped(|ui| {
+                    for tag in &tags {
+                        let mut on = self.reset_tags.contains(tag);
+                        if ui.toggle_value(&mut on, tag).changed() {
+                            if on {
+                                self.reset_tags.push(tag.clone());
+                            } else {
+                                self.reset_tags.retain(|t| t != tag);
+                            }
+                        }
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.label("Back to level");
+                ui.add(egui::DragValue::new(&mut self.reset_level).clamp_range(1..=MAX_LEVEL - 1));
+            });
+            ui.add_space(6.0);
+
+            if ui
+                .button(egui::RichText::new("🔄 Play Again").size(18.0))
+                .on_hover_text("Lower the chosen words to that level; history is kept")
+                .clicked()
+            {
+                self.reset_tags.retain(|t| tags.contains(t));
+                for i in pool {
+                    let word = &mut self.words[i];
+                    if self.reset_tags.is_empty()
+                        || self.reset_tags.iter().any(|t| word.tags.contains(t))
+                    {
+                        word.level = word.level.min(self.reset_level);
+                        word.mastered_streak = 0;
+                        word.mastered_since = 0;
+                    }
+                }
+                self.mark_dirty();
+                self.start_game(self.session.clone());
+            }
+
+            if ui
+                .button(egui::RichText::new("🧹 Maintenance cycle").size(18.0))
+                .on_hover_text("Keep every level and quiz a sample of mastered words instead")
+                .clicked()
+            {
+                self.start_maintenance();
+            }
+
+            if ui
+                .button(egui::RichText::new("➕ Add More Words").size(18.0))
+                .clicked()
+            {
+                self.screen = Screen::AddWords;
+            }
+
+            ui.add_space(10.0);
+            ui.label("Thanks for using woro!");
+        });
+    }
+}
+
+// ------------------- Core logic -------------------
+impl App {
+    fn toggle_capture(&mut self) {
+        if self.capture.take().is_some() {
+            return;
+        }
+        match arboard::Clipboard::new() {
+            Ok(mut clipboard) => {
+                // Whatever is already on the clipboard was copied before capture started
+                let last = clipboard.get_text().unwrap_or_default();
+                self.capture = Some(ClipboardCapture {
+                    clipboard,
+                    last,
+                    polled_at: Instant::now(),
+                });
+            }
+            Err(e) => self.notify_error(format!("Error opening the clipboard: {}", e)),
+        }
+    }
+
+    // Reads the clipboard once every CLIPBOARD_POLL, however often the UI repaints
+    fn poll_clipboard(&mut self, ctx: &egui::Context) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+        let elapsed = capture.polled_at.elapsed();
+        if elapsed < CLIPBOARD_POLL {
+            ctx.request_repaint_after(CLIPBOARD_POLL - elapsed);
+            return;
+        }
+        capture.polled_at = Instant::now();
+        ctx.request_repaint_after(CLIPBOARD_POLL);
+        let Ok(text) = capture.clipboard.get_text() else {
+            return;
+        };
+        if text == capture.last {
+            return;
+        }
+        capture.last = text.clone();
+
+        let Some(text) = capturable(&text) else {
+            return;
+        };
+        let lower = text.to_lowercase();
+        let known = self.words.iter().any(|w| w.foreign.to_lowercase() == lower)
+            || self.inbox.iter().any(|i| i.text.to_lowercase() == lower);
+        if known {
+            return;
+        }
+        self.inbox.push(InboxItem {
+            text,
+            translation: String::new(),
+            captured_at: now_secs(),
+            context: String::new(),
+        });
+        self.save_inbox();
+    }
+
+    // Nothing for words without stroke data
+    fn stroke_order_panel(&self, ui: &mut egui::Ui, text: &str) {
+        let characters: Vec<&Vec<Vec<egui::Pos2>>> =
+            text.chars().filter_map(|c| self.strokes.get(&c)).collect();
+        if characters.is_empty() {
+            return;
+        }
+        egui::CollapsingHeader::new("✍ Stroke order").show(ui, |ui| {
+            let secs = self.question_shown_at.elapsed().as_secs_f32();
+            ui.horizontal_wrapped(|ui| {
+                for strokes in characters {
+                    stroke_order(ui, strokes, secs, 120.0);
+                }
+            });
+            ui.ctx().request_repaint();
+        });
+    }
+
+    fn inbox_panel(&mut self, ui: &mut egui::Ui) {
+        let mut add = None;
+        let mut remove = None;
+        let mut edited = false;
+        egui::CollapsingHeader::new(format!("📥 Inbox ({})", self.inbox.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new("inbox_grid")
+                    .num_columns(3)
+                    .spacing([10.0, 4.0])
+                    .show(ui, |ui| {
+                        for (i, item) in self.inbox.iter_mut().enumerate() {
+                            let label = ui
+                                .horizontal(|ui| {
+                                    frequency_badge(ui, &self.frequency, &item.text);
+                                    ui.label(&item.text)
+                                })
+                                .inner;
+                            if !item.context.is_empty() {
+                                label.on_hover_text(&item.context);
+                            }
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut item.translation)
+                                    .hint_text("translation")
+                                    .desired_width(180.0),
+                            );
+                            edited |= response.lost_focus();
+                            let enter = response.lost_focus()
+                                && ui.input(|inp| inp.key_pressed(egui::Key::Enter));
+                            ui.horizontal(|ui| {
+                                let ready = !item.translation.trim().is_empty();
+                                if ui.add_enabled(ready, egui::Button::new("➕")).clicked()
+                                    || (ready && enter)
+                                {
+                                    add = Some(i);
+                                }
+                                if ui.button("🗑").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(i) = add {
+            let item = self.inbox.remove(i);
+            let mut word = Word::new(item.text, item.translation.trim().to_string());
+            word.tags.push("inbox".to_string());
+            word.notes = item.context;
+            self.push_word(word);
+            self.mark_dirty();
+        } else if let Some(i) = remove {
+            self.inbox.remove(i);
+        }
+        if add.is_some() || remove.is_some() || edited {
+            self.save_inbox();
+        }
+    }
+
+    fn save_inbox(&mut self) {
+        if let Err(e) = save_inbox(&self.inbox) {
+            self.notify_error(e);
+        }
+    }
+
+    fn add_word(&mut self) {
+        if !self.new_foreign.trim().is_empty() && !self.new_translation.trim().is_empty() {
+            self.push_word(Word::new(
+                self.new_foreign.trim().to_string(),
+                self.new_translation.trim().to_string(),
+            ));
+            self.new_foreign.clear();
+            self.new_translation.clear();
+            self.mark_dirty();
+        }
+    }
+
+    fn import_from_txt(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Text Files", &["txt"])
+            .set_title("Select word list")
+            .pick_file()
+        {
+            match fs::read_to_string(&path) {
+                Ok(content) => self.parse_txt_content(deck_name(&path), &content),
+                Err(e) => self.notify_error(format!("Error reading file: {}", e)),
+            }
+        }
+    }
+
+    // Each line the OCR command reads off the photo is a TXT line, so the import preview
+    // (and, for lines without a separator, the split dialog) gets to fix what it misread
+    fn import_from_photo(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(
+                "Images",
+                &["png", "jpg", "jpeg", "gif", "webp", "bmp", "tif", "tiff"],
+            )
+            .set_title("Choose a photo of a word list")
+            .pick_file()
+        else {
+            return;
+        };
+        // OCR of a photo takes seconds; poll_ocr picks up the text
+        let command = self.settings.ocr_command.clone();
+        let image = path.clone();
+        let rx = spawn_with_result(self.egui_ctx.clone(), move || run_ocr(&command, &image));
+        self.ocr_run = Some((path, rx));
+    }
+
+    fn poll_ocr(&mut self) {
+        let Some(result) = self.ocr_run.as_ref().and_then(|(_, rx)| rx.try_recv().ok()) else {
+            return;
+        };
+        let Some((path, _)) = self.ocr_run.take() else {
+            return;
+        };
+        match result {
+            Ok(text) if text.trim().is_empty() => {
+                self.notify_error(format!("No text found in {}", path.display()))
+            }
+            Ok(text) => self.parse_txt_content(deck_name(&path), &text),
+            Err(e) => self.notify_error(format!("Error reading {}: {}", path.display(), e)),
+        }
+    }
+
+    // Tags the words from `from` on that look like their translation and says how many
+    fn flag_cognates(&mut self, from: usize) {
+        let mut flagged = 0;
+        for word in self.words.iter_mut().skip(from) {
+            if cognate_similarity(&word.foreign, &word.translation) >= COGNATE_SIMILARITY
+                && !word.tags.iter().any(|t| t == COGNATE_TAG)
+            {
+                word.tags.push(COGNATE_TAG.to_string());
+                flagged += 1;
+            }
+        }
+        if flagged > 0 {
+            self.notify(format!(
+                "🆓 {} look like cognates; see the Cognates view to skip them",
+                flagged
+            ));
+        }
+    }
+
+    // Directives apply to every line after them until the next one of the same kind
+    fn parse_txt_content(&mut self, source: String, content: &str) {
+        let mut imported = Vec::new();
+        let mut skipped = 0usize;
+        let mut tags: Vec<String> = Vec::new();
+        let mut deck: Option<String> = None;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('#') {
+                match parse_txt_directive(line) {
+                    Some(TxtDirective::Tags(t)) => tags = t,
+                    Some(TxtDirective::Deck(d)) => deck = d,
+                    None => {}
+                }
+                continue;
+            }
+
+            match split_txt_line(line) {
+                TxtLine::Pair(foreign, translation) => {
+                    let mut word = Word::new(foreign, translation);
+                    word.tags = tags.clone();
+                    imported.push((deck.clone(), word));
+                }
+                TxtLine::Ambiguous(tokens) => self.ambiguous_lines.push(AmbiguousLine {
+                    tokens,
+                    split: 1,
+                    keep: true,
+                    tags: tags.clone(),
+                    deck: deck.clone(),
+                }),
+                TxtLine::Invalid => skipped += 1,
+            }
+        }
+
+        self.preview_import(source, imported, skipped);
+        if !self.ambiguous_lines.is_empty() {
+            self.notify(format!(
+                "❓ {} lines need you to say where the translation starts",
+                self.ambiguous_lines.len()
+            ));
+        }
+    }
+
+    // Opens the import preview on these words, or adds them to the one already open
+    fn preview_import(
+        &mut self,
+        source: String,
+        words: Vec<(Option<String>, Word)>,
+        skipped: usize,
+    ) {
+        if words.is_empty() {
+            if skipped > 0 {
+                self.notify_error(format!(
+                    "No words found in {}, skipped {} lines",
+                    source, skipped
+                ));
+            }
+            return;
+        }
+        let preview = self.import_preview.get_or_insert_with(|| ImportPreview {
+            source,
+            rows: Vec::new(),
+            skipped: 0,
+            tags: String::new(),
+        });
+        preview.skipped += skipped;
+        preview
+            .rows
+            .extend(words.into_iter().map(|(deck, word)| PreviewRow {
+                keep: true,
+                foreign: word.foreign,
+                translation: word.translation,
+                tags: word.tags.join(", "),
+                notes: word.notes,
+                deck,
+            }));
+    }
+
+    // Imported words go to this deck, or to the sibling deck file a #deck: directive names
+    // (created if needed). Returns how many were added.
+    fn add_to_decks(&mut self, imported: Vec<(Option<String>, Word)>) -> usize {
+        let first_new = self.words.len();
+        let current = deck_name(&self.deck_path);
+        let overwrite = self.settings.import_overwrite_translations;
+        let mut others: HashMap<String, Vec<Word>> = HashMap::new();
+        let mut added = 0;
+        let mut existing = 0;
+        let mut updated = 0;
+        let mut index = ImportIndex::new(&self.words);
+        for (deck, word) in imported {
+            match deck {
+                Some(deck) if deck != current => others.entry(deck).or_default().push(word),
+                _ => match import_word(&mut self.words, &mut index, word, overwrite) {
+                    ImportOutcome::Added => added += 1,
+                    ImportOutcome::Existing => existing += 1,
+                    ImportOutcome::Updated => updated += 1,
+                },
+            }
+        }
+        if self.words.len() > first_new {
+            self.flag_cognates(first_new);
+        }
+        if added + existing + updated > 0 {
+            self.mark_dirty();
+        }
+        if existing + updated > 0 {
+            self.notify(format!(
+                "↺ {} words were already in the deck and kept their level ({} translations updated)",
+                existing + updated,
+                updated
+            ));
+        }
+
+        for (deck, words) in others {
+            let file: String = deck
+                .chars()
+                .map(|c| if c == '/' || c == '\\' { '_' } else { c })
+                .collect();
+            let path = self.deck_path.with_file_name(format!("{}.json", file));
+            match self.add_to_other_deck(&path, words, overwrite) {
+                Ok(count) => {
+                    self.notify(format!("✅ Added {} words to deck {}", count, deck));
+                    added += count;
+                    if !self.recent_decks.contains(&path) {
+                        self.recent_decks.push(path);
+                    }
+                }
+                Err(e) => self.notify_error(e),
+            }
+        }
+        added
+    }
+
+    // Saved the way that deck is when open: its own lock, backend and file format. The
+    // write is waited for so the lock covers it.
+    fn add_to_other_deck(
+        &mut self,
+        path: &Path,
+        words: Vec<Word>,
+        overwrite: bool,
+    ) -> Result<usize, String> {
+        let lock_path = path.with_file_name(format!("{}.lock", deck_name(path)));
+        let lock = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&lock_path)
+            .map_err(|e| format!("Error opening lock file {}: {}", lock_path.display(), e))?;
+        if lock.try_lock_exclusive().is_err() {
+            return Err(format!(
+                "{} is open in another woro instance; its words weren't added",
+                path.display()
+            ));
+        }
+        let deck_settings = read_deck_settings(path)?;
+        let storage = open_storage(
+            self.settings.storage,
+            path,
+            deck_settings.compress,
+            deck_settings.binary,
+        )
+        .map_err(|e| format!("Error opening {}: {}", path.display(), e))?;
+        let mut existing = storage
+            .load()
+            .map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+        let mut count = 0;
+        let mut index = ImportIndex::new(&existing);
+        for word in words {
+            if import_word(&mut existing, &mut index, word, overwrite) == ImportOutcome::Added {
+                count += 1;
+            }
+        }
+        self.saver.submit(SaveJob::Words {
+            path: path.to_path_buf(),
+            storage,
+            words: existing,
+        });
+        if !self.saver.flush() {
+            return Err(self.saver.errors.pop().unwrap_or_default());
+        }
+        Ok(count)
+    }
+
+    fn import_spreadsheet(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Spreadsheets", &["csv", "xlsx"])
+            .set_title("Select word list")
+            .pick_file()
+        {
+            let is_xlsx = path
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("xlsx"))
+                .unwrap_or(false);
+            let table = if is_xlsx {
+                read_xlsx_table(&path)
+            } else {
+                read_csv_table(&path)
+            };
+
+            match table {
+                Ok((headers, rows)) => self.open_column_mapping(deck_name(&path), headers, rows),
+                Err(e) => self.notify_error(format!("Error reading file: {}", e)),
+            }
+        }
+    }
+
+    fn import_anki(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Anki Deck Package", &["apkg"])
+            .set_title("Select Anki deck")
+            .pick_file()
+        {
+            match read_apkg_cards(&path) {
+                Ok(cards) if cards.is_empty() => {
+                    self.notify_error(format!("No cards found in {}", path.display()))
+                }
+                Ok(cards) => {
+                    self.anki_import = Some(AnkiImport {
+                        cards,
+                        seed_levels: true,
+                    })
+                }
+                Err(e) => self.notify_error(format!("Error reading Anki deck: {}", e)),
+            }
+        }
+    }
+
+    // Kindle lookups go into a deck of their own, which then opens
+    fn import_kindle(&mut self) {
+        let Some(source) = rfd::FileDialog::new()
+            .add_filter("Kindle vocabulary", &["db"])
+            .set_title("Select Kindle vocab.db")
+            .pick_file()
+        else {
+            return;
+        };
+        let words = match read_kindle_vocab(&source) {
+            Ok(words) if words.is_empty() => {
+                self.notify_error(format!("No words found in {}", source.display()));
+                return;
+            }
+            Ok(words) => words,
+            Err(e) => {
+                self.notify_error(format!("Error reading Kindle vocabulary: {}", e));
+                return;
+            }
+        };
+
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("woro deck", &["json"])
+            .set_title("Save Kindle words as")
+            .set_file_name("kindle.json")
+            .save_file()
+        else {
+            return;
+        };
+        if path == self.deck_path {
+            self.notify_error("Pick a different file than the current deck".to_string());
+            return;
+        }
+        let result = serde_json::to_string_pretty(&words)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            self.notify_error(format!("Error saving to {}: {}", path.display(), e));
+            return;
+        }
+
+        self.notify(format!(
+            "✅ Imported {} Kindle words into {}; add translations to start practising them",
+            words.len(),
+            path.display()
+        ));
+        self.open_deck(path);
+    }
+
+    fn mine_subtitles(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Subtitles", &["srt"])
+            .set_title("Select subtitle file")
+            .pick_file()
+        else {
+            return;
+        };
+        let content = match fs::read(&path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                self.notify_error(format!("Error reading file: {}", e));
+                return;
+            }
+        };
+
+        let known: HashSet<String> = self
+            .words
+            .iter()
+            .map(|w| w.foreign.to_lowercase())
+            .chain(self.inbox.iter().map(|i| i.text.to_lowercase()))
+            .collect();
+        let candidates = mine_words(&srt_lines(&content), &known);
+        if candidates.is_empty() {
+            self.notify_error(format!("No new words found in {}", path.display()));
+            return;
+        }
+        self.subtitle_mining = Some(SubtitleMining {
+            source: deck_name(&path),
+            candidates,
+        });
+    }
+
+    fn apply_anki_import(&mut self, pending: AnkiImport) {
+        let first_new = self.words.len();
+        let overwrite = self.settings.import_overwrite_translations;
+        let mut added = 0;
+        let mut existing = 0;
+        let mut index = ImportIndex::new(&self.words);
+        for card in pending.cards {
+            let level = if pending.seed_levels {
+                card.seeded_level()
+            } else {
+                1
+            };
+            let mut word = Word::new(card.foreign, card.translation);
+            word.tags = card.tags;
+            word.level = level;
+            match import_word(&mut self.words, &mut index, word, overwrite) {
+                ImportOutcome::Added => added += 1,
+                _ => existing += 1,
+            }
+        }
+
+        if added > 0 {
+            self.flag_cognates(first_new);
+        }
+        if added + existing > 0 {
+            self.mark_dirty();
+        }
+        self.notify(format!(
+            "✅ Added {} words from Anki, {} already in the deck kept their level",
+            added, existing
+        ));
+    }
+
+    fn open_column_mapping(
+        &mut self,
+        source: String,
+        headers: Vec<String>,
+        rows: Vec<Vec<String>>,
+    ) {
+        if headers.is_empty() {
+            self.notify_error("Import file has no columns".to_string());
+            return;
+        }
+
+        // Pre-select fields from the header names, falling back to column order
+        let mut fields: Vec<ImportField> = headers
+            .iter()
+            .map(|h| {
+                let h = h.to_lowercase();
+                if h.contains("note") {
+                    ImportField::Notes
+                } else if h.contains("tag") {
+                    ImportField::Tags
+                } else {
+                    ImportField::Ignore
+                }
+            })
+            .collect();
+        for wanted in [ImportField::Foreign, ImportField::Translation] {
+            if let Some(slot) = fields.iter_mut().find(|f| **f == ImportField::Ignore) {
+                *slot = wanted;
+            }
+        }
+
+        self.column_mapping = Some(ColumnMapping {
+            source,
+            headers,
+            rows,
+            fields,
+        });
+    }
+
+    fn apply_column_mapping(&mut self, mapping: ColumnMapping) {
+        let mut imported = Vec::new();
+        let mut skipped = 0usize;
+
+        for row in &mapping.rows {
+            let mut foreign = String::new();
+            let mut translation = String::new();
+            let mut tags = Vec::new();
+            let mut notes = Vec::new();
+
+            for (cell, field) in row.iter().zip(&mapping.fields) {
+                if cell.is_empty() {
+                    continue;
+                }
+                match field {
+                    ImportField::Ignore => {}
+                    ImportField::Foreign => foreign = cell.clone(),
+                    ImportField::Translation => translation = cell.clone(),
+                    ImportField::Tags => tags.extend(split_tags(cell)),
+                    ImportField::Notes => notes.push(cell.as_str()),
+                }
+            }
+
+            if foreign.is_empty() || translation.is_empty() {
+                skipped += 1;
+                continue;
+            }
+
+            let mut word = Word::new(foreign, translation);
+            word.tags = tags;
+            word.notes = notes.join("\n");
+            imported.push((None, word));
+        }
+
+        self.preview_import(mapping.source, imported, skipped);
+    }
+
+    fn is_stale(&self, word: &Word) -> bool {
+        word.last_reviewed_at < now_secs().saturating_sub(self.stale_days as u64 * SECS_PER_DAY)
+    }
+
+    fn in_word_view(&self, word: &Word) -> bool {
+        if self.word_view == WordView::Archived {
+            return word.archived;
+        }
+        if word.archived {
+            return false;
+        }
+        match self.word_view {
+            WordView::All | WordView::Archived => true,
+            WordView::RecentlyAdded => {
+                let since = now_secs().saturating_sub(self.recent_days as u64 * SECS_PER_DAY);
+                word.created_at >= since
+            }
+            WordView::RecentlyFailed => self.recently_failed.contains(&word.foreign),
+            WordView::NotReviewed => self.is_stale(word),
+            WordView::Cognates => word.tags.iter().any(|t| t == COGNATE_TAG),
+        }
+    }
+
+    // Words answered wrong in any of the last few sessions
+    fn refresh_recently_failed(&mut self) {
+        let mut sessions: Vec<u64> = Vec::new();
+        for event in self.reviews.iter().rev() {
+            if !sessions.contains(&event.session) {
+                if sessions.len() == RECENT_SESSIONS {
+                    break;
+                }
+                sessions.push(event.session);
+            }
+        }
+
+        self.recently_failed = self
+            .reviews
+            .iter()
+            .filter(|e| !e.correct && sessions.contains(&e.session))
+            .map(|e| e.foreign.clone())
+            .collect();
+    }
+
+    fn edit_word(&mut self, index: usize) {
+        if let Some(word) = self.words.get(index) {
+            self.word_editor = Some(WordEditor {
+                index,
+                foreign: word.foreign.clone(),
+                translation: word.translation.clone(),
+                tags: word.tags.join(", "),
+                notes: word.notes.clone(),
+                snooze: (word.snoozed_until > now_secs()).then(|| secs_to_date(word.snoozed_until)),
+                due: (word.due_at != 0).then(|| secs_to_date(word.due_at)),
+                pinned_daily: word.pinned_daily,
+                image: word.image.clone(),
+                conjugations: format_conjugations(&word.conjugations),
+                article: word.article.clone(),
+                fetching: None,
+            });
+        }
+    }
+
+    fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.words.iter().flat_map(|w| w.tags.clone()).collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    fn palette_entries(&self) -> Vec<(PaletteAction, String)> {
+        let mut entries = vec![
+            (PaletteAction::AddWords, "➕ Add Words".to_string()),
+            (PaletteAction::ImportTxt, "📁 Import from TXT".to_string()),
+            (
+                PaletteAction::ImportSpreadsheet,
+                "📊 Import CSV / Excel".to_string(),
+            ),
+            (PaletteAction::ImportAnki, "🃏 Import Anki deck".to_string()),
+            (
+                PaletteAction::ImportCode,
+                "🔗 Import from code / URL…".to_string(),
+            ),
+            (
+                PaletteAction::ImportPhoto,
+                "📷 Import from a photo of a word list…".to_string(),
+            ),
+            (
+                PaletteAction::ImportKindle,
+                "📖 Import Kindle Vocabulary Builder".to_string(),
+            ),
+            (
+                PaletteAction::MineSubtitles,
+                "🎬 Find new words in subtitles (.srt)".to_string(),
+            ),
+            (PaletteAction::OpenDeck, "📂 Open deck…".to_string()),
+            (PaletteAction::NewDeck, "🆕 New deck…".to_string()),
+            (PaletteAction::MergeDeck, "🔀 Merge from file…".to_string()),
+            (PaletteAction::SplitDeck, "✂ Split deck…".to_string()),
+            (
+                PaletteAction::PushToAnki,
+                "📤 Send words to Anki…".to_string(),
+            ),
+            (PaletteAction::ExportTxt, "📝 Export as TXT".to_string()),
+            (PaletteAction::ShareQr, "📱 Share as QR code…".to_string()),
+            (PaletteAction::ExportJson, "🗄 Export as JSON".to_string()),
+            (
+                PaletteAction::ExportAudio,
+                "🔊 Export audio playlist for listening…".to_string(),
+            ),
+            (
+                PaletteAction::MediaManager,
+                "🖼 Manage media files…".to_string(),
+            ),
+            (
+                PaletteAction::MakeClozeCards,
+                "🧩 Make cloze cards from example sentences".to_string(),
+            ),
+            (
+                PaletteAction::Dictation,
+                "🔢 Number, date and time dictation".to_string(),
+            ),
+            (
+                PaletteAction::Present,
+                "🎬 Present words full screen".to_string(),
+            ),
+        ];
+        if self.has_unsaved_changes() {
+            entries.push((PaletteAction::SaveNow, "💾 Save now".to_string()));
+        }
+        if !self.words.is_empty() {
+            entries.push((PaletteAction::Game, "🎮 Go to Game".to_string()));
+            entries.push((
+                PaletteAction::CustomSession,
+                "🧪 Custom study session…".to_string(),
+            ));
+        }
+        if self
+            .words
+            .iter()
+            .any(|w| w.archived || w.level >= MAX_LEVEL)
+        {
+            entries.push((
+                PaletteAction::Maintenance,
+                "🧹 Maintenance review of mastered words".to_string(),
+            ));
+        }
+        if self.words.iter().any(|w| !w.archived && self.is_stale(w)) {
+            entries.push((
+                PaletteAction::StaleSession,
+                format!("⏳ Review words not seen in {} days", self.stale_days),
+            ));
+        }
+        for tag in self.all_tags() {
+            let label = format!("🏷 Start session tagged \"{}\"", tag);
+            entries.push((PaletteAction::TaggedSession(tag), label));
+        }
+        for deck in self.recent_decks.iter().filter(|d| **d != self.deck_path) {
+            let label = format!("📂 Switch deck: {}", deck_name(deck));
+            entries.push((PaletteAction::SwitchDeck(deck.clone()), label));
+        }
+        for (i, word) in self.words.iter().enumerate() {
+            let label = format!("✏ {} = {}", word.foreign, word.translation);
+            entries.push((PaletteAction::EditWord(i), label));
+        }
+        entries
+    }
+
+    fn run_palette_action(&mut self, action: PaletteAction) {
+        match action {
+            PaletteAction::EditWord(index) => {
+                self.screen = Screen::AddWords;
+                self.edit_word(index);
+            }
+            PaletteAction::AddWords => self.screen = Screen::AddWords,
+            PaletteAction::PushToAnki => {
+                let decks_rx = spawn_with_result(self.egui_ctx.clone(), || {
+                    anki_connect("deckNames", serde_json::json!({}))
+                        .ok()
+                        .and_then(|names| serde_json::from_value(names).ok())
+                        .unwrap_or_default()
+                });
+                self.anki_push = Some(AnkiPush {
+                    anki_decks: Vec::new(),
+                    deck: deck_name(&self.deck_path),
+                    include_archived: false,
+                    status: None,
+                    decks_rx: Some(decks_rx),
+                    sending: None,
+                });
+            }
+            PaletteAction::SaveNow => {
+                self.flush_dirty();
+                self.saver.flush();
+            }
+            PaletteAction::Game => self.start_game(SessionKind::All),
+            PaletteAction::TaggedSession(tag) => self.start_game(SessionKind::Tagged(tag)),
+            PaletteAction::StaleSession => self.start_game(SessionKind::Stale),
+            PaletteAction::Maintenance => self.start_maintenance(),
+            PaletteAction::ImportTxt => self.import_from_txt(),
+            PaletteAction::ImportSpreadsheet => self.import_spreadsheet(),
+            PaletteAction::ImportAnki => self.import_anki(),
+            PaletteAction::ImportKindle => self.import_kindle(),
+            PaletteAction::ImportCode => self.code_import = Some(String::new()),
+            PaletteAction::ImportPhoto => self.import_from_photo(),
+            PaletteAction::MineSubtitles => self.mine_subtitles(),
+            PaletteAction::ExportTxt => self.export_txt(),
+            PaletteAction::ExportJson => self.export_json(),
+            PaletteAction::ExportAudio => self.export_audio(),
+            PaletteAction::MakeClozeCards => self.make_cloze_cards(),
+            PaletteAction::Dictation => self.screen = Screen::Dictation,
+            PaletteAction::Present => self.start_presentation(),
+            PaletteAction::MediaManager => {
+                self.media_manager = Some(self.scan_media());
+            }
+            PaletteAction::SwitchDeck(path) => self.open_deck(path),
+            PaletteAction::OpenDeck => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("woro deck", &["json"])
+                    .set_title("Open deck")
+                    .pick_file()
+                {
+                    self.open_deck(path);
+                }
+            }
+            PaletteAction::NewDeck => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("woro deck", &["json"])
+                    .set_title("Create deck")
+                    .set_file_name("deck.json")
+                    .save_file()
+                {
+                    self.open_deck(path);
+                    self.mark_dirty();
+                }
+            }
+            PaletteAction::MergeDeck => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("woro deck", &["json"])
+                    .set_title("Merge from file")
+                    .pick_file()
+                {
+                    self.saver.flush();
+                    match read_deck_file(&path) {
+                        Ok(words) => {
+                            let mut report = self.merge_words(words);
+                            report.source = deck_name(&path);
+                            self.merge_report = Some(report);
+                        }
+                        Err(e) => {
+                            self.notify_error(format!("Error reading {}: {}", path.display(), e))
+                        }
+                    }
+                }
+            }
+            PaletteAction::CustomSession => {
+                self.session_builder = Some(SessionBuilder {
+                    tags: Vec::new(),
+                    min_level: 1,
+                    max_level: MAX_LEVEL,
+                    recently_failed: false,
+                    limit: 20,
+                    mode_index: self.mode_index,
+                    counts: false,
+                });
+            }
+            PaletteAction::ShareQr => {
+                let mut share = ShareQr {
+                    url: String::new(),
+                    payload: Ok(String::new()),
+                    qr: None,
+                };
+                share.set_payload(self.deck_share_code());
+                self.share_qr = Some(share);
+            }
+            PaletteAction::SplitDeck => {
+                self.split_filter = Some(SplitFilter {
+                    tag: None,
+                    min_level: 1,
+                    max_level: MAX_LEVEL,
+                    recent_only: false,
+                    recent_days: 7,
+                });
+            }
+        }
+    }
+
+    // Moves the matching words into a new deck file and out of this one
+    fn split_deck(&mut self, filter: &SplitFilter) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("woro deck", &["json"])
+            .set_title("Save split-off words as")
+            .set_file_name(format!("{}.json", filter.tag.as_deref().unwrap_or("split")))
+            .save_file()
+        else {
+            return;
+        };
+        if path == self.deck_path {
+            self.notify_error("Pick a different file than the current deck".to_string());
+            return;
+        }
+
+        // Decided once, so a word can't land in both decks or neither as time passes
+        let now = now_secs();
+        let (moved, kept): (Vec<Word>, Vec<Word>) = self
+            .words
+            .iter()
+            .cloned()
+            .partition(|w| filter.matches(w, now));
+
+        // Only drop the words from this deck once the new deck is safely on disk
+        let saved = open_storage(self.settings.storage, &path, false, false)
+            .and_then(|mut storage| storage.save(&moved));
+        if let Err(e) = saved {
+            self.notify_error(format!("Error saving to {}: {}", path.display(), e));
+            return;
+        }
+
+        self.words = kept;
+        self.split_sidecars(&path, &moved);
+        self.word_editor = None;
+        self.current_word_index = 0;
+        self.mark_dirty();
+
+        self.recent_decks.retain(|p| *p != path);
+        self.recent_decks
+            .insert(1.min(self.recent_decks.len()), path.clone());
+        self.notify(format!(
+            "✅ Moved {} words to {}",
+            moved.len(),
+            path.display()
+        ));
+    }
+
+    // The moved words take their pictures and review history along to the new deck
+    fn split_sidecars(&mut self, path: &Path, moved: &[Word]) {
+        let media = self.sidecar_path("media");
+        let new_media = path.with_file_name(format!("{}.media", deck_name(path)));
+        for word in moved.iter().filter(|w| !w.image.is_empty()) {
+            let result = fs::create_dir_all(&new_media)
+                .and_then(|()| fs::rename(media.join(&word.image), new_media.join(&word.image)));
+            if let Err(e) = result {
+                self.notify_error(format!("Error moving picture {}: {}", word.image, e));
+            }
+        }
+
+        let ids: HashSet<u64> = moved.iter().map(|w| w.id).collect();
+        let (gone, stayed): (Vec<ReviewEvent>, Vec<ReviewEvent>) =
+            std::mem::take(&mut self.reviews)
+                .into_iter()
+                .partition(|e| ids.contains(&e.word_id));
+        self.reviews = stayed;
+        if gone.is_empty() || self.read_only {
+            return;
+        }
+        let result = open_storage(self.settings.storage, path, false, false)
+            .and_then(|mut new_storage| new_storage.replace_events(&gone))
+            .and_then(|()| self.storage.replace_events(&self.reviews));
+        if let Err(e) = result {
+            self.notify_error(format!("Error splitting the review history: {}", e));
+        }
+        self.refresh_recently_failed();
+    }
+
+    // Unions another deck's words into this one. A word counts as a duplicate when both
+    // foreign word and translation match (ignoring case); duplicates keep the higher
+    // level and gain the other copy's tags and notes. The same foreign word with another
+    // translation is reported as a conflict instead.
+    fn merge_words(&mut self, incoming: Vec<Word>) -> MergeReport {
+        let mut report = MergeReport::default();
+
+        for other in incoming {
+            let foreign = other.foreign.to_lowercase();
+            let translation = other.translation.to_lowercase();
+            let exact = self.words.iter().position(|w| {
+                w.foreign.to_lowercase() == foreign && w.translation.to_lowercase() == translation
+            });
+            if exact.is_none() {
+                if let Some(word) = self
+                    .words
+                    .iter()
+                    .find(|w| w.foreign.to_lowercase() == foreign)
+                {
+                    report.conflicts.push(MergeConflict {
+                        word_id: word.id,
+                        foreign: word.foreign.clone(),
+                        ours: word.translation.clone(),
+                        theirs: other.translation,
+                    });
+                    continue;
+                }
+            }
+
+            let Some(word) = exact.map(|i| &mut self.words[i]) else {
+                self.push_word(other);
+                report.added += 1;
+                continue;
+            };
+
+            report.duplicates += 1;
+            if other.level > word.level {
+                word.level = other.level.min(MAX_LEVEL);
+                report.level_raised += 1;
+            }
+
+            let mut new_tags = false;
+            for tag in other.tags {
+                if !word.tags.contains(&tag) {
+                    word.tags.push(tag);
+                    new_tags = true;
+                }
+            }
+            if new_tags {
+                report.tags_merged += 1;
+            }
+
+            let notes = other.notes.trim();
+            if !notes.is_empty() && !word.notes.contains(notes) {
+                if word.notes.is_empty() {
+                    word.notes = notes.to_string();
+                } else {
+                    word.notes = format!("{}\n{}", word.notes, notes);
+                }
+                report.notes_merged += 1;
+            }
+
+            if word.created_at == 0 || (other.created_at != 0 && other.created_at < word.created_at)
+            {
+                word.created_at = other.created_at;
+            }
+            word.last_reviewed_at = word.last_reviewed_at.max(other.last_reviewed_at);
+        }
+
+        if report.added > 0 || report.level_raised + report.tags_merged + report.notes_merged > 0 {
+            self.mark_dirty();
+        }
+        report
+    }
+
+    fn start_game(&mut self, session: SessionKind) {
+        self.session = session;
+        if self.session_pool().is_empty() {
+            return;
+        }
+        self.session_id = now_secs();
+        self.last_answer_at = self.session_id;
+        self.session_answers.clear();
+        self.feedback_message.clear();
+        self.screen = Screen::Game;
+        self.pick_random_word();
+    }
+
+    // Word id -> id of its reverse card (foreign and translation swapped), if the deck
+    // has one
+    fn siblings(&self) -> HashMap<u64, u64> {
+        let key = |a: &str, b: &str| (ruby_base(a).trim().to_lowercase(), b.trim().to_lowercase());
+        let ids: HashMap<(String, String), u64> = self
+            .words
+            .iter()
+            .map(|w| (key(&w.foreign, &w.translation), w.id))
+            .collect();
+        self.words
+            .iter()
+            .filter_map(|w| {
+                let sibling = ids.get(&key(&w.translation, &w.foreign))?;
+                (*sibling != w.id).then_some((w.id, *sibling))
+            })
+            .collect()
+    }
+
+    // Keeps only the first of a word and its reverse card, as one gives the other away
+    fn bury_siblings(&self, ids: &mut Vec<u64>) {
+        let siblings = self.siblings();
+        let mut kept = HashSet::new();
+        ids.retain(|id| {
+            let buried = siblings.get(id).is_some_and(|s| kept.contains(s));
+            if !buried {
+                kept.insert(*id);
+            }
+            !buried
+        });
+    }
+
+    // Samples mastered and archived words for a one-pass refresher
+    fn start_maintenance(&mut self) {
+        let mut candidates: Vec<u64> = self
+            .words
+            .iter()
+            .filter(|w| w.archived || w.level >= MAX_LEVEL)
+            .map(|w| w.id)
+            .collect();
+        candidates.shuffle(&mut self.rng);
+        self.bury_siblings(&mut candidates);
+        candidates.truncate(self.settings.maintenance_sample as usize);
+
+        self.maintenance_queue = candidates;
+        self.maintenance_kept = 0;
+        self.maintenance_returned = 0;
+        self.start_game(SessionKind::Maintenance);
+    }
+
+    // Draws the builder's words once; the session runs until each has been asked
+    fn start_custom_session(&mut self, builder: &SessionBuilder) {
+        let now = now_secs();
+        let mut ids: Vec<u64> = self
+            .words
+            .iter()
+            .filter(|w| builder.matches(w, now, &self.recently_failed))
+            .map(|w| w.id)
+            .collect();
+        ids.shuffle(&mut self.rng);
+        self.bury_siblings(&mut ids);
+        if builder.limit > 0 {
+            ids.truncate(builder.limit as usize);
+        }
+
+        self.custom_queue = ids;
+        self.custom_right = 0;
+        self.custom_wrong = 0;
+        if builder.mode_index < self.modes.len() {
+            self.mode_index = builder.mode_index;
+        }
+        self.start_game(SessionKind::Custom {
+            counts: builder.counts,
+        });
+    }
+
+    // Indices of the words the current session draws from
+    fn session_pool(&self) -> Vec<usize> {
+        let now = now_secs();
+        self.words
+            .iter()
+            .enumerate()
+            // Words without a translation yet (e.g. fresh Kindle lookups) can't be asked
+            .filter(|(_, w)| !w.translation.is_empty() && !is_snoozed(w, now))
+            .filter(|(_, w)| match &self.session {
+                SessionKind::All => !w.archived,
+                SessionKind::Tagged(tag) => !w.archived && w.tags.contains(tag),
+                SessionKind::RecentlyFailed => {
+                    !w.archived && self.recently_failed.contains(&w.foreign)
+                }
+                SessionKind::Maintenance => self.maintenance_queue.contains(&w.id),
+                SessionKind::Stale => !w.archived && self.is_stale(w),
+                SessionKind::Custom { .. } => self.custom_queue.contains(&w.id),
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn export_review_log(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV Files", &["csv"])
+            .set_title("Export review log")
+            .set_file_name(format!("{}-revlog.csv", deck_name(&self.deck_path)))
+            .save_file()
+        else {
+            return;
+        };
+
+        match write_revlog_csv(&path, &self.reviews, &self.words) {
+            Ok(count) => self.notify(format!(
+                "✅ Exported {} reviews to {}",
+                count,
+                path.display()
+            )),
+            Err(e) => self.notify_error(format!("Error exporting review log: {}", e)),
+        }
+    }
+
+    fn export_txt(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Text Files", &["txt"])
+            .set_title("Export as TXT")
+            .set_file_name(format!("{}.txt", deck_name(&self.deck_path)))
+            .save_file()
+        else {
+            return;
+        };
+
+        match write_txt_deck(&path, &deck_name(&self.deck_path), &self.words) {
+            Ok(count) => self.notify(format!("✅ Exported {} words to {}", count, path.display())),
+            Err(e) => self.notify_error(format!("Error exporting TXT: {}", e)),
+        }
+    }
+
+    // Rendering takes a while for big decks, so it runs on its own thread
+    fn export_audio(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("OGG audio", &["ogg"])
+            .add_filter("MP3 audio", &["mp3"])
+            .add_filter("WAV audio (large)", &["wav"])
+            .set_title("Export audio playlist")
+            .set_file_name(format!("{}.ogg", deck_name(&self.deck_path)))
+            .save_file()
+        else {
+            return;
+        };
+
+        let words: Vec<(String, String)> = self
+            .words
+            .iter()
+            .filter(|w| !w.archived && !w.translation.is_empty())
+            .map(|w| (ruby_base(&w.foreign), w.translation.clone()))
+            .collect();
+        let foreign_voice = self.deck_settings.foreign_voice();
+        let native_voice = self.deck_settings.native_voice();
+        self.notify(format!(
+            "🔊 Rendering {} words to {}…",
+            words.len(),
+            path.display()
+        ));
+        self.audio_export = Some(spawn_with_result(self.egui_ctx.clone(), move || {
+            write_audio_playlist(&path, &words, &foreign_voice, &native_voice)
+                .map(|count| (count, path))
+        }));
+    }
+
+    fn poll_audio_export(&mut self) {
+        let Some(result) = self.audio_export.as_ref().and_then(|rx| rx.try_recv().ok()) else {
+            return;
+        };
+        self.audio_export = None;
+        match result {
+            Ok((count, path)) => {
+                self.notify(format!("✅ Exported {} words to {}", count, path.display()))
+            }
+            Err(e) => self.notify_error(format!("Error exporting audio: {}", e)),
+        }
+    }
+
+    // Plain JSON in the regular deck format, whatever this deck is saved as
+    fn export_json(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .set_title("Export as JSON")
+            .set_file_name(format!("{}-export.json", deck_name(&self.deck_path)))
+            .save_file()
+        else {
+            return;
+        };
+
+        let result = serde_json::to_string_pretty(&self.words)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => self.notify(format!(
+                "✅ Exported {} words to {}",
+                self.words.len(),
+                path.display()
+            )),
+            Err(e) => self.notify_error(format!("Error exporting JSON: {}", e)),
+        }
+    }
+
+    fn export_memrise(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Tab-separated text", &["tsv", "txt"])
+            .set_title("Export for Memrise")
+            .set_file_name(format!("{}-memrise.tsv", deck_name(&self.deck_path)))
+            .save_file()
+        else {
+            return;
+        };
+
+        let words: Vec<&Word> = self.words.iter().filter(|w| !w.archived).collect();
+        match write_memrise_tsv(&path, &words) {
+            Ok(count) => self.notify(format!("✅ Exported {} words to {}", count, path.display())),
+            Err(e) => self.notify_error(format!("Error exporting for Memrise: {}", e)),
+        }
+    }
+
+    // Due words come first; once they're done the rest of the pool keeps the game going
+    fn pick_random_word(&mut self) {
+        self.question = QuestionState::Asking;
+        self.modes[self.mode_index].new_question();
+        let pool = self.session_pool();
+        if pool.is_empty() {
+            return;
+        }
+
+        // The stale queue ignores the schedule: longest-untouched word first
+        if self.session == SessionKind::Stale {
+            if let Some(oldest) = pool
+                .iter()
+                .copied()
+                .min_by_key(|&i| (self.words[i].last_reviewed_at, self.words[i].created_at))
+            {
+                self.current_word_index = oldest;
+                self.question_shown_at = Instant::now();
+            }
+            return;
+        }
+
+        let now = now_secs();
+        let mode = &self.modes[self.mode_index];
+        let cloze = mode.kind() == ReviewMode::Cloze;
+        let mut pool = pool;
+        pool.retain(|&i| mode.fits(&self.words[i]));
+        if pool.is_empty() {
+            return;
+        }
+
+        // A learning step that's come round goes first
+        self.questions_asked += 1;
+        let learning_due = self
+            .learning
+            .iter()
+            .filter(|(_, l)| l.due_at <= self.questions_asked)
+            .min_by_key(|(_, l)| l.due_at)
+            .and_then(|(&id, _)| pool.iter().copied().find(|&i| self.words[i].id == id));
+        if let Some(i) = learning_due {
+            self.current_word_index = i;
+            self.question_shown_at = Instant::now();
+            return;
+        }
+        let due: Vec<usize> = pool
+            .iter()
+            .copied()
+            .filter(|&i| {
+                if cloze {
+                    self.is_cloze_due(&self.words[i], now)
+                } else {
+                    self.is_due(&self.words[i], now)
+                }
+            })
+            .collect();
+        // A custom session picked its words itself, due or not
+        let custom = matches!(self.session, SessionKind::Custom { .. });
+        let mut candidates = if due.is_empty() || custom { pool } else { due };
+
+        // Not the reverse card of a word already asked this session, unless that's all
+        // there is left
+        let siblings = self.siblings();
+        let asked: HashSet<u64> = self.session_answers.iter().map(|a| a.word_id).collect();
+        let unburied: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|&i| {
+                !siblings
+                    .get(&self.words[i].id)
+                    .is_some_and(|s| asked.contains(s))
+            })
+            .collect();
+        if !unburied.is_empty() {
+            candidates = unburied;
+        }
+
+        // Of the never-seen words only the most frequent one is up for introduction
+        if self.deck_settings.frequency_order && !self.frequency.is_empty() {
+            let first_new = candidates
+                .iter()
+                .copied()
+                .filter(|&i| self.words[i].last_reviewed_at == 0)
+                .min_by_key(|&i| self.frequency_rank(&self.words[i]).unwrap_or(u32::MAX));
+            if let Some(first_new) = first_new {
+                candidates.retain(|&i| i == first_new || self.words[i].last_reviewed_at != 0);
+            }
+        }
+
+        self.current_word_index =
+            self.scheduler()
+                .pick(&self.settings, &self.words, &candidates, now, &mut self.rng);
+        self.question_shown_at = Instant::now();
+    }
+
+    fn scheduler(&self) -> &'static dyn Scheduler {
+        self.deck_settings.scheduler.scheduler()
+    }
+
+    fn is_due(&self, word: &Word, now: u64) -> bool {
+        self.scheduler().is_due(&self.settings, word, now)
+    }
+
+    fn is_cloze_due(&self, word: &Word, now: u64) -> bool {
+        is_cloze_due(&self.settings, word, now)
+    }
+
+    fn due_count(&self) -> usize {
+        let now = now_secs();
+        self.words.iter().filter(|w| self.is_due(w, now)).count()
+    }
+
+    // Time until the next not-yet-due word becomes due, for scheduling a repaint
+    fn next_due_in(&self) -> Option<Duration> {
+        let now = now_secs();
+        self.words
+            .iter()
+            .filter(|w| !w.archived && w.last_reviewed_at != 0 && !self.is_due(w, now))
+            .map(|w| {
+                let interval =
+                    self.scheduler().interval_days(&self.settings, w) * SECS_PER_DAY as f32;
+                let due_at =
+                    due_override(w).unwrap_or_else(|| w.last_reviewed_at + interval.ceil() as u64);
+                due_at.saturating_sub(now)
+            })
+            .min()
+            .map(|secs| Duration::from_secs(secs.max(1)))
+    }
+
+    fn game_button_label(&self, label: &str) -> String {
+        match self.due_count() {
+            0 => label.to_string(),
+            due => format!("{} ({} due)", label, due),
+        }
+    }
+
+    // Returns whether the answer was right. A first miss may instead get a second try
+    // (Settings), which returns false without recording anything.
+    fn check_answer(&mut self) -> bool {
+        let idx = self.current_word_index;
+        let mode = &self.modes[self.mode_index];
+        let correct_translation = mode.expected(&self.words[idx]);
+
+        // A spelling drill takes the answer exactly as typed, none of the leniency below
+        let exact = mode.kind() == ReviewMode::Typing;
+
+        let (answer, correct, script_error) =
+            self.grade_answer(&self.user_answer, &correct_translation, exact, |answer| {
+                mode.grade(&self.words[idx], answer)
+            });
+        if let Some(e) = script_error {
+            self.grading_script_failed(e);
+        }
+        let variants = translation_variants(&correct_translation);
+        if !correct && self.second_chance_sentence().is_some() {
+            self.question = QuestionState::SecondChance;
+            self.feedback_message =
+                "❌ Not quite. Here it is in a sentence, try again:".to_string();
+            self.user_answer.clear();
+            return false;
+        }
+        let correct = self.record_answer(correct);
+
+        // Learn the alternatives too, not just the one that was typed
+        let typed = answer.trim().to_lowercase();
+        let others: Vec<&str> = variants
+            .iter()
+            .copied()
+            .filter(|v| v.to_lowercase() != typed)
+            .collect();
+        // The reading exercise expects two answers, not alternatives
+        let two_part = self.modes[self.mode_index].kind() == ReviewMode::Reading;
+        if correct && variants.len() > 1 && !two_part {
+            self.feedback_message
+                .push_str(&format!("\nAlso correct: {}", others.join(", ")));
+        }
+        correct
+    }
+
+    // The answer as the deck's grading script rewrites it, whether it counts, and the
+    // script's error if it failed. `grade` decides first; then the script's accept() and
+    // the deck's answer pattern get a say on each variant of `expected`, unless `exact`.
+    fn grade_answer(
+        &self,
+        answer: &str,
+        expected: &str,
+        exact: bool,
+        grade: impl Fn(&str) -> bool,
+    ) -> (String, bool, Option<String>) {
+        let script = self.grading_script.as_ref().filter(|_| !exact);
+        let mut script_error = None;
+        let mut answer = answer.to_string();
+        if let Some(script) = script {
+            match script.normalize(&answer) {
+                Ok(normalized) => answer = normalized,
+                Err(e) => script_error = Some(e),
+            }
+        }
+        let variants = translation_variants(expected);
+        let mut correct = grade(&answer);
+        if !correct && script_error.is_none() {
+            if let Some(script) = script {
+                for v in &variants {
+                    match script.accept(&answer, v) {
+                        Ok(false) => {}
+                        Ok(true) => {
+                            correct = true;
+                            break;
+                        }
+                        Err(e) => {
+                            script_error = Some(e);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        if !correct && !exact {
+            correct = variants
+                .iter()
+                .any(|v| self.deck_settings.accepts(&answer, v));
+        }
+        (answer, correct, script_error)
+    }
+
+    // A broken script would fail the same way on every answer; report it once and grade
+    // without it until the deck is opened again
+    fn grading_script_failed(&mut self, error: String) {
+        self.grading_script = None;
+        self.notify_error(format!("{} (script turned off for now)", error));
+    }
+
+    // The example sentence a miss on the current word can be retried with, if any
+    fn second_chance_sentence(&self) -> Option<String> {
+        if !self.settings.second_chance
+            || !matches!(
+                self.question,
+                QuestionState::Asking | QuestionState::SecondChance
+            )
+            || self.modes[self.mode_index].kind() != ReviewMode::Translate
+        {
+            return None;
+        }
+        second_chance_sentence(
+            &self.words[self.current_word_index],
+            self.question == QuestionState::SecondChance,
+        )
+    }
+
+    fn say(&mut self, text: &str, voice: Voice) {
+        if let Some(mut child) = self.speech.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        match speak(text, &voice) {
+            Ok(child) => self.speech = Some(child),
+            Err(e) => self.notify_error(e),
+        }
+    }
+
+    fn start_hands_free(&mut self) {
+        let mut hands_free = HandsFree {
+            step: HandsFreeStep::Asking,
+            since: Instant::now(),
+            speech: None,
+            voice: !self.settings.speech_recognition_command.trim().is_empty(),
+            listening: None,
+        };
+        let word = &self.words[self.current_word_index];
+        let said = hands_free.say(
+            HandsFreeStep::Asking,
+            &ruby_base(&word.foreign),
+            &self.deck_settings.foreign_voice(),
+        );
+        if let Err(e) = said {
+            self.notify_error(e);
+            return;
+        }
+        self.feedback_message.clear();
+        self.hands_free = Some(hands_free);
+    }
+
+    fn stop_hands_free(&mut self) {
+        if let Some(mut hands_free) = self.hands_free.take() {
+            hands_free.stop_speaking();
         }
+    }
 
-        ui.add_space(10.0);
-        egui::Grid::new("add_word_grid")
-            .num_columns(2)
-            .spacing([10.0, 8.0])
-            .show(ui, |ui| {
-                ui.label("Foreign word:");
-                ui.text_edit_singleline(&mut self.new_foreign);
-                ui.end_row();
+    // Moves hands-free review along: question, pause, answer, then waits for a grade
+    fn hands_free_tick(&mut self, ctx: &egui::Context) {
+        if self.hands_free.is_some() && self.screen != Screen::Game {
+            self.stop_hands_free();
+        }
+        let Some(hands_free) = &mut self.hands_free else {
+            return;
+        };
+        ctx.request_repaint_after(Duration::from_millis(100));
 
-                ui.label("Translation:");
-                let response = ui.text_edit_singleline(&mut self.new_translation);
-                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                    self.add_word();
-                }
-                ui.end_row();
-            });
+        let word = &self.words[self.current_word_index];
+        let speaking = hands_free.speaking();
+        let mut said = Ok(());
+        match hands_free.step {
+            HandsFreeStep::Asking if !speaking => {
+                hands_free.step = HandsFreeStep::Thinking;
+                hands_free.since = Instant::now();
+            }
+            HandsFreeStep::Thinking
+                if hands_free.since.elapsed() >= Duration::from_secs(HANDS_FREE_THINK_SECS) =>
+            {
+                said = hands_free.say(
+                    HandsFreeStep::Telling,
+                    &word.translation,
+                    &self.deck_settings.native_voice(),
+                );
+            }
+            HandsFreeStep::Telling if !speaking => hands_free.step = HandsFreeStep::Waiting,
+            _ => {}
+        }
+        if let Err(e) = said {
+            self.stop_hands_free();
+            self.notify_error(e);
+            return;
+        }
+        let Some(hands_free) = &mut self.hands_free else {
+            return;
+        };
 
-        ui.add_space(8.0);
-        if ui.button("➕ Add Word").clicked() {
-            self.add_word();
+        if !matches!(
+            hands_free.step,
+            HandsFreeStep::Telling | HandsFreeStep::Waiting
+        ) {
+            return;
+        }
+        let mut grade = ctx.input(|i| {
+            if i.key_pressed(egui::Key::Space) || i.key_pressed(egui::Key::Enter) {
+                Some(true)
+            } else if i.key_pressed(egui::Key::Backspace) {
+                Some(false)
+            } else {
+                None
+            }
+        });
+        let mut voice_error = None;
+        match hands_free.listening.as_ref().map(|rx| rx.try_recv()) {
+            Some(Ok(Ok(heard))) => {
+                hands_free.listening = None;
+                grade = grade.or(voice_command(&heard));
+            }
+            Some(Ok(Err(e))) => {
+                hands_free.listening = None;
+                hands_free.voice = false;
+                voice_error = Some(e);
+            }
+            Some(Err(mpsc::TryRecvError::Disconnected)) => hands_free.listening = None,
+            Some(Err(mpsc::TryRecvError::Empty)) | None => {}
         }
+        // Anything else heard just means listening again
+        if grade.is_none() && hands_free.step == HandsFreeStep::Waiting {
+            hands_free.listen(&self.settings.speech_recognition_command, ctx);
+        }
+        if let Some(e) = voice_error {
+            self.notify_error(format!(
+                "Error running the speech recognizer: {}; grade with the keys",
+                e
+            ));
+        }
+        let Some(knew_it) = grade else {
+            return;
+        };
 
-        if !self.words.is_empty() {
-            if ui.button("🎮 Go to Game").clicked() {
-                self.feedback_message.clear();
-                self.screen = Screen::Game;
-                self.pick_random_word();
+        self.record_answer(knew_it);
+        self.next_question();
+        // A session that ran out of words ends hands-free too
+        if self.screen != Screen::Game || self.session_pool().is_empty() {
+            self.stop_hands_free();
+            return;
+        }
+        if let Some(hands_free) = &mut self.hands_free {
+            let next = &self.words[self.current_word_index];
+            let said = hands_free.say(
+                HandsFreeStep::Asking,
+                &ruby_base(&next.foreign),
+                &self.deck_settings.foreign_voice(),
+            );
+            if let Err(e) = said {
+                self.stop_hands_free();
+                self.notify_error(e);
             }
         }
+    }
 
-        ui.separator();
-        ui.heading("Your Words");
+    // Applies a graded answer to the current word and logs it; the word stays current
+    fn record_answer(&mut self, correct: bool) -> bool {
+        let idx = self.current_word_index;
+        let response_ms = self.question_shown_at.elapsed().as_millis() as u64;
+        let mode = &self.modes[self.mode_index];
+        let correct_translation = mode.expected(&self.words[idx]);
+        let review_mode = mode.kind();
 
-        if self.words.is_empty() {
-            ui.label("No words yet. Add some words to start learning!");
+        if review_mode == ReviewMode::Speak {
+            return self.record_spoken_answer(idx, correct, response_ms);
+        }
+        if review_mode == ReviewMode::Cloze {
+            return self.record_cloze_answer(idx, correct, response_ms, &correct_translation);
+        }
+        if review_mode == ReviewMode::Article {
+            return self.record_article_answer(idx, correct, response_ms, &correct_translation);
+        }
+        if review_mode == ReviewMode::Typing {
+            return self.record_typing_answer(idx, correct, response_ms, &correct_translation);
+        }
+        if self.session == (SessionKind::Custom { counts: false }) {
+            self.push_session_answer(idx, correct, response_ms, self.words[idx].level);
+            return self.check_practice_answer(idx, correct, &correct_translation);
+        }
+        let old_level = self.words[idx].level;
+        let hinted = correct && self.question == QuestionState::SecondChance;
+        let learning = if hinted {
+            None
         } else {
-            egui::ScrollArea::vertical()
-                .max_height(320.0)
-                .show(ui, |ui| {
-                    let mut to_delete: Option<usize> = None;
+            self.learning_answer(idx, correct)
+        };
 
-                    for (i, word) in self.words.iter().enumerate() {
-                        ui.horizontal(|ui| {
-                            ui.label(format!("🔹 {} = {}", word.foreign, word.translation));
-                            ui.label(format!("(Level {})", word.level));
-                            if ui.button("🗑 Delete").clicked() {
-                                to_delete = Some(i);
-                            }
-                        });
-                    }
+        if hinted {
+            // Partial credit: it took the sentence, so the level neither rises nor falls
+            self.words[idx].correct_streak = 0;
+            self.feedback_message = format!("🟡 Right, with the hint. Level stays {}", old_level);
+        } else if let (true, Some(gap)) = (correct, learning) {
+            self.words[idx].correct_streak += 1;
+            self.feedback_message = format!("✅ CORRECT! Again in {} questions", gap);
+        } else if correct {
+            let scheduler = self.scheduler();
+            scheduler.record(&self.settings, &mut self.words[idx], true);
+            let w = &mut self.words[idx];
+            let needed = self.settings.answers_to_level_up.max(1);
+            if w.level > old_level {
+                if w.level == MAX_LEVEL {
+                    w.mastered_since = now_secs();
+                    w.mastered_streak = 0;
+                }
+                self.feedback_message = format!("✅ CORRECT! Level: {} → {}", old_level, w.level);
+            } else if old_level == MAX_LEVEL {
+                w.mastered_streak += 1;
+                self.feedback_message = "✅ CORRECT! Already mastered!".to_string();
+            } else if self.deck_settings.scheduler == SchedulerKind::Levels {
+                self.feedback_message = format!(
+                    "✅ CORRECT! {}/{} in a row for level {}",
+                    w.correct_streak,
+                    needed,
+                    w.level + 1
+                );
+            } else {
+                self.feedback_message = format!(
+                    "✅ CORRECT! Next review in {:.0} days",
+                    scheduler.interval_days(&self.settings, w)
+                );
+            }
+        } else {
+            self.scheduler()
+                .record(&self.settings, &mut self.words[idx], false);
+            let w = &mut self.words[idx];
+            w.mastered_streak = 0;
+            w.mastered_since = 0;
+            self.feedback_message = format!(
+                "❌ WRONG! Correct answer: {} (Level: {} → {})",
+                correct_translation, old_level, w.level
+            );
+            if let Some(gap) = learning {
+                self.feedback_message
+                    .push_str(&format!(" Again in {} questions.", gap));
+            }
+        }
 
-                    if let Some(index) = to_delete {
-                        self.words.remove(index);
-                        self.save();
-                    }
-                });
+        mark_reviewed(&mut self.words[idx], now_secs());
+
+        if self.session == SessionKind::Maintenance {
+            let id = self.words[idx].id;
+            self.maintenance_queue.retain(|&q| q != id);
+            if correct {
+                self.maintenance_kept += 1;
+            } else {
+                let w = &mut self.words[idx];
+                w.archived = false;
+                w.level = w.level.min(self.settings.maintenance_fail_level);
+                self.maintenance_returned += 1;
+                self.feedback_message = format!(
+                    "❌ WRONG! Correct answer: {} ↩ Back in rotation at level {}.",
+                    correct_translation, w.level
+                );
+            }
         }
-    }
 
-    fn game_screen(&mut self, ui: &mut egui::Ui) {
-        if self.words.is_empty() {
-            ui.heading("No words yet!");
-            ui.label("Go to 'Add Words' and add some words first.");
-            return;
+        if let SessionKind::Custom { .. } = self.session {
+            let id = self.words[idx].id;
+            self.custom_queue.retain(|&q| q != id);
+            if correct {
+                self.custom_right += 1;
+            } else {
+                self.custom_wrong += 1;
+            }
         }
 
-        ui.heading("🎮 Game Mode");
-        ui.add_space(6.0);
+        if self.settings.auto_archive && self.has_stayed_mastered(&self.words[idx]) {
+            self.words[idx].archived = true;
+            self.feedback_message.push_str(" 📦 Moved to the archive.");
+        }
 
-        // Progress
-        let mastered = self.words.iter().filter(|w| w.level >= 5).count();
-        let total = self.words.len();
-        let progress = mastered as f32 / (total as f32).max(1.0);
-        ui.horizontal(|ui| {
-            ui.label("Mastery:");
-            ui.add(
-                egui::ProgressBar::new(progress)
-                    .text(format!("{}/{} mastered", mastered, total))
-                    .desired_width(220.0),
-            );
+        // Log the answer before saving, so the log is never behind the save
+        self.append_review(ReviewEvent {
+            at: now_secs(),
+            session: self.session_id,
+            foreign: self.words[idx].foreign.clone(),
+            correct,
+            word_id: self.words[idx].id,
+            mode: review_mode,
+            response_ms,
+            level: self.words[idx].level,
         });
+        self.record_answer_stats(correct);
+        self.push_session_answer(idx, correct, response_ms, old_level);
 
-        ui.separator();
-        ui.add_space(10.0);
+        // Save persistent progress; the journal covers the gap until the debounced write
+        self.journal_answer(idx);
+        self.mark_dirty();
 
-        let word = &self.words[self.current_word_index];
-        ui.label("What is the translation of this word?");
-        ui.label(
-            egui::RichText::new(&word.foreign)
-                .size(48.0)
-                .strong(),
-        );
-        ui.label(format!("Level: {}", word.level));
+        self.question = QuestionState::ShowingResult;
+        correct
+    }
 
-        ui.add_space(12.0);
-        ui.label("Your answer:");
-        let response = ui.text_edit_singleline(&mut self.user_answer);
+    // Leaves the answered word for another one, or for the end screen once all are mastered
+    fn next_question(&mut self) {
+        self.pick_random_word();
 
-        // Clear feedback on input change
-        if response.changed() {
+        // A maintenance review ends on its own summary instead
+        let ends_on_summary = matches!(
+            self.session,
+            SessionKind::Maintenance | SessionKind::Stale | SessionKind::Custom { .. }
+        );
+        if !ends_on_summary && self.all_words_mastered() {
+            self.screen = Screen::End;
             self.feedback_message.clear();
         }
 
-        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-            self.check_answer();
-        }
+        self.user_answer.clear();
+    }
 
-        if ui.button("✓ Check").clicked() {
-            self.check_answer();
+    // Moves on from a graded answer right away, unless Settings asks to wait for Enter
+    fn step_question(&mut self) {
+        if self.question == QuestionState::ShowingResult && !self.settings.wait_after_answer {
+            self.question = QuestionState::Next;
         }
+        if self.question == QuestionState::Next {
+            self.next_question();
+        }
+    }
 
-        ui.add_space(10.0);
-        if !self.feedback_message.is_empty() {
-            ui.label(&self.feedback_message);
+    // Moves a new word through the learning steps. Returns how many questions until it
+    // comes back, or None once it's graduated (or was never new) and levels as usual.
+    fn learning_answer(&mut self, idx: usize, correct: bool) -> Option<u32> {
+        let id = self.words[idx].id;
+        let current = self.learning.get(&id).map(|l| l.step);
+        if current.is_none() && self.words[idx].last_reviewed_at != 0 {
+            return None;
         }
+        // A wrong answer starts the steps over
+        let next = if correct { current.unwrap_or(0) } else { 0 };
+        let Some(&gap) = self.settings.learning_steps.get(next) else {
+            self.learning.remove(&id);
+            return None;
+        };
+        self.learning.insert(
+            id,
+            LearningStep {
+                step: next + 1,
+                // `gap` other questions in between
+                due_at: self.questions_asked + u64::from(gap) + 1,
+            },
+        );
+        Some(gap)
     }
 
-    fn end_screen(&mut self, ui: &mut egui::Ui) {
-        ui.vertical_centered(|ui| {
-            ui.add_space(40.0);
-            ui.label(egui::RichText::new("🎉🎉🎉").size(72.0));
-            ui.add_space(12.0);
+    fn push_session_answer(
+        &mut self,
+        idx: usize,
+        correct: bool,
+        response_ms: u64,
+        level_before: u8,
+    ) {
+        let word = &self.words[idx];
+        self.session_answers.push(SessionAnswer {
+            word_id: word.id,
+            foreign: word.foreign.clone(),
+            correct,
+            response_ms,
+            level_before,
+            level_after: word.level,
+        });
+    }
 
-            ui.label(
-                egui::RichText::new("CONGRATULATIONS!")
-                    .size(40.0)
-                    .strong()
-                    .color(egui::Color32::GOLD),
-            );
-            ui.add_space(8.0);
-            ui.label(
-                egui::RichText::new("ALL WORDS MASTERED!")
-                    .size(26.0)
-                    .color(egui::Color32::GREEN),
-            );
+    // Accuracy, time, fastest/slowest words and level changes of the run just finished
+    fn session_summary(&self, ui: &mut egui::Ui) {
+        let answers = &self.session_answers;
+        if answers.is_empty() {
+            return;
+        }
 
-            ui.add_space(20.0);
-            ui.label(
-                egui::RichText::new(format!("You mastered {} words!", self.words.len()))
-                    .size(18.0),
-            );
+        let right = answers.iter().filter(|a| a.correct).count();
+        let total_ms: u64 = answers.iter().map(|a| a.response_ms).sum();
+        ui.label(format!(
+            "🎯 {}/{} right ({:.0}%) · ⏱ {} answering",
+            right,
+            answers.len(),
+            right as f32 * 100.0 / answers.len() as f32,
+            format_duration(total_ms / 1000)
+        ));
 
-            ui.add_space(30.0);
-            if ui.button(egui::RichText::new("🔄 Play Again").size(18.0)).clicked() {
-                for w in &mut self.words {
-                    w.level = 1;
-                }
-                self.save();
-                self.screen = Screen::Game;
-                self.pick_random_word();
-            }
+        let mut by_time: Vec<&SessionAnswer> = answers.iter().collect();
+        by_time.sort_by_key(|a| a.response_ms);
+        let list = |picked: &[&SessionAnswer]| {
+            picked
+                .iter()
+                .map(|a| format!("{} ({:.1}s)", a.foreign, a.response_ms as f32 / 1000.0))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let shown = by_time.len().min(3);
+        ui.label(format!("⚡ Fastest: {}", list(&by_time[..shown])));
+        let slowest: Vec<&SessionAnswer> = by_time.iter().rev().take(shown).copied().collect();
+        ui.label(format!("🐢 Slowest: {}", list(&slowest)));
 
-            if ui.button(egui::RichText::new("➕ Add More Words").size(18.0)).clicked() {
-                self.screen = Screen::AddWords;
+        // First level seen in the run against the last, per word
+        let mut changes: Vec<(&str, u8, u8)> = Vec::new();
+        let mut seen = HashSet::new();
+        for first in answers.iter().filter(|a| seen.insert(a.word_id)) {
+            let last = answers.iter().rev().find(|a| a.word_id == first.word_id);
+            let after = last.map_or(first.level_after, |a| a.level_after);
+            if after != first.level_before {
+                changes.push((&first.foreign, first.level_before, after));
             }
-
-            ui.add_space(10.0);
-            ui.label("Thanks for using woro!");
-        });
+        }
+        if changes.is_empty() {
+            ui.label("No level changes.");
+            return;
+        }
+        let up = changes.iter().filter(|(_, from, to)| to > from).count();
+        ui.collapsing(
+            format!("📶 {} words up, {} down", up, changes.len() - up),
+            |ui| {
+                for (foreign, from, to) in &changes {
+                    ui.label(format!("{}: {} → {}", foreign, from, to));
+                }
+            },
+        );
     }
-}
 
-// ------------------- Core logic -------------------
-impl App {
-    fn add_word(&mut self) {
-        if !self.new_foreign.trim().is_empty() && !self.new_translation.trim().is_empty() {
-            self.words.push(Word::new(
-                self.new_foreign.trim().to_string(),
-                self.new_translation.trim().to_string(),
-            ));
-            self.new_foreign.clear();
-            self.new_translation.clear();
-            self.save();
+    // Speaking moves the word's speaking level only; the schedule and level stay put
+    fn record_spoken_answer(&mut self, idx: usize, correct: bool, response_ms: u64) -> bool {
+        let counts = self.session != (SessionKind::Custom { counts: false });
+        let w = &mut self.words[idx];
+        let old_level = w.speaking_level;
+        if counts {
+            w.speaking_level = if correct {
+                (w.speaking_level + 1).min(MAX_LEVEL)
+            } else {
+                w.speaking_level.saturating_sub(1).max(1)
+            };
         }
+        self.feedback_message = format!(
+            "{} Heard \"{}\" (speaking level {} → {})",
+            if correct { "✅" } else { "❌" },
+            self.user_answer,
+            old_level,
+            w.speaking_level
+        );
+
+        let level = w.speaking_level;
+        self.finish_side_answer(idx, correct, ReviewMode::Speak, response_ms, level)
     }
 
-    fn import_from_txt(&mut self) {
-        if let Some(path) = rfd::FileDialog::new()
-            .add_filter("Text Files", &["txt"])
-            .set_title("Select word list")
-            .pick_file()
-        {
-            match fs::read_to_string(path) {
-                Ok(content) => self.parse_txt_content(&content),
-                Err(e) => eprintln!("Error reading file: {}", e),
-            }
+    // Moves the cloze card only; the word's own level and schedule stay put
+    fn record_cloze_answer(
+        &mut self,
+        idx: usize,
+        correct: bool,
+        response_ms: u64,
+        expected: &str,
+    ) -> bool {
+        let counts = self.session != (SessionKind::Custom { counts: false });
+        let Some(card) = self.words[idx].cloze.as_mut() else {
+            return correct;
+        };
+        let old_level = card.level;
+        if counts {
+            card.level = if correct {
+                (card.level + 1).min(MAX_LEVEL)
+            } else {
+                card.level.saturating_sub(1).max(1)
+            };
+            card.last_reviewed_at = now_secs();
         }
+        self.feedback_message = if correct {
+            format!("✅ CORRECT! Cloze level: {} → {}", old_level, card.level)
+        } else {
+            format!(
+                "❌ WRONG! Correct answer: {} (Cloze level: {} → {})",
+                expected, old_level, card.level
+            )
+        };
+        let level = card.level;
+        self.finish_side_answer(idx, correct, ReviewMode::Cloze, response_ms, level)
     }
 
-    fn parse_txt_content(&mut self, content: &str) {
-        let mut added = 0usize;
-        let mut skipped = 0usize;
+    // Gender is tracked by its own accuracy stat only; levels stay put
+    fn record_article_answer(
+        &mut self,
+        idx: usize,
+        correct: bool,
+        response_ms: u64,
+        expected: &str,
+    ) -> bool {
+        self.feedback_message = if correct {
+            format!("✅ {} {}", expected, self.words[idx].foreign)
+        } else {
+            format!("❌ WRONG! It's {} {}", expected, self.words[idx].foreign)
+        };
+        let level = self.words[idx].level;
+        self.finish_side_answer(idx, correct, ReviewMode::Article, response_ms, level)
+    }
 
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
+    // Spelling is practice on the side; levels stay put
+    fn record_typing_answer(
+        &mut self,
+        idx: usize,
+        correct: bool,
+        response_ms: u64,
+        expected: &str,
+    ) -> bool {
+        self.feedback_message = if correct {
+            "✅ Spelled right!".to_string()
+        } else {
+            format!("❌ It's spelled {}", expected)
+        };
+        let level = self.words[idx].level;
+        self.finish_side_answer(idx, correct, ReviewMode::Typing, response_ms, level)
+    }
 
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                let foreign = parts[0].to_string();
-                let translation = parts[1..].join(" ");
-                self.words.push(Word::new(foreign, translation));
-                added += 1;
+    // Logs an answer that didn't go through the word's own level
+    fn finish_side_answer(
+        &mut self,
+        idx: usize,
+        correct: bool,
+        mode: ReviewMode,
+        response_ms: u64,
+        level: u8,
+    ) -> bool {
+        if self.session != (SessionKind::Custom { counts: false }) {
+            self.append_review(ReviewEvent {
+                at: now_secs(),
+                session: self.session_id,
+                foreign: self.words[idx].foreign.clone(),
+                correct,
+                word_id: self.words[idx].id,
+                mode,
+                response_ms,
+                level,
+            });
+            self.journal_answer(idx);
+            self.mark_dirty();
+        }
+        if let SessionKind::Custom { .. } = self.session {
+            let id = self.words[idx].id;
+            self.custom_queue.retain(|&q| q != id);
+            if correct {
+                self.custom_right += 1;
             } else {
-                skipped += 1;
+                self.custom_wrong += 1;
             }
         }
+        self.question = QuestionState::ShowingResult;
+        correct
+    }
 
-        if added > 0 {
-            self.save();
+    // Gives every word with an example sentence in its notes a cloze card
+    fn make_cloze_cards(&mut self) {
+        let mut made = 0;
+        for word in self.words.iter_mut().filter(|w| w.cloze.is_none()) {
+            if let Some(sentence) = cloze_sentence(word) {
+                word.cloze = Some(ClozeCard {
+                    sentence,
+                    level: 1,
+                    last_reviewed_at: 0,
+                });
+                made += 1;
+            }
+        }
+        self.notify(format!("🧩 Made {} cloze cards", made));
+        if made > 0 {
+            self.mark_dirty();
+            self.rebuild_modes();
         }
-        println!("✅ Added {} words, skipped {} invalid lines", added, skipped);
     }
 
-    fn pick_random_word(&mut self) {
-        if self.words.is_empty() {
+    // A custom session that doesn't count: nothing about the word changes or gets logged
+    fn check_practice_answer(&mut self, idx: usize, correct: bool, expected: &str) -> bool {
+        let id = self.words[idx].id;
+        self.custom_queue.retain(|&q| q != id);
+        if correct {
+            self.custom_right += 1;
+            self.feedback_message = "✅ CORRECT!".to_string();
+        } else {
+            self.custom_wrong += 1;
+            self.feedback_message = format!("❌ WRONG! Correct answer: {}", expected);
+        }
+        self.question = QuestionState::ShowingResult;
+        correct
+    }
+
+    fn handle_api_requests(&mut self) {
+        let Some(requests) = &self.api_requests else {
             return;
+        };
+        let pending: Vec<ApiRequest> = requests.try_iter().collect();
+        for request in pending {
+            let (status, body) = self.api_response(&request.method, &request.path, &request.body);
+            let _ = request.reply.send((status, body.to_string()));
         }
-        let mut rng = rand::thread_rng();
-        self.current_word_index = rng.gen_range(0..self.words.len());
     }
 
-    fn check_answer(&mut self) {
-        let idx = self.current_word_index;
-        let correct_translation = self.words[idx].translation.clone();
-        let old_level = self.words[idx].level;
-        let user = self.user_answer.trim().to_lowercase();
-        let right = correct_translation.to_lowercase();
+    //   GET  /        a translate-only quiz page on the endpoints below
+    //   GET  /deck    all words with their levels
+    //   GET  /due     the next word to ask, or null when nothing is due
+    //   POST /answer  {"id": 3, "answer": "house"} -> whether it was right and the new level
+    //   GET  /reviews the review log
+    // The API quizzes on its own; the game in the window carries on undisturbed.
+    fn api_response(&mut self, method: &str, path: &str, body: &str) -> (u16, serde_json::Value) {
+        use serde_json::json;
 
-        if user == right {
-            let w = &mut self.words[idx];
-            if w.level < 5 {
-                w.level += 1;
-                self.feedback_message =
-                    format!("✅ CORRECT! Level: {} → {}", old_level, w.level);
-            } else {
-                self.feedback_message = "✅ CORRECT! Already mastered!".to_string();
+        match (method, path.trim_end_matches('/')) {
+            ("GET", "/deck") => {
+                let words: Vec<_> = self
+                    .words
+                    .iter()
+                    .map(|w| {
+                        json!({
+                            "id": w.id,
+                            "foreign": w.foreign,
+                            "translation": w.translation,
+                            "level": w.level,
+                            "tags": w.tags,
+                            "archived": w.archived,
+                        })
+                    })
+                    .collect();
+                (
+                    200,
+                    json!({ "deck": deck_name(&self.deck_path), "words": words }),
+                )
             }
-        } else {
-            let w = &mut self.words[idx];
-            if w.level > 1 {
-                w.level -= 1;
+            ("GET", "/due") => {
+                let now = now_secs();
+                let next = self
+                    .words
+                    .iter()
+                    .filter(|w| self.is_due(w, now))
+                    .min_by_key(|w| w.last_reviewed_at);
+                self.api_quiz.asked = next.map(|w| (w.id, Instant::now()));
+                let card =
+                    next.map(|w| json!({ "id": w.id, "foreign": w.foreign, "level": w.level }));
+                (200, json!({ "card": card, "due": self.due_count() }))
             }
-            self.feedback_message = format!(
-                "❌ WRONG! Correct answer: {} (Level: {} → {})",
-                correct_translation, old_level, w.level
-            );
+            ("POST", "/answer") => {
+                let answer: ApiAnswer = match serde_json::from_str(body) {
+                    Ok(answer) => answer,
+                    Err(e) => return (400, json!({ "error": e.to_string() })),
+                };
+                let Some(idx) = self.words.iter().position(|w| w.id == answer.id) else {
+                    return (404, json!({ "error": "no word with that id" }));
+                };
+                let expected = self.words[idx].translation.clone();
+                let correct = self.record_api_answer(idx, &answer.answer);
+                (
+                    200,
+                    json!({
+                        "correct": correct,
+                        "expected": expected,
+                        "level": self.words[idx].level,
+                    }),
+                )
+            }
+            ("GET", "/reviews") => (200, json!(self.reviews)),
+            _ => (404, json!({ "error": "unknown endpoint" })),
         }
+    }
 
-        // Save persistent progress
-        self.save();
+    // Grades a translation sent to the API and records it like a game answer, leaving the
+    // game's own question, session and stats alone
+    fn record_api_answer(&mut self, idx: usize, answer: &str) -> bool {
+        let now = now_secs();
+        if self.api_quiz.session == 0 {
+            self.api_quiz.session = now;
+        }
+        let id = self.words[idx].id;
+        let response_ms = match self.api_quiz.asked {
+            Some((asked, at)) if asked == id => {
+                self.api_quiz.asked = None;
+                at.elapsed().as_millis() as u64
+            }
+            _ => 0,
+        };
 
-        // Move to next word
-        self.pick_random_word();
+        let expected = self.words[idx].translation.clone();
+        let (_, correct, script_error) = self.grade_answer(answer, &expected, false, |answer| {
+            let answer = answer.trim().to_lowercase();
+            answer == expected.to_lowercase()
+                || translation_variants(&expected)
+                    .iter()
+                    .any(|v| v.to_lowercase() == answer)
+        });
+        if let Some(e) = script_error {
+            self.grading_script_failed(e);
+        }
 
-        // All mastered?
-        if self.all_words_mastered() {
-            self.screen = Screen::End;
-            self.feedback_message.clear();
+        let old_level = self.words[idx].level;
+        self.scheduler()
+            .record(&self.settings, &mut self.words[idx], correct);
+        let w = &mut self.words[idx];
+        if !correct {
+            w.mastered_streak = 0;
+            w.mastered_since = 0;
+        } else if w.level > old_level && w.level == MAX_LEVEL {
+            w.mastered_since = now;
+            w.mastered_streak = 0;
+        } else if old_level == MAX_LEVEL {
+            w.mastered_streak += 1;
+        }
+        mark_reviewed(w, now);
+        if self.settings.auto_archive && self.has_stayed_mastered(&self.words[idx]) {
+            self.words[idx].archived = true;
         }
 
-        self.user_answer.clear();
+        self.append_review(ReviewEvent {
+            at: now,
+            session: self.api_quiz.session,
+            foreign: self.words[idx].foreign.clone(),
+            correct,
+            word_id: id,
+            mode: ReviewMode::Translate,
+            response_ms,
+            level: self.words[idx].level,
+        });
+        self.count_review(correct, (response_ms / 1000).min(MAX_THINK_SECS));
+        self.journal_answer(idx);
+        self.mark_dirty();
+        correct
+    }
+
+    fn has_stayed_mastered(&self, word: &Word) -> bool {
+        let min_secs = self.settings.auto_archive_days as u64 * SECS_PER_DAY;
+        word.level >= MAX_LEVEL
+            && word.mastered_since != 0
+            && word.mastered_streak >= self.settings.auto_archive_reviews
+            && now_secs().saturating_sub(word.mastered_since) >= min_secs
     }
 
+    // An empty pool means every word of the session has been archived, which counts too
     fn all_words_mastered(&self) -> bool {
-        !self.words.is_empty() && self.words.iter().all(|w| w.level >= 5)
+        let pool = self.session_pool();
+        !self.words.is_empty() && pool.iter().all(|&i| self.words[i].level >= MAX_LEVEL)
     }
 }